@@ -1,7 +1,11 @@
 // Shared types and utilities for XRPL Financial Primitives on CosmWasm
 
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{Addr, StdError, StdResult, Uint128};
+use sha2::{Digest, Sha256};
+
+/// Basis points in 100%
+pub const BPS_DENOMINATOR: u64 = 10_000;
 
 #[cw_serde]
 pub struct TokenInfo {
@@ -17,6 +21,102 @@ pub enum Status {
     Cancelled,
 }
 
+/// Release condition for escrow-style funds
+#[cw_serde]
+pub enum Condition {
+    None,
+    /// Requires a preimage whose sha256 equals `hash`
+    HashLock {
+        hash: [u8; 32],
+    },
+    /// Unlocks once `release_time` (seconds) is reached
+    TimeLock {
+        release_time: u64,
+    },
+    /// Requires both the preimage and the release time
+    Combined {
+        hash: [u8; 32],
+        release_time: u64,
+    },
+}
+
+impl Condition {
+    /// Check whether the condition is met at `current_time` with the given preimage
+    pub fn is_satisfied(&self, current_time: u64, preimage: Option<&[u8]>) -> bool {
+        let hash_ok = |hash: &[u8; 32]| preimage.is_some_and(|p| verify_hashlock(p, hash));
+        match self {
+            Condition::None => true,
+            Condition::HashLock { hash } => hash_ok(hash),
+            Condition::TimeLock { release_time } => current_time >= *release_time,
+            Condition::Combined { hash, release_time } => {
+                current_time >= *release_time && hash_ok(hash)
+            }
+        }
+    }
+}
+
+/// KYC tier bounding what an authorization may ever allow
+#[cw_serde]
+pub enum AuthTier {
+    Basic,
+    Standard,
+    Premium,
+    Institutional,
+}
+
+impl AuthTier {
+    /// Tier ceiling in micro-units (6 decimals): 1, 10, 100 and 1000 tokens
+    pub fn max_amount(&self) -> Uint128 {
+        match self {
+            AuthTier::Basic => Uint128::new(1_000_000),
+            AuthTier::Standard => Uint128::new(10_000_000),
+            AuthTier::Premium => Uint128::new(100_000_000),
+            AuthTier::Institutional => Uint128::new(1_000_000_000),
+        }
+    }
+}
+
+/// Deposit authorization with a spend ceiling and optional expiry
+#[cw_serde]
+pub struct Authorization {
+    pub authorizer: Addr,
+    pub authorized: Addr,
+    pub denom: String,
+    pub max_amount: Uint128,
+    pub used_amount: Uint128,
+    /// Expiry in seconds; 0 = never expires
+    pub expiration: u64,
+    pub tier: AuthTier,
+}
+
+impl Authorization {
+    /// Effective ceiling: the lower of `max_amount` and the tier ceiling
+    pub fn ceiling(&self) -> Uint128 {
+        self.max_amount.min(self.tier.max_amount())
+    }
+
+    /// Check the authorization is unexpired and not yet used up
+    pub fn is_active(&self, now: u64) -> bool {
+        !is_expired(now, self.expiration) && self.used_amount < self.ceiling()
+    }
+
+    /// How much of `amount` still fits under the ceiling
+    pub fn remaining(&self, amount: Uint128) -> Uint128 {
+        amount.min(self.ceiling().saturating_sub(self.used_amount))
+    }
+
+    /// Record `amount` as used, failing if it exceeds what remains
+    pub fn consume(&self, amount: Uint128) -> StdResult<Self> {
+        if self.remaining(amount) < amount {
+            return Err(StdError::generic_err("Amount exceeds authorization"));
+        }
+        Ok(Self {
+            used_amount: self.used_amount.checked_add(amount)?,
+            ..self.clone()
+        })
+    }
+}
+
 // Common helper functions
 
 /// Calculate percentage with precision
@@ -29,6 +129,23 @@ pub fn is_expired(current_time: u64, expiry: u64) -> bool {
     expiry > 0 && current_time >= expiry
 }
 
+/// Split `total` into `(net, fee)` for a fee in basis points, rounding the fee down.
+/// Fees above 100% are capped at `total`.
+pub fn split_amount(total: Uint128, fee_bps: u64) -> (Uint128, Uint128) {
+    let fee = total.multiply_ratio(fee_bps.min(BPS_DENOMINATOR), BPS_DENOMINATOR);
+    (total - fee, fee)
+}
+
+/// Subtract, returning an error instead of panicking on underflow
+pub fn safe_sub(a: Uint128, b: Uint128) -> StdResult<Uint128> {
+    Ok(a.checked_sub(b)?)
+}
+
+/// Check that sha256(preimage) matches the expected hash lock
+pub fn verify_hashlock(preimage: &[u8], expected: &[u8; 32]) -> bool {
+    Sha256::digest(preimage).as_slice() == expected
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -46,4 +163,122 @@ mod tests {
         assert!(!is_expired(50, 100));
         assert!(!is_expired(100, 0)); // 0 = never expires
     }
+
+    fn hash_of(preimage: &[u8]) -> [u8; 32] {
+        Sha256::digest(preimage).into()
+    }
+
+    #[test]
+    fn test_verify_hashlock() {
+        let hash = hash_of(b"secret");
+        assert!(verify_hashlock(b"secret", &hash));
+        assert!(!verify_hashlock(b"wrong", &hash));
+        assert!(!verify_hashlock(b"", &hash));
+    }
+
+    #[test]
+    fn test_condition_is_satisfied() {
+        let hash = hash_of(b"secret");
+        assert!(Condition::None.is_satisfied(0, None));
+
+        let lock = Condition::HashLock { hash };
+        assert!(lock.is_satisfied(0, Some(b"secret")));
+        assert!(!lock.is_satisfied(0, Some(b"wrong")));
+        assert!(!lock.is_satisfied(0, None));
+
+        let timed = Condition::TimeLock { release_time: 100 };
+        assert!(!timed.is_satisfied(99, None));
+        assert!(timed.is_satisfied(100, None));
+
+        let combined = Condition::Combined {
+            hash,
+            release_time: 100,
+        };
+        assert!(!combined.is_satisfied(99, Some(b"secret")));
+        assert!(!combined.is_satisfied(100, Some(b"wrong")));
+        assert!(!combined.is_satisfied(100, None));
+        assert!(combined.is_satisfied(100, Some(b"secret")));
+    }
+
+    #[test]
+    fn test_split_amount() {
+        // 0.3% of 1000 is 3
+        assert_eq!(
+            split_amount(Uint128::new(1000), 30),
+            (Uint128::new(997), Uint128::new(3))
+        );
+        // 0.3% of 999 is 2.997, rounded down
+        assert_eq!(
+            split_amount(Uint128::new(999), 30),
+            (Uint128::new(997), Uint128::new(2))
+        );
+        assert_eq!(
+            split_amount(Uint128::new(1000), 0),
+            (Uint128::new(1000), Uint128::zero())
+        );
+        assert_eq!(
+            split_amount(Uint128::new(1000), 20_000),
+            (Uint128::zero(), Uint128::new(1000))
+        );
+    }
+
+    #[test]
+    fn test_safe_sub() {
+        assert_eq!(
+            safe_sub(Uint128::new(10), Uint128::new(4)).unwrap(),
+            Uint128::new(6)
+        );
+        assert_eq!(
+            safe_sub(Uint128::new(4), Uint128::new(4)).unwrap(),
+            Uint128::zero()
+        );
+        assert!(safe_sub(Uint128::new(4), Uint128::new(10)).is_err());
+    }
+
+    fn authorization(max_amount: u128, tier: AuthTier, expiration: u64) -> Authorization {
+        Authorization {
+            authorizer: Addr::unchecked("authorizer"),
+            authorized: Addr::unchecked("authorized"),
+            denom: "uatom".to_string(),
+            max_amount: Uint128::new(max_amount),
+            used_amount: Uint128::zero(),
+            expiration,
+            tier,
+        }
+    }
+
+    #[test]
+    fn test_authorization_expiry() {
+        let auth = authorization(500_000, AuthTier::Basic, 100);
+        assert!(auth.is_active(99));
+        assert!(!auth.is_active(100));
+        assert!(authorization(500_000, AuthTier::Basic, 0).is_active(u64::MAX));
+    }
+
+    #[test]
+    fn test_authorization_tier_ceiling() {
+        // Basic caps a 5 token authorization at 1 token
+        let auth = authorization(5_000_000, AuthTier::Basic, 0);
+        assert_eq!(auth.ceiling(), Uint128::new(1_000_000));
+        assert_eq!(
+            auth.remaining(Uint128::new(2_000_000)),
+            Uint128::new(1_000_000)
+        );
+        assert!(auth.consume(Uint128::new(1_000_001)).is_err());
+
+        let auth = authorization(5_000_000, AuthTier::Standard, 0);
+        assert_eq!(auth.ceiling(), Uint128::new(5_000_000));
+    }
+
+    #[test]
+    fn test_authorization_consume() {
+        let auth = authorization(1_000, AuthTier::Basic, 0);
+        let auth = auth.consume(Uint128::new(600)).unwrap();
+        assert_eq!(auth.used_amount, Uint128::new(600));
+        assert_eq!(auth.remaining(Uint128::new(1_000)), Uint128::new(400));
+        assert!(auth.consume(Uint128::new(401)).is_err());
+
+        let auth = auth.consume(Uint128::new(400)).unwrap();
+        assert!(!auth.is_active(0));
+    }
 }
@@ -3,7 +3,8 @@ use near_sdk::collections::UnorderedMap;
 use near_sdk::{env, near_bindgen, AccountId, Balance, PanicOnDefault, Promise};
 use serde::{Deserialize, Serialize};
 
-const GRACE_PERIOD_NS: u64 = 86_400_000_000_000; // 24 hours in nanoseconds
+const MIN_GRACE_PERIOD_NS: u64 = 60_000_000_000; // 1 minute in nanoseconds
+const MAX_GRACE_PERIOD_NS: u64 = 90 * 86_400_000_000_000; // 90 days in nanoseconds
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
@@ -28,14 +29,20 @@ pub struct Account {
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct AccountDeleteContract {
     accounts: UnorderedMap<AccountId, Account>,
+    grace_period_ns: u64,
 }
 
 #[near_bindgen]
 impl AccountDeleteContract {
     #[init]
-    pub fn new() -> Self {
+    pub fn new(grace_period_ns: u64) -> Self {
+        assert!(
+            (MIN_GRACE_PERIOD_NS..=MAX_GRACE_PERIOD_NS).contains(&grace_period_ns),
+            "Grace period out of bounds"
+        );
         Self {
             accounts: UnorderedMap::new(b"a"),
+            grace_period_ns,
         }
     }
 
@@ -113,7 +120,7 @@ impl AccountDeleteContract {
         );
 
         let elapsed = env::block_timestamp() - account.deletion_request_time;
-        assert!(elapsed >= GRACE_PERIOD_NS, "Grace period not elapsed");
+        assert!(elapsed >= self.grace_period_ns, "Grace period not elapsed");
 
         let beneficiary = account.beneficiary.clone().expect("No beneficiary set");
         let balance = account.balance;
@@ -138,7 +145,7 @@ impl AccountDeleteContract {
         if let Some(account) = self.accounts.get(&account_id) {
             if account.status == AccountStatus::PendingDeletion {
                 let elapsed = env::block_timestamp() - account.deletion_request_time;
-                elapsed >= GRACE_PERIOD_NS
+                elapsed >= self.grace_period_ns
             } else {
                 false
             }
@@ -147,14 +154,18 @@ impl AccountDeleteContract {
         }
     }
 
+    pub fn get_grace_period(&self) -> u64 {
+        self.grace_period_ns
+    }
+
     pub fn get_time_until_deletion(&self, account_id: AccountId) -> u64 {
         if let Some(account) = self.accounts.get(&account_id) {
             if account.status == AccountStatus::PendingDeletion {
                 let elapsed = env::block_timestamp() - account.deletion_request_time;
-                if elapsed >= GRACE_PERIOD_NS {
+                if elapsed >= self.grace_period_ns {
                     0
                 } else {
-                    GRACE_PERIOD_NS - elapsed
+                    self.grace_period_ns - elapsed
                 }
             } else {
                 0
@@ -164,3 +175,86 @@ impl AccountDeleteContract {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const GRACE_PERIOD_NS: u64 = 10 * MIN_GRACE_PERIOD_NS;
+    const REQUESTED_AT: u64 = 1_000_000_000;
+
+    fn context(predecessor: AccountId, timestamp: u64) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .predecessor_account_id(predecessor)
+            .block_timestamp(timestamp);
+        builder
+    }
+
+    /// `accounts(0)` opens an account and requests deletion to `accounts(1)`
+    /// at `REQUESTED_AT` under a `GRACE_PERIOD_NS` grace period
+    fn setup() -> AccountDeleteContract {
+        testing_env!(context(accounts(0), REQUESTED_AT).build());
+        let mut contract = AccountDeleteContract::new(GRACE_PERIOD_NS);
+        contract.create_account();
+        contract.request_deletion(accounts(1));
+        contract
+    }
+
+    #[test]
+    fn new_accepts_bounds() {
+        testing_env!(context(accounts(0), 0).build());
+        let contract = AccountDeleteContract::new(MIN_GRACE_PERIOD_NS);
+        assert_eq!(contract.get_grace_period(), MIN_GRACE_PERIOD_NS);
+        let contract = AccountDeleteContract::new(MAX_GRACE_PERIOD_NS);
+        assert_eq!(contract.get_grace_period(), MAX_GRACE_PERIOD_NS);
+    }
+
+    #[test]
+    #[should_panic(expected = "Grace period out of bounds")]
+    fn new_rejects_period_below_minimum() {
+        testing_env!(context(accounts(0), 0).build());
+        AccountDeleteContract::new(MIN_GRACE_PERIOD_NS - 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Grace period out of bounds")]
+    fn new_rejects_period_above_maximum() {
+        testing_env!(context(accounts(0), 0).build());
+        AccountDeleteContract::new(MAX_GRACE_PERIOD_NS + 1);
+    }
+
+    #[test]
+    fn can_delete_honors_custom_period() {
+        let contract = setup();
+
+        testing_env!(context(accounts(2), REQUESTED_AT + GRACE_PERIOD_NS - 1).build());
+        assert!(!contract.can_delete(accounts(0)));
+        assert_eq!(contract.get_time_until_deletion(accounts(0)), 1);
+
+        testing_env!(context(accounts(2), REQUESTED_AT + GRACE_PERIOD_NS).build());
+        assert!(contract.can_delete(accounts(0)));
+        assert_eq!(contract.get_time_until_deletion(accounts(0)), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Grace period not elapsed")]
+    fn execute_deletion_waits_for_custom_period() {
+        let mut contract = setup();
+
+        testing_env!(context(accounts(2), REQUESTED_AT + GRACE_PERIOD_NS - 1).build());
+        contract.execute_deletion(accounts(0));
+    }
+
+    #[test]
+    fn execute_deletion_after_custom_period() {
+        let mut contract = setup();
+
+        testing_env!(context(accounts(2), REQUESTED_AT + GRACE_PERIOD_NS).build());
+        contract.execute_deletion(accounts(0));
+        let account = contract.get_account(accounts(0)).unwrap();
+        assert!(account.status == AccountStatus::Deleted);
+    }
+}
@@ -1,8 +1,20 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::UnorderedMap;
-use near_sdk::{env, near_bindgen, AccountId, Balance, PanicOnDefault, Promise};
+use near_sdk::json_types::U128;
+use near_sdk::{
+    env, ext_contract, is_promise_success, near_bindgen, AccountId, Balance, Gas, PanicOnDefault,
+    Promise, PromiseOrValue,
+};
 use serde::{Deserialize, Serialize};
 
+const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas(10_000_000_000_000);
+
+#[ext_contract(ext_ft)]
+pub trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub enum CheckStatus {
@@ -17,12 +29,23 @@ pub struct Check {
     pub sender: AccountId,
     pub receiver: AccountId,
     pub amount: Balance,
+    /// NEP-141 token contract the check is denominated in; `None` for native NEAR.
+    pub token: Option<AccountId>,
     pub expiration: u64,
     pub status: CheckStatus,
     pub cashed_amount: Balance,
     pub created_at: u64,
 }
 
+/// `msg` payload accepted by `ft_on_transfer` to write a check funded with the
+/// transferred NEP-141 token.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenCheckMsg {
+    pub receiver: AccountId,
+    pub expiration: u64,
+}
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct ChecksContract {
@@ -45,6 +68,34 @@ impl ChecksContract {
         let sender = env::predecessor_account_id();
         let amount = env::attached_deposit();
 
+        self.insert_check(sender, receiver, amount, None, expiration)
+    }
+
+    /// NEP-141 receiver hook. Writes a check for the transferred amount, denominated in
+    /// the calling token contract.
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let token = env::predecessor_account_id();
+        let parsed: TokenCheckMsg =
+            near_sdk::serde_json::from_str(&msg).expect("Invalid check message");
+
+        self.insert_check(sender_id, parsed.receiver, amount.0, Some(token), parsed.expiration);
+
+        PromiseOrValue::Value(U128(0))
+    }
+
+    fn insert_check(
+        &mut self,
+        sender: AccountId,
+        receiver: AccountId,
+        amount: Balance,
+        token: Option<AccountId>,
+        expiration: u64,
+    ) -> u64 {
         assert!(amount > 0, "Deposit required");
         assert_ne!(sender, receiver, "Cannot create check to self");
         assert!(expiration > env::block_timestamp(), "Invalid expiration");
@@ -56,6 +107,7 @@ impl ChecksContract {
             sender,
             receiver,
             amount,
+            token,
             expiration,
             status: CheckStatus::Active,
             cashed_amount: 0,
@@ -85,7 +137,75 @@ impl ChecksContract {
 
         self.checks.insert(&check_id, &check);
 
-        Promise::new(receiver).transfer(amount)
+        match &check.token {
+            None => Promise::new(receiver).transfer(amount),
+            Some(token) => Self::ft_payout(token, receiver, amount).then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .resolve_cash(check_id, U128(amount)),
+            ),
+        }
+    }
+
+    /// Reverts a token cash whose `ft_transfer` failed so the balance can be cashed again.
+    /// If the check was cancelled or expired meanwhile, the reverted amount goes to the sender.
+    #[private]
+    pub fn resolve_cash(&mut self, check_id: u64, amount: U128) -> bool {
+        if is_promise_success() {
+            return true;
+        }
+
+        let mut check = self.checks.get(&check_id).expect("Check not found");
+        check.cashed_amount -= amount.0;
+        if check.status == CheckStatus::Cancelled {
+            // The refund already went out for the rest, so only the reverted part is owed
+            let token = check.token.as_ref().expect("Not a token check");
+            Self::ft_payout(token, check.sender.clone(), amount.0);
+        } else if check.status == CheckStatus::Cashed {
+            check.status = CheckStatus::Active;
+        }
+        self.checks.insert(&check_id, &check);
+        false
+    }
+
+    /// Moves `amount` of the caller's remaining balance onto a new check for `new_receiver`.
+    /// The new check keeps the original sender and expiration, so the combined outstanding
+    /// value is unchanged.
+    pub fn split_check(&mut self, check_id: u64, amount: U128, new_receiver: AccountId) -> u64 {
+        let amount = amount.0;
+        let receiver = env::predecessor_account_id();
+
+        let mut check = self.checks.get(&check_id).expect("Check not found");
+        assert_eq!(check.receiver, receiver, "Not authorized");
+        assert_eq!(check.status, CheckStatus::Active, "Check not active");
+        assert!(env::block_timestamp() < check.expiration, "Check expired");
+        assert_ne!(check.sender, new_receiver, "Cannot create check to self");
+
+        let remaining = check.amount - check.cashed_amount;
+        assert!(amount > 0 && amount <= remaining, "Invalid amount");
+
+        check.amount -= amount;
+        if check.cashed_amount == check.amount {
+            check.status = CheckStatus::Cashed;
+        }
+        self.checks.insert(&check_id, &check);
+
+        let new_id = self.next_id;
+        self.next_id += 1;
+
+        let split = Check {
+            sender: check.sender,
+            receiver: new_receiver,
+            amount,
+            token: check.token,
+            expiration: check.expiration,
+            status: CheckStatus::Active,
+            cashed_amount: 0,
+            created_at: env::block_timestamp(),
+        };
+
+        self.checks.insert(&new_id, &split);
+        new_id
     }
 
     pub fn cancel_check(&mut self, check_id: u64) -> Promise {
@@ -100,7 +220,7 @@ impl ChecksContract {
         check.status = CheckStatus::Cancelled;
         self.checks.insert(&check_id, &check);
 
-        Promise::new(sender).transfer(remaining)
+        Self::refund(check_id, &check, remaining)
     }
 
     pub fn expire_check(&mut self, check_id: u64) -> Promise {
@@ -113,7 +233,38 @@ impl ChecksContract {
         check.status = CheckStatus::Cancelled;
         self.checks.insert(&check_id, &check);
 
-        Promise::new(check.sender.clone()).transfer(remaining)
+        Self::refund(check_id, &check, remaining)
+    }
+
+    /// Reopens a token check whose refund `ft_transfer` failed so it can be cancelled again.
+    #[private]
+    pub fn resolve_refund(&mut self, check_id: u64) -> bool {
+        if is_promise_success() {
+            return true;
+        }
+
+        let mut check = self.checks.get(&check_id).expect("Check not found");
+        check.status = CheckStatus::Active;
+        self.checks.insert(&check_id, &check);
+        false
+    }
+
+    fn refund(check_id: u64, check: &Check, amount: Balance) -> Promise {
+        match &check.token {
+            None => Promise::new(check.sender.clone()).transfer(amount),
+            Some(token) => Self::ft_payout(token, check.sender.clone(), amount).then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .resolve_refund(check_id),
+            ),
+        }
+    }
+
+    fn ft_payout(token: &AccountId, receiver: AccountId, amount: Balance) -> Promise {
+        ext_ft::ext(token.clone())
+            .with_attached_deposit(1)
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(receiver, U128(amount), None)
     }
 
     pub fn get_check(&self, check_id: u64) -> Option<Check> {
@@ -128,3 +279,198 @@ impl ChecksContract {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig, VMConfig};
+
+    const EXPIRATION: u64 = 1_000;
+
+    fn context(predecessor: AccountId, deposit: Balance) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("checks.near".parse().unwrap())
+            .predecessor_account_id(predecessor)
+            .attached_deposit(deposit);
+        builder
+    }
+
+    fn token() -> AccountId {
+        "token.near".parse().unwrap()
+    }
+
+    /// `ft_transfer` calls scheduled so far, as (token, receiver, amount)
+    fn ft_transfers() -> Vec<(AccountId, AccountId, Balance)> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let token = receipt.receiver_id.clone();
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    VmAction::FunctionCall { function_name, args, .. } if function_name == "ft_transfer" => {
+                        let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(&args).unwrap();
+                        let receiver = args["receiver_id"].as_str().unwrap().parse().unwrap();
+                        let amount = args["amount"].as_str().unwrap().parse().unwrap();
+                        Some((token.clone(), receiver, amount))
+                    }
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    /// Check for 1000 of `token()` from `accounts(0)` to `accounts(1)`
+    fn token_check(contract: &mut ChecksContract) -> u64 {
+        testing_env!(context(token(), 0).build());
+        let refund = contract.ft_on_transfer(
+            accounts(0),
+            U128(1000),
+            format!(r#"{{"receiver":"{}","expiration":{}}}"#, accounts(1), EXPIRATION),
+        );
+        assert!(matches!(refund, PromiseOrValue::Value(U128(0))));
+        contract.next_id - 1
+    }
+
+    /// Runs the contract's own callback with the preceding promise reported as failed
+    fn with_failed_promise() {
+        testing_env!(
+            context("checks.near".parse().unwrap(), 0).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+    }
+
+    /// NEAR check for 1000 from `accounts(0)` to `accounts(1)`
+    fn setup() -> (ChecksContract, u64) {
+        testing_env!(context(accounts(0), 0).build());
+        let mut contract = ChecksContract::new();
+
+        testing_env!(context(accounts(0), 1000).build());
+        let check_id = contract.create_check(accounts(1), EXPIRATION);
+        (contract, check_id)
+    }
+
+    #[test]
+    fn split_moves_balance_to_new_check() {
+        let (mut contract, check_id) = setup();
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.cash_check(check_id, 100);
+        let split_id = contract.split_check(check_id, U128(300), accounts(2));
+
+        let split = contract.get_check(split_id).unwrap();
+        assert_eq!(split.sender, accounts(0));
+        assert_eq!(split.receiver, accounts(2));
+        assert_eq!(split.amount, 300);
+        assert_eq!(split.expiration, EXPIRATION);
+        assert_eq!(contract.get_remaining_amount(check_id), 600);
+        assert_eq!(contract.get_remaining_amount(check_id) + contract.get_remaining_amount(split_id), 900);
+    }
+
+    #[test]
+    fn split_of_whole_remainder_marks_original_cashed() {
+        let (mut contract, check_id) = setup();
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.cash_check(check_id, 400);
+        contract.split_check(check_id, U128(600), accounts(2));
+
+        assert!(contract.get_check(check_id).unwrap().status == CheckStatus::Cashed);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid amount")]
+    fn split_rejects_more_than_remaining() {
+        let (mut contract, check_id) = setup();
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.split_check(check_id, U128(1001), accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not authorized")]
+    fn split_is_receiver_only() {
+        let (mut contract, check_id) = setup();
+
+        testing_env!(context(accounts(2), 0).build());
+        contract.split_check(check_id, U128(100), accounts(2));
+    }
+
+    #[test]
+    fn token_transfer_writes_token_check() {
+        let (mut contract, _) = setup();
+        let check_id = token_check(&mut contract);
+
+        let check = contract.get_check(check_id).unwrap();
+        assert_eq!(check.sender, accounts(0));
+        assert_eq!(check.receiver, accounts(1));
+        assert_eq!(check.amount, 1000);
+        assert_eq!(check.token, Some(token()));
+    }
+
+    #[test]
+    fn token_cash_pays_out_in_token() {
+        let (mut contract, _) = setup();
+        let check_id = token_check(&mut contract);
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.cash_check(check_id, 400);
+        assert_eq!(ft_transfers(), vec![(token(), accounts(1), 400)]);
+        assert_eq!(contract.get_remaining_amount(check_id), 600);
+    }
+
+    #[test]
+    fn failed_token_cash_is_rolled_back() {
+        let (mut contract, _) = setup();
+        let check_id = token_check(&mut contract);
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.cash_check(check_id, 1000);
+        assert!(contract.get_check(check_id).unwrap().status == CheckStatus::Cashed);
+
+        with_failed_promise();
+        assert!(!contract.resolve_cash(check_id, U128(1000)));
+        let check = contract.get_check(check_id).unwrap();
+        assert!(check.status == CheckStatus::Active);
+        assert_eq!(check.cashed_amount, 0);
+    }
+
+    #[test]
+    fn failed_token_refund_reopens_check() {
+        let (mut contract, _) = setup();
+        let check_id = token_check(&mut contract);
+
+        testing_env!(context(accounts(0), 0).build());
+        contract.cancel_check(check_id);
+        assert_eq!(ft_transfers(), vec![(token(), accounts(0), 1000)]);
+
+        with_failed_promise();
+        assert!(!contract.resolve_refund(check_id));
+        assert!(contract.get_check(check_id).unwrap().status == CheckStatus::Active);
+    }
+
+    #[test]
+    fn failed_cash_after_cancel_refunds_sender() {
+        let (mut contract, _) = setup();
+        let check_id = token_check(&mut contract);
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.cash_check(check_id, 400);
+
+        // The sender cancels while the cash transfer is still in flight
+        testing_env!(context(accounts(0), 0).build());
+        contract.cancel_check(check_id);
+        assert_eq!(ft_transfers(), vec![(token(), accounts(0), 600)]);
+
+        with_failed_promise();
+        assert!(!contract.resolve_cash(check_id, U128(400)));
+        assert_eq!(ft_transfers(), vec![(token(), accounts(0), 400)]);
+        let check = contract.get_check(check_id).unwrap();
+        assert!(check.status == CheckStatus::Cancelled);
+        assert_eq!(check.cashed_amount, 0);
+    }
+}
@@ -21,6 +21,11 @@ impl KYCTier {
             KYCTier::Institutional => 1_000_000_000_000_000_000_000_000_000, // 1000 NEAR
         }
     }
+
+    /// Tiers above `Standard` may only be granted by the compliance officer.
+    pub fn requires_officer(&self) -> bool {
+        matches!(self, KYCTier::Premium | KYCTier::Institutional)
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -35,20 +40,52 @@ pub struct Authorization {
     pub tier: KYCTier,
     pub active: bool,
     pub created_at: u64,
+    /// Length of the rolling spend window in nanoseconds; 0 disables it.
+    pub window_ns: u64,
+    pub window_limit: Balance,
+    pub window_start: u64,
+    pub window_used: Balance,
+}
+
+impl Authorization {
+    fn window_used_at(&self, now: u64) -> Balance {
+        if now.saturating_sub(self.window_start) >= self.window_ns {
+            0
+        } else {
+            self.window_used
+        }
+    }
+
+    fn window_allows(&self, amount: Balance, now: u64) -> bool {
+        self.window_ns == 0 || self.window_used_at(now) + amount <= self.window_limit
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GlobalLimit {
+    pub max_amount: Balance,
+    pub used_amount: Balance,
 }
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct DepositAuthContract {
+    admin: AccountId,
+    compliance_officer: AccountId,
     authorizations: UnorderedMap<String, Authorization>,
+    global_limits: UnorderedMap<String, GlobalLimit>,
 }
 
 #[near_bindgen]
 impl DepositAuthContract {
     #[init]
-    pub fn new() -> Self {
+    pub fn new(compliance_officer: AccountId) -> Self {
         Self {
+            admin: env::predecessor_account_id(),
+            compliance_officer,
             authorizations: UnorderedMap::new(b"a"),
+            global_limits: UnorderedMap::new(b"g"),
         }
     }
 
@@ -59,6 +96,8 @@ impl DepositAuthContract {
         max_amount: Balance,
         expiration: u64,
         tier: KYCTier,
+        window_ns: u64,
+        window_limit: Balance,
     ) {
         let authorizer = env::predecessor_account_id();
 
@@ -69,6 +108,13 @@ impl DepositAuthContract {
             max_amount <= tier.max_amount(),
             "Amount exceeds tier limit"
         );
+        assert_eq!(
+            window_ns == 0,
+            window_limit == 0,
+            "Window length and limit must be set together"
+        );
+        assert!(window_limit <= max_amount, "Window limit exceeds max amount");
+        self.assert_may_grant(&authorizer, &tier);
 
         let key = Self::generate_key(&authorizer, &authorized, &asset);
         assert!(
@@ -86,6 +132,10 @@ impl DepositAuthContract {
             tier,
             active: true,
             created_at: env::block_timestamp(),
+            window_ns,
+            window_limit,
+            window_start: env::block_timestamp(),
+            window_used: 0,
         };
 
         self.authorizations.insert(&key, &authorization);
@@ -105,6 +155,8 @@ impl DepositAuthContract {
             auth.active
                 && now < auth.expiration
                 && (auth.used_amount + amount) <= auth.max_amount
+                && auth.window_allows(amount, now)
+                && self.global_headroom(&authorizer, &authorized) >= amount
         } else {
             false
         }
@@ -134,10 +186,65 @@ impl DepositAuthContract {
             "Amount exceeds limit"
         );
 
+        if auth.window_ns > 0 {
+            let now = env::block_timestamp();
+            if now - auth.window_start >= auth.window_ns {
+                auth.window_start = now;
+                auth.window_used = 0;
+            }
+            assert!(
+                auth.window_used + amount <= auth.window_limit,
+                "Amount exceeds window limit"
+            );
+            auth.window_used += amount;
+        }
+
+        let global_key = Self::generate_global_key(&authorizer, &authorized);
+        if let Some(mut global) = self.global_limits.get(&global_key) {
+            assert!(
+                global.used_amount + amount <= global.max_amount,
+                "Amount exceeds global limit"
+            );
+            global.used_amount += amount;
+            self.global_limits.insert(&global_key, &global);
+        }
+
         auth.used_amount += amount;
         self.authorizations.insert(&key, &auth);
     }
 
+    /// Sets a ceiling on the total used across every asset authorized for `authorized`.
+    /// Usage already recorded against an existing global limit is preserved.
+    pub fn set_global_limit(&mut self, authorized: AccountId, max_amount: Balance) {
+        let authorizer = env::predecessor_account_id();
+        assert_ne!(authorizer, authorized, "Cannot authorize self");
+        assert!(max_amount > 0, "Invalid max amount");
+
+        let key = Self::generate_global_key(&authorizer, &authorized);
+        let used_amount = self
+            .global_limits
+            .get(&key)
+            .map(|global| global.used_amount)
+            .unwrap_or(0);
+
+        self.global_limits.insert(
+            &key,
+            &GlobalLimit {
+                max_amount,
+                used_amount,
+            },
+        );
+    }
+
+    pub fn remove_global_limit(&mut self, authorized: AccountId) {
+        let authorizer = env::predecessor_account_id();
+        let key = Self::generate_global_key(&authorizer, &authorized);
+        assert!(
+            self.global_limits.remove(&key).is_some(),
+            "Global limit not found"
+        );
+    }
+
     pub fn revoke_authorization(&mut self, authorized: AccountId, asset: String) {
         let authorizer = env::predecessor_account_id();
         let key = Self::generate_key(&authorizer, &authorized, &asset);
@@ -165,15 +272,28 @@ impl DepositAuthContract {
 
         assert_eq!(auth.authorizer, authorizer, "Not authorized");
         assert!(auth.active, "Authorization not active");
+        self.assert_may_grant(&authorizer, &new_tier);
+        let ceiling = new_tier.max_amount();
         assert!(
-            auth.max_amount <= new_tier.max_amount(),
-            "Amount exceeds new tier limit"
+            auth.used_amount <= ceiling,
+            "Cannot downgrade tier: used amount exceeds new tier limit"
         );
 
+        auth.max_amount = auth.max_amount.min(ceiling);
+        auth.window_limit = auth.window_limit.min(auth.max_amount);
         auth.tier = new_tier;
         self.authorizations.insert(&key, &auth);
     }
 
+    pub fn set_compliance_officer(&mut self, compliance_officer: AccountId) {
+        assert_eq!(env::predecessor_account_id(), self.admin, "Only admin");
+        self.compliance_officer = compliance_officer;
+    }
+
+    pub fn get_compliance_officer(&self) -> AccountId {
+        self.compliance_officer.clone()
+    }
+
     pub fn get_authorization(
         &self,
         authorizer: AccountId,
@@ -198,7 +318,291 @@ impl DepositAuthContract {
         }
     }
 
+    pub fn get_global_limit(
+        &self,
+        authorizer: AccountId,
+        authorized: AccountId,
+    ) -> Option<GlobalLimit> {
+        let key = Self::generate_global_key(&authorizer, &authorized);
+        self.global_limits.get(&key)
+    }
+
+    pub fn get_global_used(&self, authorizer: AccountId, authorized: AccountId) -> Balance {
+        let key = Self::generate_global_key(&authorizer, &authorized);
+        self.global_limits
+            .get(&key)
+            .map(|global| global.used_amount)
+            .unwrap_or(0)
+    }
+
+    fn assert_may_grant(&self, authorizer: &AccountId, tier: &KYCTier) {
+        assert!(
+            !tier.requires_officer() || *authorizer == self.compliance_officer,
+            "Tier requires compliance officer"
+        );
+    }
+
+    fn global_headroom(&self, authorizer: &AccountId, authorized: &AccountId) -> Balance {
+        let key = Self::generate_global_key(authorizer, authorized);
+        match self.global_limits.get(&key) {
+            Some(global) => global.max_amount.saturating_sub(global.used_amount),
+            None => Balance::MAX,
+        }
+    }
+
+    fn generate_global_key(authorizer: &AccountId, authorized: &AccountId) -> String {
+        format!("{}:{}", authorizer, authorized)
+    }
+
     fn generate_key(authorizer: &AccountId, authorized: &AccountId, asset: &String) -> String {
         format!("{}:{}:{}", authorizer, authorized, asset)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+    const EXPIRATION: u64 = 1_000_000;
+
+    fn context(predecessor: AccountId, timestamp: u64) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder.predecessor_account_id(predecessor).block_timestamp(timestamp);
+        builder
+    }
+
+    /// Contract administered by `accounts(0)` with `accounts(4)` as compliance officer
+    fn setup() -> DepositAuthContract {
+        testing_env!(context(accounts(0), 0).build());
+        DepositAuthContract::new(accounts(4))
+    }
+
+    /// `accounts(1)` authorizes `accounts(2)` for up to `max_amount` of `asset` at the Standard tier
+    fn authorize(contract: &mut DepositAuthContract, asset: &str, max_amount: Balance) {
+        testing_env!(context(accounts(1), 0).build());
+        contract.create_authorization(
+            accounts(2),
+            asset.to_string(),
+            max_amount,
+            EXPIRATION,
+            KYCTier::Standard,
+            0,
+            0,
+        );
+    }
+
+    /// `accounts(2)` spends `amount` of `asset` against `accounts(1)`'s authorization
+    fn spend(contract: &mut DepositAuthContract, asset: &str, amount: Balance, timestamp: u64) {
+        testing_env!(context(accounts(2), timestamp).build());
+        contract.use_authorization(accounts(1), asset.to_string(), amount);
+    }
+
+    #[test]
+    fn global_limit_caps_spending_across_assets() {
+        let mut contract = setup();
+        authorize(&mut contract, "USD", 5 * NEAR);
+        authorize(&mut contract, "EUR", 5 * NEAR);
+        contract.set_global_limit(accounts(2), 6 * NEAR);
+
+        spend(&mut contract, "USD", 4 * NEAR, 0);
+        assert_eq!(contract.get_global_used(accounts(1), accounts(2)), 4 * NEAR);
+        assert!(contract.validate_deposit(accounts(1), accounts(2), "EUR".to_string(), 2 * NEAR));
+        assert!(!contract.validate_deposit(accounts(1), accounts(2), "EUR".to_string(), 3 * NEAR));
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount exceeds global limit")]
+    fn global_limit_rejects_spend_over_cap() {
+        let mut contract = setup();
+        authorize(&mut contract, "USD", 5 * NEAR);
+        authorize(&mut contract, "EUR", 5 * NEAR);
+        contract.set_global_limit(accounts(2), 6 * NEAR);
+
+        spend(&mut contract, "USD", 4 * NEAR, 0);
+        spend(&mut contract, "EUR", 3 * NEAR, 0);
+    }
+
+    #[test]
+    fn resetting_global_limit_keeps_usage() {
+        let mut contract = setup();
+        authorize(&mut contract, "USD", 5 * NEAR);
+        contract.set_global_limit(accounts(2), 6 * NEAR);
+        spend(&mut contract, "USD", 4 * NEAR, 0);
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.set_global_limit(accounts(2), 8 * NEAR);
+        let global = contract.get_global_limit(accounts(1), accounts(2)).unwrap();
+        assert_eq!(global.max_amount, 8 * NEAR);
+        assert_eq!(global.used_amount, 4 * NEAR);
+    }
+
+    /// Like `authorize`, with a 2 NEAR limit per 100ns rolling window
+    fn authorize_windowed(contract: &mut DepositAuthContract, asset: &str, max_amount: Balance) {
+        testing_env!(context(accounts(1), 0).build());
+        contract.create_authorization(
+            accounts(2),
+            asset.to_string(),
+            max_amount,
+            EXPIRATION,
+            KYCTier::Standard,
+            100,
+            2 * NEAR,
+        );
+    }
+
+    #[test]
+    fn window_limit_resets_after_window() {
+        let mut contract = setup();
+        authorize_windowed(&mut contract, "USD", 5 * NEAR);
+
+        spend(&mut contract, "USD", 2 * NEAR, 0);
+        assert!(!contract.validate_deposit(accounts(1), accounts(2), "USD".to_string(), NEAR));
+
+        testing_env!(context(accounts(2), 100).build());
+        assert!(contract.validate_deposit(accounts(1), accounts(2), "USD".to_string(), 2 * NEAR));
+        spend(&mut contract, "USD", 2 * NEAR, 100);
+
+        let auth = contract.get_authorization(accounts(1), accounts(2), "USD".to_string()).unwrap();
+        assert_eq!(auth.window_start, 100);
+        assert_eq!(auth.window_used, 2 * NEAR);
+        assert_eq!(auth.used_amount, 4 * NEAR);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount exceeds window limit")]
+    fn window_limit_rejects_spend_within_window() {
+        let mut contract = setup();
+        authorize_windowed(&mut contract, "USD", 5 * NEAR);
+
+        spend(&mut contract, "USD", 2 * NEAR, 0);
+        spend(&mut contract, "USD", NEAR, 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "Window length and limit must be set together")]
+    fn window_requires_both_length_and_limit() {
+        let mut contract = setup();
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.create_authorization(
+            accounts(2),
+            "USD".to_string(),
+            NEAR,
+            EXPIRATION,
+            KYCTier::Standard,
+            100,
+            0,
+        );
+    }
+
+    #[test]
+    fn downgrade_clamps_limit_to_new_tier() {
+        let mut contract = setup();
+        authorize(&mut contract, "USD", 5 * NEAR);
+        spend(&mut contract, "USD", NEAR / 2, 0);
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.update_tier(accounts(2), "USD".to_string(), KYCTier::Basic);
+        let auth = contract.get_authorization(accounts(1), accounts(2), "USD".to_string()).unwrap();
+        assert!(auth.tier == KYCTier::Basic);
+        assert_eq!(auth.max_amount, NEAR);
+        assert_eq!(contract.get_remaining_amount(accounts(1), accounts(2), "USD".to_string()), NEAR / 2);
+    }
+
+    #[test]
+    fn upgrade_by_authorizer_keeps_limit_within_new_tier() {
+        let mut contract = setup();
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.create_authorization(
+            accounts(2),
+            "USD".to_string(),
+            NEAR,
+            EXPIRATION,
+            KYCTier::Basic,
+            0,
+            0,
+        );
+        spend(&mut contract, "USD", NEAR, 0);
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.update_tier(accounts(2), "USD".to_string(), KYCTier::Standard);
+        let auth = contract.get_authorization(accounts(1), accounts(2), "USD".to_string()).unwrap();
+        assert!(auth.tier == KYCTier::Standard);
+        assert_eq!(auth.used_amount, NEAR);
+        assert!(auth.max_amount <= KYCTier::Standard.max_amount());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot downgrade tier: used amount exceeds new tier limit")]
+    fn downgrade_below_usage_is_rejected() {
+        let mut contract = setup();
+        authorize(&mut contract, "USD", 5 * NEAR);
+        spend(&mut contract, "USD", 3 * NEAR, 0);
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.update_tier(accounts(2), "USD".to_string(), KYCTier::Basic);
+    }
+
+    /// `authorizer` grants `accounts(2)` up to 50 NEAR of USD at the Premium tier
+    fn grant_premium(contract: &mut DepositAuthContract, authorizer: AccountId) {
+        testing_env!(context(authorizer, 0).build());
+        contract.create_authorization(
+            accounts(2),
+            "USD".to_string(),
+            50 * NEAR,
+            EXPIRATION,
+            KYCTier::Premium,
+            0,
+            0,
+        );
+    }
+
+    #[test]
+    fn officer_grants_premium_tier() {
+        let mut contract = setup();
+        grant_premium(&mut contract, accounts(4));
+
+        let auth = contract.get_authorization(accounts(4), accounts(2), "USD".to_string()).unwrap();
+        assert!(auth.tier == KYCTier::Premium);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tier requires compliance officer")]
+    fn premium_tier_requires_officer() {
+        let mut contract = setup();
+        grant_premium(&mut contract, accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Tier requires compliance officer")]
+    fn upgrade_to_premium_requires_officer() {
+        let mut contract = setup();
+        authorize(&mut contract, "USD", 5 * NEAR);
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.update_tier(accounts(2), "USD".to_string(), KYCTier::Premium);
+    }
+
+    #[test]
+    fn admin_replaces_officer() {
+        let mut contract = setup();
+
+        testing_env!(context(accounts(0), 0).build());
+        contract.set_compliance_officer(accounts(3));
+        assert_eq!(contract.get_compliance_officer(), accounts(3));
+        grant_premium(&mut contract, accounts(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only admin")]
+    fn officer_is_set_by_admin_only() {
+        let mut contract = setup();
+
+        testing_env!(context(accounts(4), 0).build());
+        contract.set_compliance_officer(accounts(1));
+    }
+}
@@ -1,8 +1,20 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::UnorderedMap;
-use near_sdk::{env, near_bindgen, AccountId, Balance, PanicOnDefault, Promise};
+use near_sdk::json_types::U128;
+use near_sdk::{
+    env, ext_contract, is_promise_success, near_bindgen, AccountId, Balance, Gas, PanicOnDefault,
+    Promise, PromiseOrValue,
+};
 use serde::{Deserialize, Serialize};
 
+const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas(10_000_000_000_000);
+
+#[ext_contract(ext_ft)]
+pub trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub enum OrderStatus {
@@ -25,6 +37,31 @@ pub struct Order {
     pub created_at: u64,
 }
 
+/// `msg` payload accepted by `ft_on_transfer` to place an order selling the
+/// transferred NEP-141 token.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenOrderMsg {
+    pub buy_asset: String,
+    pub buy_amount: U128,
+}
+
+/// `msg` payload accepted by `ft_on_transfer` to fill an order that buys the
+/// transferred NEP-141 token. Any amount above the payment is returned.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenFillMsg {
+    pub fill_order: u64,
+    pub fill_amount: U128,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde", untagged)]
+enum TokenMsg {
+    Fill(TokenFillMsg),
+    Place(TokenOrderMsg),
+}
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct DEXContract {
@@ -53,13 +90,60 @@ impl DEXContract {
         let creator = env::predecessor_account_id();
         let deposit = env::attached_deposit();
 
-        assert!(sell_amount > 0 && buy_amount > 0, "Invalid amounts");
-        assert_ne!(sell_asset, buy_asset, "Assets must differ");
+        // Token orders are escrowed through `ft_transfer_call`, NEAR orders by deposit
+        assert_eq!(sell_asset, "NEAR", "Sell tokens via ft_transfer_call");
+        assert_eq!(deposit, sell_amount, "Deposit must match sell amount");
 
-        // For NEAR native token orders, require deposit
-        if sell_asset == "NEAR" {
-            assert_eq!(deposit, sell_amount, "Deposit must match sell amount");
+        self.insert_order(creator, sell_asset, buy_asset, sell_amount, buy_amount)
+    }
+
+    /// NEP-141 receiver hook. Either places an order selling the transferred token, with
+    /// the token contract's account id as `sell_asset`, or pays for a fill of an order
+    /// whose `buy_asset` is that token.
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let token = env::predecessor_account_id();
+        let parsed: TokenMsg =
+            near_sdk::serde_json::from_str(&msg).expect("Invalid order message");
+
+        match parsed {
+            TokenMsg::Place(order) => {
+                self.insert_order(
+                    sender_id,
+                    token.to_string(),
+                    order.buy_asset,
+                    amount.0,
+                    order.buy_amount.0,
+                );
+                PromiseOrValue::Value(U128(0))
+            }
+            TokenMsg::Fill(fill) => {
+                let order = self.orders.get(&fill.fill_order).expect("Order not found");
+                assert_eq!(order.buy_asset, token.to_string(), "Wrong payment token");
+                let payment = Self::payment_for(&order, fill.fill_amount.0);
+                assert!(amount.0 >= payment, "Insufficient payment");
+
+                // The token contract refunds whatever `resolve_fill` reports as unused
+                self.fill(fill.fill_order, sender_id, fill.fill_amount.0, payment, amount.0 - payment)
+                    .into()
+            }
         }
+    }
+
+    fn insert_order(
+        &mut self,
+        creator: AccountId,
+        sell_asset: String,
+        buy_asset: String,
+        sell_amount: Balance,
+        buy_amount: Balance,
+    ) -> u64 {
+        assert!(sell_amount > 0 && buy_amount > 0, "Invalid amounts");
+        assert_ne!(sell_asset, buy_asset, "Assets must differ");
 
         let order_id = self.next_id;
         self.next_id += 1;
@@ -79,11 +163,31 @@ impl DEXContract {
         order_id
     }
 
+    /// Fills an order that buys NEAR, paid by the attached deposit. Orders buying a token
+    /// are filled by sending that token with `ft_transfer_call` and a `TokenFillMsg`.
     #[payable]
     pub fn fill_order(&mut self, order_id: u64, fill_amount: Balance) -> Promise {
         let filler = env::predecessor_account_id();
         let deposit = env::attached_deposit();
 
+        let order = self.orders.get(&order_id).expect("Order not found");
+        assert_eq!(order.buy_asset, "NEAR", "Pay tokens via ft_transfer_call");
+        let payment = Self::payment_for(&order, fill_amount);
+        assert_eq!(deposit, payment, "Deposit must match payment");
+
+        self.fill(order_id, filler, fill_amount, payment, 0)
+    }
+
+    /// Records a paid fill and releases the sold asset to the filler. The maker is only
+    /// paid once that release succeeds; otherwise `resolve_fill` unwinds the fill.
+    fn fill(
+        &mut self,
+        order_id: u64,
+        filler: AccountId,
+        fill_amount: Balance,
+        payment: Balance,
+        excess: Balance,
+    ) -> Promise {
         let mut order = self.orders.get(&order_id).expect("Order not found");
         assert!(
             order.status == OrderStatus::Open || order.status == OrderStatus::PartiallyFilled,
@@ -93,14 +197,6 @@ impl DEXContract {
         let remaining = order.sell_amount - order.filled_amount;
         assert!(fill_amount > 0 && fill_amount <= remaining, "Invalid fill amount");
 
-        // Calculate proportional payment
-        let payment = (fill_amount * order.buy_amount) / order.sell_amount;
-
-        // For NEAR native token, verify deposit
-        if order.buy_asset == "NEAR" {
-            assert_eq!(deposit, payment, "Deposit must match payment");
-        }
-
         order.filled_amount += fill_amount;
 
         if order.filled_amount == order.sell_amount {
@@ -111,16 +207,70 @@ impl DEXContract {
 
         self.orders.insert(&order_id, &order);
 
-        // Transfer filled amount to filler
-        // In production, this would integrate with token contracts
-        if order.sell_asset == "NEAR" {
-            Promise::new(filler).transfer(fill_amount)
+        let release = if order.sell_asset == "NEAR" {
+            Promise::new(filler.clone()).transfer(fill_amount)
         } else {
-            // For non-NEAR assets, would call token contract
-            Promise::new(filler).transfer(0)
+            Self::ft_payout(&order.sell_asset, filler.clone(), fill_amount)
+        };
+        release.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_TRANSFER + GAS_FOR_FT_TRANSFER)
+                .resolve_fill(order_id, filler, U128(fill_amount), U128(payment), U128(excess)),
+        )
+    }
+
+    /// Pays the maker once the filler has received the sold asset. If that release failed,
+    /// the fill is reverted and the payment goes back to the filler: NEAR directly, tokens
+    /// through the returned unused amount that `ft_resolve_transfer` refunds.
+    #[private]
+    pub fn resolve_fill(
+        &mut self,
+        order_id: u64,
+        filler: AccountId,
+        fill_amount: U128,
+        payment: U128,
+        excess: U128,
+    ) -> U128 {
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+
+        if is_promise_success() {
+            if order.buy_asset == "NEAR" {
+                Promise::new(order.creator.clone()).transfer(payment.0);
+            } else {
+                Self::ft_payout(&order.buy_asset, order.creator.clone(), payment.0);
+            }
+            return excess;
+        }
+
+        order.filled_amount -= fill_amount.0;
+        if order.status == OrderStatus::Cancelled {
+            // Cancelled while the fill was in flight: the maker gets the reverted part back
+            if order.sell_asset == "NEAR" {
+                Promise::new(order.creator.clone()).transfer(fill_amount.0);
+            } else {
+                Self::ft_payout(&order.sell_asset, order.creator.clone(), fill_amount.0);
+            }
+        } else if order.filled_amount == 0 {
+            order.status = OrderStatus::Open;
+        } else {
+            order.status = OrderStatus::PartiallyFilled;
+        }
+        self.orders.insert(&order_id, &order);
+
+        if order.buy_asset == "NEAR" {
+            Promise::new(filler).transfer(payment.0);
+            U128(0)
+        } else {
+            U128(excess.0 + payment.0)
         }
     }
 
+    fn payment_for(order: &Order, fill_amount: Balance) -> Balance {
+        let payment = (fill_amount * order.buy_amount) / order.sell_amount;
+        assert!(payment > 0, "Fill too small");
+        payment
+    }
+
     pub fn cancel_order(&mut self, order_id: u64) -> Promise {
         let creator = env::predecessor_account_id();
 
@@ -132,18 +282,44 @@ impl DEXContract {
         );
 
         let remaining = order.sell_amount - order.filled_amount;
+        let previous_status = order.status.clone();
 
         order.status = OrderStatus::Cancelled;
         self.orders.insert(&order_id, &order);
 
-        // Return remaining deposit for NEAR orders
+        // Return the unfilled remainder in the asset the maker deposited
         if order.sell_asset == "NEAR" {
             Promise::new(creator).transfer(remaining)
         } else {
-            Promise::new(creator).transfer(0)
+            Self::ft_payout(&order.sell_asset, creator, remaining).then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .resolve_cancel(order_id, previous_status),
+            )
         }
     }
 
+    /// Restores a token order whose refund `ft_transfer` failed so it can be cancelled again.
+    #[private]
+    pub fn resolve_cancel(&mut self, order_id: u64, previous_status: OrderStatus) -> bool {
+        if is_promise_success() {
+            return true;
+        }
+
+        let mut order = self.orders.get(&order_id).expect("Order not found");
+        order.status = previous_status;
+        self.orders.insert(&order_id, &order);
+        false
+    }
+
+    fn ft_payout(token: &str, receiver: AccountId, amount: Balance) -> Promise {
+        let token: AccountId = token.parse().expect("Invalid token account");
+        ext_ft::ext(token)
+            .with_attached_deposit(1)
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(receiver, U128(amount), None)
+    }
+
     pub fn get_order(&self, order_id: u64) -> Option<Order> {
         self.orders.get(&order_id)
     }
@@ -156,3 +332,212 @@ impl DEXContract {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig, VMConfig};
+
+    fn context(predecessor: AccountId, deposit: Balance) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("dex.near".parse().unwrap())
+            .predecessor_account_id(predecessor)
+            .attached_deposit(deposit);
+        builder
+    }
+
+    fn token() -> AccountId {
+        "token.near".parse().unwrap()
+    }
+
+    /// Native transfers scheduled so far, as (receiver, amount)
+    fn near_transfers() -> Vec<(AccountId, Balance)> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let receiver = receipt.receiver_id.clone();
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    VmAction::Transfer { deposit } => Some((receiver.clone(), deposit)),
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    /// `ft_transfer` calls scheduled so far, as (token, receiver, amount)
+    fn ft_transfers() -> Vec<(AccountId, AccountId, Balance)> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let token = receipt.receiver_id.clone();
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    VmAction::FunctionCall { function_name, args, .. } if function_name == "ft_transfer" => {
+                        let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(&args).unwrap();
+                        let receiver = args["receiver_id"].as_str().unwrap().parse().unwrap();
+                        let amount = args["amount"].as_str().unwrap().parse().unwrap();
+                        Some((token.clone(), receiver, amount))
+                    }
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    /// Token order from `accounts(0)` selling 1000 of `token()` for 500 NEAR
+    fn token_order(contract: &mut DEXContract) -> u64 {
+        testing_env!(context(token(), 0).build());
+        contract.ft_on_transfer(
+            accounts(0),
+            U128(1000),
+            r#"{"buy_asset":"NEAR","buy_amount":"500"}"#.to_string(),
+        );
+        0
+    }
+
+    fn setup() -> DEXContract {
+        testing_env!(context(accounts(0), 0).build());
+        DEXContract::new()
+    }
+
+    #[test]
+    fn cancel_refunds_unfilled_token_remainder() {
+        let mut contract = setup();
+        let order_id = token_order(&mut contract);
+
+        testing_env!(context(accounts(1), 200).build());
+        contract.fill_order(order_id, 400);
+
+        testing_env!(context(accounts(0), 0).build());
+        contract.cancel_order(order_id);
+        assert_eq!(ft_transfers(), vec![(token(), accounts(0), 600)]);
+        assert!(contract.get_order(order_id).unwrap().status == OrderStatus::Cancelled);
+    }
+
+    #[test]
+    fn cancel_refunds_unfilled_near_remainder() {
+        let mut contract = setup();
+        testing_env!(context(accounts(0), 1000).build());
+        let order_id = contract.place_order("NEAR".to_string(), token().to_string(), 1000, 300);
+
+        testing_env!(context(token(), 0).build());
+        contract.ft_on_transfer(
+            accounts(1),
+            U128(75),
+            format!(r#"{{"fill_order":{},"fill_amount":"250"}}"#, order_id),
+        );
+
+        testing_env!(context(accounts(0), 0).build());
+        contract.cancel_order(order_id);
+        assert_eq!(near_transfers(), vec![(accounts(0), 750)]);
+    }
+
+    #[test]
+    fn failed_refund_restores_order() {
+        let mut contract = setup();
+        let order_id = token_order(&mut contract);
+
+        testing_env!(context(accounts(0), 0).build());
+        contract.cancel_order(order_id);
+
+        testing_env!(
+            context("dex.near".parse().unwrap(), 0).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        assert!(!contract.resolve_cancel(order_id, OrderStatus::Open));
+        assert!(contract.get_order(order_id).unwrap().status == OrderStatus::Open);
+    }
+
+    #[test]
+    fn near_fill_releases_tokens_then_pays_maker() {
+        let mut contract = setup();
+        let order_id = token_order(&mut contract);
+
+        testing_env!(context(accounts(1), 200).build());
+        contract.fill_order(order_id, 400);
+        assert_eq!(ft_transfers(), vec![(token(), accounts(1), 400)]);
+        assert_eq!(contract.get_remaining_amount(order_id), 600);
+
+        testing_env!(
+            context("dex.near".parse().unwrap(), 0).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        contract.resolve_fill(order_id, accounts(1), U128(400), U128(200), U128(0));
+        assert_eq!(near_transfers(), vec![(accounts(0), 200)]);
+    }
+
+    #[test]
+    fn failed_release_reverts_fill_and_refunds_filler() {
+        let mut contract = setup();
+        let order_id = token_order(&mut contract);
+
+        testing_env!(context(accounts(1), 200).build());
+        contract.fill_order(order_id, 400);
+
+        testing_env!(
+            context("dex.near".parse().unwrap(), 0).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.resolve_fill(order_id, accounts(1), U128(400), U128(200), U128(0));
+        let order = contract.get_order(order_id).unwrap();
+        assert_eq!(order.filled_amount, 0);
+        assert!(order.status == OrderStatus::Open);
+        assert_eq!(near_transfers(), vec![(accounts(1), 200)]);
+    }
+
+    #[test]
+    fn token_payment_excess_is_returned() {
+        let mut contract = setup();
+        testing_env!(context(accounts(0), 1000).build());
+        let order_id = contract.place_order("NEAR".to_string(), token().to_string(), 1000, 300);
+
+        testing_env!(
+            context("dex.near".parse().unwrap(), 0).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        // 120 sent for a 300-unit fill costing 90: 30 is unused
+        let unused = contract.resolve_fill(order_id, accounts(1), U128(300), U128(90), U128(30));
+        assert_eq!(unused.0, 30);
+        assert_eq!(ft_transfers(), vec![(token(), accounts(0), 90)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Pay tokens via ft_transfer_call")]
+    fn token_priced_order_needs_token_payment() {
+        let mut contract = setup();
+        testing_env!(context(accounts(0), 1000).build());
+        let order_id = contract.place_order("NEAR".to_string(), token().to_string(), 1000, 300);
+
+        testing_env!(context(accounts(1), 0).build());
+        contract.fill_order(order_id, 1000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient payment")]
+    fn token_fill_checks_payment() {
+        let mut contract = setup();
+        testing_env!(context(accounts(0), 1000).build());
+        let order_id = contract.place_order("NEAR".to_string(), token().to_string(), 1000, 300);
+
+        testing_env!(context(token(), 0).build());
+        contract.ft_on_transfer(
+            accounts(1),
+            U128(89),
+            format!(r#"{{"fill_order":{},"fill_amount":"300"}}"#, order_id),
+        );
+    }
+}
@@ -10,6 +10,7 @@ pub struct DIDDocument {
     pub owner: AccountId,
     pub document_uri: String,
     pub active: bool,
+    pub delegates: Vec<AccountId>,
     pub created_at: u64,
     pub updated_at: u64,
 }
@@ -49,6 +50,7 @@ impl DIDManager {
             owner: owner.clone(),
             document_uri,
             active: true,
+            delegates: Vec::new(),
             created_at: timestamp,
             updated_at: timestamp,
         };
@@ -57,7 +59,32 @@ impl DIDManager {
         self.account_to_did.insert(&owner, &did);
     }
 
-    pub fn update_did(&mut self, new_document_uri: String) {
+    /// Updates the document URI. Owners may omit `did` to update their own DID;
+    /// delegates must name the DID they manage.
+    pub fn update_did(&mut self, new_document_uri: String, did: Option<String>) {
+        let caller = env::predecessor_account_id();
+
+        let did = did.unwrap_or_else(|| {
+            self.account_to_did
+                .get(&caller)
+                .expect("No DID registered")
+        });
+
+        let mut did_document = self.dids.get(&did).expect("DID not found");
+        assert!(
+            did_document.owner == caller || did_document.delegates.contains(&caller),
+            "Not authorized"
+        );
+        assert!(did_document.active, "DID not active");
+        assert!(!new_document_uri.is_empty(), "Document URI required");
+
+        did_document.document_uri = new_document_uri;
+        did_document.updated_at = env::block_timestamp();
+
+        self.dids.insert(&did, &did_document);
+    }
+
+    pub fn add_delegate(&mut self, delegate: AccountId) {
         let owner = env::predecessor_account_id();
 
         let did = self
@@ -68,9 +95,35 @@ impl DIDManager {
         let mut did_document = self.dids.get(&did).expect("DID not found");
         assert_eq!(did_document.owner, owner, "Not authorized");
         assert!(did_document.active, "DID not active");
-        assert!(!new_document_uri.is_empty(), "Document URI required");
+        assert_ne!(delegate, owner, "Owner cannot be a delegate");
+        assert!(
+            !did_document.delegates.contains(&delegate),
+            "Delegate already added"
+        );
 
-        did_document.document_uri = new_document_uri;
+        did_document.delegates.push(delegate);
+        did_document.updated_at = env::block_timestamp();
+
+        self.dids.insert(&did, &did_document);
+    }
+
+    pub fn remove_delegate(&mut self, delegate: AccountId) {
+        let owner = env::predecessor_account_id();
+
+        let did = self
+            .account_to_did
+            .get(&owner)
+            .expect("No DID registered");
+
+        let mut did_document = self.dids.get(&did).expect("DID not found");
+        assert_eq!(did_document.owner, owner, "Not authorized");
+
+        let index = did_document
+            .delegates
+            .iter()
+            .position(|d| d == &delegate)
+            .expect("Delegate not found");
+        did_document.delegates.remove(index);
         did_document.updated_at = env::block_timestamp();
 
         self.dids.insert(&did, &did_document);
@@ -118,3 +171,72 @@ impl DIDManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const DID: &str = "did:near:alice";
+
+    fn context(predecessor: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder.predecessor_account_id(predecessor);
+        builder
+    }
+
+    /// `accounts(0)` registers `DID` and appoints `accounts(1)` as delegate
+    fn setup() -> DIDManager {
+        testing_env!(context(accounts(0)).build());
+        let mut contract = DIDManager::new();
+        contract.register_did(DID.to_string(), "ipfs://v1".to_string());
+        contract.add_delegate(accounts(1));
+        contract
+    }
+
+    #[test]
+    fn delegate_updates_named_did() {
+        let mut contract = setup();
+
+        testing_env!(context(accounts(1)).build());
+        contract.update_did("ipfs://v2".to_string(), Some(DID.to_string()));
+        assert_eq!(contract.resolve_did(DID.to_string()), Some("ipfs://v2".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not authorized")]
+    fn stranger_cannot_update() {
+        let mut contract = setup();
+
+        testing_env!(context(accounts(2)).build());
+        contract.update_did("ipfs://v2".to_string(), Some(DID.to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not authorized")]
+    fn removed_delegate_loses_access() {
+        let mut contract = setup();
+        contract.remove_delegate(accounts(1));
+        assert!(contract.get_did(DID.to_string()).unwrap().delegates.is_empty());
+
+        testing_env!(context(accounts(1)).build());
+        contract.update_did("ipfs://v2".to_string(), Some(DID.to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Owner cannot be a delegate")]
+    fn owner_cannot_delegate_to_self() {
+        let mut contract = setup();
+        contract.add_delegate(accounts(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "No DID registered")]
+    fn delegate_cannot_revoke() {
+        let mut contract = setup();
+
+        testing_env!(context(accounts(1)).build());
+        contract.revoke_did();
+    }
+}
@@ -12,6 +12,7 @@ pub struct Escrow {
     pub release_time: u64,
     pub cancel_time: u64,
     pub condition_hash: Option<Vec<u8>>,
+    pub allow_clawback: bool,
     pub status: EscrowStatus,
     pub created_at: u64,
 }
@@ -22,11 +23,13 @@ pub enum EscrowStatus {
     Active,
     Executed,
     Cancelled,
+    ClawedBack,
 }
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct EscrowContract {
+    admin: AccountId,
     escrows: UnorderedMap<u64, Escrow>,
     next_id: u64,
 }
@@ -36,14 +39,21 @@ impl EscrowContract {
     #[init]
     pub fn new() -> Self {
         Self {
+            admin: env::predecessor_account_id(),
             escrows: UnorderedMap::new(b"e"),
             next_id: 0,
         }
     }
 
     #[payable]
-    pub fn create_time_locked(&mut self, receiver: AccountId, release_time: u64, cancel_time: u64) -> u64 {
-        self.create_escrow_internal(receiver, release_time, cancel_time, None)
+    pub fn create_time_locked(
+        &mut self,
+        receiver: AccountId,
+        release_time: u64,
+        cancel_time: u64,
+        allow_clawback: bool,
+    ) -> u64 {
+        self.create_escrow_internal(receiver, release_time, cancel_time, None, allow_clawback)
     }
 
     #[payable]
@@ -53,9 +63,10 @@ impl EscrowContract {
         release_time: u64,
         cancel_time: u64,
         condition_hash: Vec<u8>,
+        allow_clawback: bool,
     ) -> u64 {
         assert_eq!(condition_hash.len(), 32, "Hash must be 32 bytes");
-        self.create_escrow_internal(receiver, release_time, cancel_time, Some(condition_hash))
+        self.create_escrow_internal(receiver, release_time, cancel_time, Some(condition_hash), allow_clawback)
     }
 
     fn create_escrow_internal(
@@ -64,6 +75,7 @@ impl EscrowContract {
         release_time: u64,
         cancel_time: u64,
         condition_hash: Option<Vec<u8>>,
+        allow_clawback: bool,
     ) -> u64 {
         let sender = env::predecessor_account_id();
         let amount = env::attached_deposit();
@@ -82,6 +94,7 @@ impl EscrowContract {
             release_time,
             cancel_time,
             condition_hash,
+            allow_clawback,
             status: EscrowStatus::Active,
             created_at: env::block_timestamp(),
         };
@@ -112,18 +125,85 @@ impl EscrowContract {
         Promise::new(receiver).transfer(escrow.amount)
     }
 
+    /// Settles every listed escrow the caller can execute with the shared preimage. Escrows that
+    /// are not the caller's, outside their window, or whose hash doesn't match are skipped; at
+    /// least one escrow must settle.
+    pub fn execute_batch(&mut self, escrow_ids: Vec<u64>, preimage: Option<Vec<u8>>) -> Promise {
+        let receiver = env::predecessor_account_id();
+        let computed_hash = preimage.as_ref().map(|p| env::sha256(p));
+        let now = env::block_timestamp();
+
+        let mut batch: Option<Promise> = None;
+        for escrow_id in escrow_ids {
+            let mut escrow = match self.escrows.get(&escrow_id) {
+                Some(escrow) => escrow,
+                None => continue,
+            };
+            if escrow.receiver != receiver
+                || escrow.status != EscrowStatus::Active
+                || now < escrow.release_time
+                || now >= escrow.cancel_time
+            {
+                continue;
+            }
+            if let Some(hash) = &escrow.condition_hash {
+                match &computed_hash {
+                    Some(computed) if computed[..] == hash[..] => {}
+                    _ => continue,
+                }
+            }
+
+            escrow.status = EscrowStatus::Executed;
+            self.escrows.insert(&escrow_id, &escrow);
+            let transfer = Promise::new(receiver.clone()).transfer(escrow.amount);
+            batch = Some(match batch {
+                Some(promise) => promise.and(transfer),
+                None => transfer,
+            });
+        }
+
+        batch.expect("No executable escrow in batch")
+    }
+
     pub fn cancel_escrow(&mut self, escrow_id: u64) -> Promise {
         let sender = env::predecessor_account_id();
 
-        let mut escrow = self.escrows.get(&escrow_id).expect("Escrow not found");
+        let escrow = self.escrows.get(&escrow_id).expect("Escrow not found");
         assert_eq!(escrow.sender, sender, "Not authorized");
+
+        self.refund_expired(escrow_id, escrow)
+    }
+
+    /// Permissionless counterpart to `cancel_escrow`: once `cancel_time` has passed anyone may
+    /// return an unexecuted escrow to its sender.
+    pub fn sweep_expired(&mut self, escrow_id: u64) -> Promise {
+        let escrow = self.escrows.get(&escrow_id).expect("Escrow not found");
+        self.refund_expired(escrow_id, escrow)
+    }
+
+    fn refund_expired(&mut self, escrow_id: u64, mut escrow: Escrow) -> Promise {
         assert_eq!(escrow.status, EscrowStatus::Active, "Escrow not active");
         assert!(env::block_timestamp() >= escrow.cancel_time, "Cannot cancel yet");
 
         escrow.status = EscrowStatus::Cancelled;
         self.escrows.insert(&escrow_id, &escrow);
 
-        Promise::new(sender).transfer(escrow.amount)
+        Promise::new(escrow.sender).transfer(escrow.amount)
+    }
+
+    /// Compliance hold: the admin returns an active escrow's funds to its sender, provided the
+    /// sender opted into clawback when creating it.
+    pub fn clawback(&mut self, escrow_id: u64) -> Promise {
+        assert_eq!(env::predecessor_account_id(), self.admin, "Only admin");
+
+        let mut escrow = self.escrows.get(&escrow_id).expect("Escrow not found");
+        assert!(escrow.status == EscrowStatus::Active, "Escrow not active");
+        assert!(escrow.allow_clawback, "Clawback not allowed");
+
+        escrow.status = EscrowStatus::ClawedBack;
+        self.escrows.insert(&escrow_id, &escrow);
+
+        Promise::new(escrow.sender).transfer(escrow.amount)
     }
 
     pub fn get_escrow(&self, escrow_id: u64) -> Option<Escrow> {
@@ -141,3 +221,127 @@ impl EscrowContract {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const RELEASE: u64 = 100;
+    const CANCEL: u64 = 200;
+
+    fn context(predecessor: AccountId, deposit: Balance, timestamp: u64) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .predecessor_account_id(predecessor)
+            .attached_deposit(deposit)
+            .block_timestamp(timestamp);
+        builder
+    }
+
+    fn status(contract: &EscrowContract, escrow_id: u64) -> EscrowStatus {
+        contract.get_escrow(escrow_id).unwrap().status
+    }
+
+    #[test]
+    fn execute_batch_settles_matching_escrows() {
+        testing_env!(context(accounts(0), 0, 0).build());
+        let mut contract = EscrowContract::new();
+        let hash = env::sha256(b"secret");
+
+        testing_env!(context(accounts(0), 100, 0).build());
+        let first = contract.create_hash_locked(accounts(1), RELEASE, CANCEL, hash.clone(), false);
+        let second = contract.create_hash_locked(accounts(1), RELEASE, CANCEL, hash.clone(), false);
+        let other_receiver = contract.create_hash_locked(accounts(2), RELEASE, CANCEL, hash, false);
+        let other_hash = contract.create_hash_locked(accounts(1), RELEASE, CANCEL, env::sha256(b"other"), false);
+
+        testing_env!(context(accounts(1), 0, RELEASE).build());
+        contract.execute_batch(vec![first, second, other_receiver, other_hash], Some(b"secret".to_vec()));
+
+        assert!(status(&contract, first) == EscrowStatus::Executed);
+        assert!(status(&contract, second) == EscrowStatus::Executed);
+        assert!(status(&contract, other_receiver) == EscrowStatus::Active);
+        assert!(status(&contract, other_hash) == EscrowStatus::Active);
+    }
+
+    #[test]
+    #[should_panic(expected = "No executable escrow in batch")]
+    fn execute_batch_rejects_batch_with_nothing_to_settle() {
+        testing_env!(context(accounts(0), 0, 0).build());
+        let mut contract = EscrowContract::new();
+
+        testing_env!(context(accounts(0), 100, 0).build());
+        let escrow_id = contract.create_hash_locked(accounts(1), RELEASE, CANCEL, env::sha256(b"secret"), false);
+
+        testing_env!(context(accounts(1), 0, RELEASE).build());
+        contract.execute_batch(vec![escrow_id], Some(b"wrong".to_vec()));
+    }
+
+    /// Time-locked escrow of 100 from `accounts(0)` to `accounts(1)`, created under admin `accounts(3)`
+    fn setup(allow_clawback: bool) -> (EscrowContract, u64) {
+        testing_env!(context(accounts(3), 0, 0).build());
+        let mut contract = EscrowContract::new();
+
+        testing_env!(context(accounts(0), 100, 0).build());
+        let escrow_id = contract.create_time_locked(accounts(1), RELEASE, CANCEL, allow_clawback);
+        (contract, escrow_id)
+    }
+
+    #[test]
+    fn admin_claws_back_opted_in_escrow() {
+        let (mut contract, escrow_id) = setup(true);
+
+        testing_env!(context(accounts(3), 0, RELEASE).build());
+        contract.clawback(escrow_id);
+        assert!(status(&contract, escrow_id) == EscrowStatus::ClawedBack);
+    }
+
+    #[test]
+    #[should_panic(expected = "Clawback not allowed")]
+    fn clawback_requires_opt_in() {
+        let (mut contract, escrow_id) = setup(false);
+
+        testing_env!(context(accounts(3), 0, RELEASE).build());
+        contract.clawback(escrow_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only admin")]
+    fn clawback_is_admin_only() {
+        let (mut contract, escrow_id) = setup(true);
+
+        testing_env!(context(accounts(0), 0, RELEASE).build());
+        contract.clawback(escrow_id);
+    }
+
+    #[test]
+    fn anyone_sweeps_expired_escrow_to_sender() {
+        let (mut contract, escrow_id) = setup(false);
+
+        testing_env!(context(accounts(2), 0, CANCEL).build());
+        contract.sweep_expired(escrow_id);
+        assert!(status(&contract, escrow_id) == EscrowStatus::Cancelled);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot cancel yet")]
+    fn sweep_waits_for_cancel_time() {
+        let (mut contract, escrow_id) = setup(false);
+
+        testing_env!(context(accounts(2), 0, CANCEL - 1).build());
+        contract.sweep_expired(escrow_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Escrow not active")]
+    fn sweep_skips_executed_escrow() {
+        let (mut contract, escrow_id) = setup(false);
+
+        testing_env!(context(accounts(1), 0, RELEASE).build());
+        contract.execute_escrow(escrow_id, None);
+
+        testing_env!(context(accounts(2), 0, CANCEL).build());
+        contract.sweep_expired(escrow_id);
+    }
+}
@@ -1,27 +1,71 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::UnorderedMap;
-use near_sdk::{env, near_bindgen, AccountId, Balance, PanicOnDefault, Promise};
+use near_sdk::json_types::U128;
+use near_sdk::{
+    env, ext_contract, near_bindgen, AccountId, Balance, CurveType, Gas,
+    PanicOnDefault, Promise, PromiseOrValue, PromiseResult, PublicKey,
+};
 use serde::{Deserialize, Serialize};
 
+const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas(10_000_000_000_000);
+/// Window after a close is initiated during which a later voucher can still be submitted.
+const CHALLENGE_PERIOD: u64 = 86_400_000_000_000; // 24 hours in nanoseconds
+
+#[ext_contract(ext_ft)]
+pub trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+/// Two-way channel. Each participant deposits into its own balance and both sides
+/// exchange vouchers over `net`, the cumulative amount `participant_a` has paid
+/// `participant_b` minus what `participant_b` has paid back. At any point
+/// `participant_a` is entitled to `balance_a - net` and `participant_b` to
+/// `balance_b + net`, less what each has already withdrawn.
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Channel {
-    pub sender: AccountId,
-    pub receiver: AccountId,
-    pub balance: Balance,
-    pub total_claimed: Balance,
+    pub participant_a: AccountId,
+    pub participant_b: AccountId,
+    /// NEP-141 token contract backing the channel; `None` for native NEAR.
+    pub token: Option<AccountId>,
+    /// Keys each participant signs vouchers with. `participant_b` registers its own.
+    pub public_key_a: PublicKey,
+    pub public_key_b: Option<PublicKey>,
+    pub balance_a: Balance,
+    pub balance_b: Balance,
+    pub paid_a: Balance,
+    pub paid_b: Balance,
+    /// Net settlement from the highest-nonce voucher seen; positive favours `participant_b`.
+    pub net: i128,
+    pub highest_nonce: u64,
     pub expiration: u64,
     pub status: ChannelStatus,
+    /// End of the challenge window while the channel is `Closing`.
+    pub closing_until: u64,
     pub created_at: u64,
 }
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub enum ChannelStatus {
     Open,
+    Closing,
     Closed,
 }
 
+/// `msg` payload accepted by `ft_on_transfer`.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum TokenChannelMsg {
+    Create {
+        participant_b: AccountId,
+        expiration: u64,
+        public_key: PublicKey,
+    },
+    Fund { channel_id: u64 },
+}
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct PaymentChannels {
@@ -40,90 +84,677 @@ impl PaymentChannels {
     }
 
     #[payable]
-    pub fn create_channel(&mut self, receiver: AccountId, expiration: u64) -> u64 {
-        let sender = env::predecessor_account_id();
+    pub fn create_channel(
+        &mut self,
+        participant_b: AccountId,
+        expiration: u64,
+        public_key: PublicKey,
+    ) -> u64 {
+        let participant_a = env::predecessor_account_id();
         let deposit = env::attached_deposit();
 
-        assert_ne!(sender, receiver, "Cannot create channel with self");
-        assert!(deposit > 0, "Deposit required");
-        assert!(expiration > env::block_timestamp(), "Invalid expiration");
+        self.open_channel(participant_a, participant_b, None, public_key, deposit, expiration)
+    }
 
-        let channel_id = self.next_id;
-        self.next_id += 1;
+    /// Lets `participant_b` set the key its vouchers are checked against. Can only be set once.
+    pub fn register_public_key(&mut self, channel_id: u64, public_key: PublicKey) {
+        let caller = env::predecessor_account_id();
 
-        let channel = Channel {
-            sender,
-            receiver,
-            balance: deposit,
-            total_claimed: 0,
-            expiration,
-            status: ChannelStatus::Open,
-            created_at: env::block_timestamp(),
-        };
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert_eq!(channel.participant_b, caller, "Not authorized");
+        assert!(channel.public_key_b.is_none(), "Key already registered");
 
+        channel.public_key_b = Some(public_key);
         self.channels.insert(&channel_id, &channel);
-        channel_id
     }
 
     #[payable]
     pub fn add_funds(&mut self, channel_id: u64) {
-        let sender = env::predecessor_account_id();
+        let participant = env::predecessor_account_id();
         let deposit = env::attached_deposit();
 
+        let channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert!(channel.token.is_none(), "Token channel, fund via ft_transfer_call");
+
+        self.fund_channel(channel_id, participant, deposit);
+    }
+
+    /// NEP-141 receiver hook. Token channels are created or topped up by calling
+    /// `ft_transfer_call` on the token with a `TokenChannelMsg` as `msg`.
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let token = env::predecessor_account_id();
+        let parsed: TokenChannelMsg =
+            near_sdk::serde_json::from_str(&msg).expect("Invalid channel message");
+
+        match parsed {
+            TokenChannelMsg::Create {
+                participant_b,
+                expiration,
+                public_key,
+            } => {
+                self.open_channel(
+                    sender_id,
+                    participant_b,
+                    Some(token),
+                    public_key,
+                    amount.0,
+                    expiration,
+                );
+            }
+            TokenChannelMsg::Fund { channel_id } => {
+                let channel = self.channels.get(&channel_id).expect("Channel not found");
+                assert_eq!(channel.token, Some(token), "Wrong token for channel");
+                self.fund_channel(channel_id, sender_id, amount.0);
+            }
+        }
+
+        PromiseOrValue::Value(U128(0))
+    }
+
+    /// Claims the caller's share under a voucher signed by the other participant by starting
+    /// the close. Nothing is paid until `settle_channel`, so the other participant can still
+    /// replace a stale voucher with a higher-nonce one during the challenge period.
+    pub fn claim_with_voucher(
+        &mut self,
+        channel_id: u64,
+        net: i128,
+        nonce: u64,
+        signature: Vec<u8>,
+    ) {
+        let caller = env::predecessor_account_id();
+
         let mut channel = self.channels.get(&channel_id).expect("Channel not found");
-        assert_eq!(channel.sender, sender, "Not authorized");
-        assert_eq!(channel.status, ChannelStatus::Open, "Channel closed");
-        assert!(deposit > 0, "Deposit required");
+        assert_eq!(channel.status, ChannelStatus::Open, "Channel not open");
+        Self::apply_voucher(&mut channel, channel_id, &caller, net, nonce, &signature);
 
-        channel.balance += deposit;
+        Self::start_closing(&mut channel);
         self.channels.insert(&channel_id, &channel);
     }
 
-    pub fn claim_funds(&mut self, channel_id: u64, amount: Balance) -> Promise {
-        let receiver = env::predecessor_account_id();
+    /// Starts closing the channel on the last recorded net. Before the channel expires a
+    /// participant can only force a close by presenting a voucher through `claim_with_voucher`.
+    pub fn close_channel(&mut self, channel_id: u64) {
+        let caller = env::predecessor_account_id();
 
         let mut channel = self.channels.get(&channel_id).expect("Channel not found");
-        assert_eq!(channel.receiver, receiver, "Not authorized");
-        assert_eq!(channel.status, ChannelStatus::Open, "Channel closed");
-
-        let available = channel.balance - channel.total_claimed;
-        assert!(amount <= available, "Insufficient funds");
+        assert_eq!(channel.status, ChannelStatus::Open, "Channel not open");
+        assert!(
+            channel.participant_a == caller || channel.participant_b == caller,
+            "Not authorized"
+        );
+        assert!(
+            env::block_timestamp() >= channel.expiration,
+            "Channel not expired"
+        );
 
-        channel.total_claimed += amount;
+        Self::start_closing(&mut channel);
         self.channels.insert(&channel_id, &channel);
-
-        Promise::new(receiver).transfer(amount)
     }
 
-    pub fn close_channel(&mut self, channel_id: u64) -> Promise {
+    /// Submits a higher-nonce voucher, signed by the other participant, while the
+    /// channel is closing. It replaces the net used at settlement.
+    pub fn challenge_close(&mut self, channel_id: u64, net: i128, nonce: u64, signature: Vec<u8>) {
         let caller = env::predecessor_account_id();
 
         let mut channel = self.channels.get(&channel_id).expect("Channel not found");
-        assert_eq!(channel.status, ChannelStatus::Open, "Channel already closed");
+        assert_eq!(channel.status, ChannelStatus::Closing, "Channel not closing");
+        assert!(
+            env::block_timestamp() < channel.closing_until,
+            "Challenge period over"
+        );
+        Self::apply_voucher(&mut channel, channel_id, &caller, net, nonce, &signature);
 
-        if channel.sender == caller {
-            assert!(env::block_timestamp() >= channel.expiration, "Not expired");
-        } else {
-            assert_eq!(channel.receiver, caller, "Not authorized");
-        }
+        self.channels.insert(&channel_id, &channel);
+    }
+
+    /// Pays out a closing channel after the challenge period, splitting the deposits
+    /// according to the final net and what each side has already withdrawn.
+    pub fn settle_channel(&mut self, channel_id: u64) -> PromiseOrValue<bool> {
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert_eq!(channel.status, ChannelStatus::Closing, "Channel not closing");
+        assert!(
+            env::block_timestamp() >= channel.closing_until,
+            "Challenge period active"
+        );
+
+        let (entitled_a, paid_a) = Self::position(&channel, &channel.participant_a);
+        let (entitled_b, paid_b) = Self::position(&channel, &channel.participant_b);
+        let to_a = entitled_a - paid_a;
+        let to_b = entitled_b - paid_b;
 
+        channel.paid_a = entitled_a;
+        channel.paid_b = entitled_b;
         channel.status = ChannelStatus::Closed;
         self.channels.insert(&channel_id, &channel);
 
-        let remaining = channel.balance - channel.total_claimed;
-        if remaining > 0 {
-            Promise::new(channel.sender).transfer(remaining)
-        } else {
-            Promise::new(caller).transfer(0)
+        let mut payouts = Vec::new();
+        if to_a > 0 {
+            payouts.push((channel.participant_a.clone(), to_a));
+        }
+        if to_b > 0 {
+            payouts.push((channel.participant_b.clone(), to_b));
+        }
+
+        let mut promise: Option<Promise> = None;
+        for (account, amount) in payouts {
+            let payout = match &channel.token {
+                Some(token) => Self::ft_payout(token.clone(), account, amount),
+                None => Promise::new(account).transfer(amount),
+            };
+            promise = Some(match promise {
+                Some(p) => p.and(payout),
+                None => payout,
+            });
+        }
+
+        match promise {
+            Some(p) => p
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                        .resolve_settle(channel_id, U128(to_a), U128(to_b)),
+                )
+                .into(),
+            None => PromiseOrValue::Value(true),
         }
     }
 
+    /// Rolls back any failed payout from a settlement and returns the channel to
+    /// `Closing` so `settle_channel` can be retried for the outstanding amounts.
+    #[private]
+    pub fn resolve_settle(&mut self, channel_id: u64, to_a: U128, to_b: U128) -> bool {
+        let mut index = 0;
+        let mut failed = |amount: u128| {
+            if amount == 0 {
+                return false;
+            }
+            let result = env::promise_result(index);
+            index += 1;
+            !matches!(result, PromiseResult::Successful(_))
+        };
+        let a_failed = failed(to_a.0);
+        let b_failed = failed(to_b.0);
+
+        if !a_failed && !b_failed {
+            return true;
+        }
+
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        if a_failed {
+            channel.paid_a -= to_a.0;
+        }
+        if b_failed {
+            channel.paid_b -= to_b.0;
+        }
+        channel.status = ChannelStatus::Closing;
+        self.channels.insert(&channel_id, &channel);
+        false
+    }
+
     pub fn get_channel(&self, channel_id: u64) -> Option<Channel> {
         self.channels.get(&channel_id)
     }
 
-    pub fn get_available_balance(&self, channel_id: u64) -> Balance {
+    /// Amount `account` could still withdraw under the channel's current net.
+    pub fn get_available_balance(&self, channel_id: u64, account: AccountId) -> Balance {
         let channel = self.channels.get(&channel_id).expect("Channel not found");
-        channel.balance - channel.total_claimed
+        let (entitled, paid) = Self::position(&channel, &account);
+        entitled.saturating_sub(paid)
+    }
+
+    /// Bytes a participant signs for a voucher:
+    /// `contract account id || channel_id (u64 LE) || net (i128 LE) || nonce (u64 LE)`.
+    /// The account id keeps a voucher from being replayed against another deployment.
+    pub fn voucher_message(channel_id: u64, net: i128, nonce: u64) -> Vec<u8> {
+        let contract = env::current_account_id();
+        let mut message = Vec::with_capacity(contract.as_bytes().len() + 32);
+        message.extend_from_slice(contract.as_bytes());
+        message.extend_from_slice(&channel_id.to_le_bytes());
+        message.extend_from_slice(&net.to_le_bytes());
+        message.extend_from_slice(&nonce.to_le_bytes());
+        message
+    }
+
+    /// Checks a voucher submitted by `submitter` against the other participant's key
+    /// and records its net and nonce on the channel.
+    fn apply_voucher(
+        channel: &mut Channel,
+        channel_id: u64,
+        submitter: &AccountId,
+        net: i128,
+        nonce: u64,
+        signature: &[u8],
+    ) {
+        let counterparty_key = if *submitter == channel.participant_a {
+            channel.public_key_b.as_ref().expect("Counterparty key not registered")
+        } else {
+            assert_eq!(channel.participant_b, *submitter, "Not authorized");
+            &channel.public_key_a
+        };
+        assert!(nonce > channel.highest_nonce, "Stale voucher nonce");
+        assert!(
+            net >= channel.paid_b as i128 - channel.balance_b as i128
+                && net <= channel.balance_a as i128 - channel.paid_a as i128,
+            "Net exceeds channel balances"
+        );
+        Self::verify_voucher(counterparty_key, channel_id, net, nonce, signature);
+
+        channel.net = net;
+        channel.highest_nonce = nonce;
+    }
+
+    fn verify_voucher(
+        public_key: &PublicKey,
+        channel_id: u64,
+        net: i128,
+        nonce: u64,
+        signature: &[u8],
+    ) {
+        assert!(
+            public_key.curve_type() == CurveType::ED25519,
+            "Unsupported key type"
+        );
+        let key: &[u8; 32] = public_key.as_bytes()[1..]
+            .try_into()
+            .expect("Invalid public key");
+        let signature: &[u8; 64] = signature.try_into().expect("Invalid signature length");
+        let message = Self::voucher_message(channel_id, net, nonce);
+        assert!(
+            env::ed25519_verify(signature, &message, key),
+            "Invalid voucher signature"
+        );
+    }
+
+    /// Returns `(entitled, paid)` for a participant under the channel's current net.
+    fn position(channel: &Channel, account: &AccountId) -> (Balance, Balance) {
+        if *account == channel.participant_a {
+            ((channel.balance_a as i128 - channel.net) as Balance, channel.paid_a)
+        } else {
+            assert_eq!(channel.participant_b, *account, "Not a participant");
+            ((channel.balance_b as i128 + channel.net) as Balance, channel.paid_b)
+        }
+    }
+
+    fn start_closing(channel: &mut Channel) {
+        channel.status = ChannelStatus::Closing;
+        channel.closing_until = env::block_timestamp() + CHALLENGE_PERIOD;
+    }
+
+    fn open_channel(
+        &mut self,
+        participant_a: AccountId,
+        participant_b: AccountId,
+        token: Option<AccountId>,
+        public_key_a: PublicKey,
+        deposit: Balance,
+        expiration: u64,
+    ) -> u64 {
+        assert_ne!(participant_a, participant_b, "Cannot create channel with self");
+        assert!(deposit > 0, "Deposit required");
+        assert!(expiration > env::block_timestamp(), "Invalid expiration");
+
+        let channel_id = self.next_id;
+        self.next_id += 1;
+
+        let channel = Channel {
+            participant_a,
+            participant_b,
+            token,
+            public_key_a,
+            public_key_b: None,
+            balance_a: deposit,
+            balance_b: 0,
+            paid_a: 0,
+            paid_b: 0,
+            net: 0,
+            highest_nonce: 0,
+            expiration,
+            status: ChannelStatus::Open,
+            closing_until: 0,
+            created_at: env::block_timestamp(),
+        };
+
+        self.channels.insert(&channel_id, &channel);
+        channel_id
+    }
+
+    fn fund_channel(&mut self, channel_id: u64, participant: AccountId, deposit: Balance) {
+        let mut channel = self.channels.get(&channel_id).expect("Channel not found");
+        assert_eq!(channel.status, ChannelStatus::Open, "Channel not open");
+        assert!(env::block_timestamp() < channel.expiration, "Channel expired");
+        assert!(deposit > 0, "Deposit required");
+
+        if participant == channel.participant_a {
+            channel.balance_a += deposit;
+        } else {
+            assert_eq!(channel.participant_b, participant, "Not authorized");
+            channel.balance_b += deposit;
+        }
+        self.channels.insert(&channel_id, &channel);
+    }
+
+    fn ft_payout(token: AccountId, receiver: AccountId, amount: Balance) -> Promise {
+        ext_ft::ext(token)
+            .with_attached_deposit(1)
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(receiver, U128(amount), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use near_sdk::mock::VmAction;
+    use near_sdk::serde_json::json;
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const EXPIRATION: u64 = 1_000_000_000_000;
+
+    fn context(predecessor: AccountId, deposit: Balance, timestamp: u64) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .predecessor_account_id(predecessor)
+            .attached_deposit(deposit)
+            .block_timestamp(timestamp);
+        builder
+    }
+
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn public_key(key: &SigningKey) -> PublicKey {
+        let mut bytes = vec![0u8];
+        bytes.extend_from_slice(key.verifying_key().as_bytes());
+        PublicKey::try_from(bytes).unwrap()
+    }
+
+    fn voucher(key: &SigningKey, channel_id: u64, net: i128, nonce: u64) -> Vec<u8> {
+        key.sign(&PaymentChannels::voucher_message(channel_id, net, nonce))
+            .to_bytes()
+            .to_vec()
+    }
+
+    fn token() -> AccountId {
+        "token.near".parse().unwrap()
+    }
+
+    /// `ft_transfer` calls scheduled so far, as (token, receiver, amount)
+    fn ft_transfers() -> Vec<(AccountId, AccountId, Balance)> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let token = receipt.receiver_id.clone();
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    VmAction::FunctionCall { function_name, args, .. } if function_name == "ft_transfer" => {
+                        let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(&args).unwrap();
+                        let receiver = args["receiver_id"].as_str().unwrap().parse().unwrap();
+                        let amount = args["amount"].as_str().unwrap().parse().unwrap();
+                        Some((token.clone(), receiver, amount))
+                    }
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    /// `token()` channel funded with 1000 by `accounts(0)`, who signs with key 1
+    fn token_setup() -> (PaymentChannels, u64) {
+        testing_env!(context(accounts(0), 0, 0).build());
+        let mut contract = PaymentChannels::new();
+
+        testing_env!(context(token(), 0, 0).build());
+        let msg = json!({
+            "create": {
+                "participant_b": accounts(1),
+                "expiration": EXPIRATION,
+                "public_key": public_key(&signing_key(1)),
+            }
+        });
+        contract.ft_on_transfer(accounts(0), U128(1000), msg.to_string());
+        (contract, 0)
+    }
+
+    /// Channel funded with 1000 by `accounts(0)`, who signs with key 1; `accounts(1)`
+    /// registers key 2.
+    fn setup() -> (PaymentChannels, u64) {
+        testing_env!(context(accounts(0), 1000, 0).build());
+        let mut contract = PaymentChannels::new();
+        let channel_id = contract.create_channel(accounts(1), EXPIRATION, public_key(&signing_key(1)));
+
+        testing_env!(context(accounts(1), 0, 0).build());
+        contract.register_public_key(channel_id, public_key(&signing_key(2)));
+        (contract, channel_id)
+    }
+
+    #[test]
+    fn receiver_close_settles_after_challenge_period() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(1), 0, 10).build());
+        contract.claim_with_voucher(channel_id, 300, 1, voucher(&signing_key(1), channel_id, 300, 1));
+        let channel = contract.get_channel(channel_id).unwrap();
+        assert_eq!(channel.status, ChannelStatus::Closing);
+        assert_eq!(channel.closing_until, 10 + CHALLENGE_PERIOD);
+        // Nothing is paid out while the close can still be challenged
+        assert_eq!(channel.paid_b, 0);
+
+        testing_env!(context(accounts(2), 0, 10 + CHALLENGE_PERIOD).build());
+        contract.settle_channel(channel_id);
+        let channel = contract.get_channel(channel_id).unwrap();
+        assert_eq!(channel.status, ChannelStatus::Closed);
+        assert_eq!(channel.paid_a, 700);
+        assert_eq!(channel.paid_b, 300);
+    }
+
+    #[test]
+    fn sender_challenge_adjusts_final_split() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(1), 0, 10).build());
+        contract.claim_with_voucher(channel_id, 300, 1, voucher(&signing_key(1), channel_id, 300, 1));
+
+        // `accounts(0)` holds a later state, countersigned by `accounts(1)`, with 100 paid back
+        testing_env!(context(accounts(0), 0, 20).build());
+        contract.challenge_close(channel_id, 200, 2, voucher(&signing_key(2), channel_id, 200, 2));
+
+        testing_env!(context(accounts(0), 0, 10 + CHALLENGE_PERIOD).build());
+        contract.settle_channel(channel_id);
+        let channel = contract.get_channel(channel_id).unwrap();
+        assert_eq!(channel.net, 200);
+        assert_eq!(channel.paid_a, 800);
+        assert_eq!(channel.paid_b, 200);
+    }
+
+    #[test]
+    #[should_panic(expected = "Stale voucher nonce")]
+    fn challenge_requires_higher_nonce() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(1), 0, 10).build());
+        contract.claim_with_voucher(channel_id, 300, 5, voucher(&signing_key(1), channel_id, 300, 5));
+
+        testing_env!(context(accounts(0), 0, 20).build());
+        contract.challenge_close(channel_id, 100, 4, voucher(&signing_key(2), channel_id, 100, 4));
+    }
+
+    #[test]
+    #[should_panic(expected = "Challenge period active")]
+    fn settle_waits_for_challenge_period() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(1), 0, 10).build());
+        contract.claim_with_voucher(channel_id, 300, 1, voucher(&signing_key(1), channel_id, 300, 1));
+        contract.settle_channel(channel_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel not expired")]
+    fn bare_close_waits_for_expiration() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(0), 0, EXPIRATION - 1).build());
+        contract.close_channel(channel_id);
+    }
+
+    #[test]
+    fn bare_close_after_expiration_refunds_funder() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(0), 0, EXPIRATION).build());
+        contract.close_channel(channel_id);
+
+        testing_env!(context(accounts(0), 0, EXPIRATION + CHALLENGE_PERIOD).build());
+        contract.settle_channel(channel_id);
+        let channel = contract.get_channel(channel_id).unwrap();
+        assert_eq!(channel.paid_a, 1000);
+        assert_eq!(channel.paid_b, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel expired")]
+    fn funding_rejected_after_expiration() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(1), 500, EXPIRATION).build());
+        contract.add_funds(channel_id);
+    }
+
+    #[test]
+    fn both_participants_fund_and_settle_on_net() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(1), 400, 5).build());
+        contract.add_funds(channel_id);
+        testing_env!(context(accounts(0), 100, 6).build());
+        contract.add_funds(channel_id);
+        let channel = contract.get_channel(channel_id).unwrap();
+        assert_eq!((channel.balance_a, channel.balance_b), (1100, 400));
+
+        // Net -250: `accounts(1)` has paid `accounts(0)` 250 more than it received
+        testing_env!(context(accounts(0), 0, 10).build());
+        contract.claim_with_voucher(channel_id, -250, 3, voucher(&signing_key(2), channel_id, -250, 3));
+        assert_eq!(contract.get_available_balance(channel_id, accounts(0)), 1350);
+        assert_eq!(contract.get_available_balance(channel_id, accounts(1)), 150);
+
+        testing_env!(context(accounts(1), 0, 10 + CHALLENGE_PERIOD).build());
+        contract.settle_channel(channel_id);
+        let channel = contract.get_channel(channel_id).unwrap();
+        assert_eq!((channel.paid_a, channel.paid_b), (1350, 150));
+    }
+
+    #[test]
+    #[should_panic(expected = "Net exceeds channel balances")]
+    fn net_bounded_by_payer_balance() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(0), 0, 10).build());
+        contract.claim_with_voucher(channel_id, -1, 1, voucher(&signing_key(2), channel_id, -1, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid voucher signature")]
+    fn voucher_bound_to_contract_account() {
+        let (mut contract, channel_id) = setup();
+
+        let mut builder = context(accounts(3), 0, 10);
+        testing_env!(builder.current_account_id(accounts(4)).build());
+        let foreign = voucher(&signing_key(1), channel_id, 300, 1);
+
+        testing_env!(context(accounts(1), 0, 10).build());
+        contract.claim_with_voucher(channel_id, 300, 1, foreign);
+    }
+
+    #[test]
+    fn token_transfer_opens_and_funds_channel() {
+        let (mut contract, channel_id) = token_setup();
+        let channel = contract.get_channel(channel_id).unwrap();
+        assert_eq!(channel.token, Some(token()));
+        assert_eq!(channel.participant_a, accounts(0));
+        assert_eq!(channel.balance_a, 1000);
+
+        testing_env!(context(token(), 0, 0).build());
+        let msg = json!({ "fund": { "channel_id": channel_id } });
+        contract.ft_on_transfer(accounts(1), U128(500), msg.to_string());
+        assert_eq!(contract.get_channel(channel_id).unwrap().balance_b, 500);
+    }
+
+    #[test]
+    #[should_panic(expected = "Wrong token for channel")]
+    fn token_channel_rejects_other_token() {
+        let (mut contract, channel_id) = token_setup();
+
+        testing_env!(context(accounts(4), 0, 0).build());
+        let msg = json!({ "fund": { "channel_id": channel_id } });
+        contract.ft_on_transfer(accounts(0), U128(500), msg.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Token channel, fund via ft_transfer_call")]
+    fn token_channel_rejects_native_funding() {
+        let (mut contract, channel_id) = token_setup();
+
+        testing_env!(context(accounts(0), 100, 0).build());
+        contract.add_funds(channel_id);
+    }
+
+    #[test]
+    fn token_channel_settles_in_token() {
+        let (mut contract, channel_id) = token_setup();
+
+        testing_env!(context(accounts(1), 0, 10).build());
+        contract.register_public_key(channel_id, public_key(&signing_key(2)));
+        contract.claim_with_voucher(channel_id, 300, 1, voucher(&signing_key(1), channel_id, 300, 1));
+
+        testing_env!(context(accounts(2), 0, 10 + CHALLENGE_PERIOD).build());
+        contract.settle_channel(channel_id);
+        assert_eq!(
+            ft_transfers(),
+            vec![(token(), accounts(0), 700), (token(), accounts(1), 300)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid voucher signature")]
+    fn voucher_must_be_signed_by_sender_key() {
+        let (mut contract, channel_id) = setup();
+
+        // Signed with the receiver's own key rather than `accounts(0)`'s
+        testing_env!(context(accounts(1), 0, 10).build());
+        contract.claim_with_voucher(channel_id, 300, 1, voucher(&signing_key(2), channel_id, 300, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid voucher signature")]
+    fn voucher_signature_covers_amount() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(1), 0, 10).build());
+        contract.claim_with_voucher(channel_id, 900, 1, voucher(&signing_key(1), channel_id, 300, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not authorized")]
+    fn voucher_claims_are_participant_only() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(2), 0, 10).build());
+        contract.claim_with_voucher(channel_id, 300, 1, voucher(&signing_key(1), channel_id, 300, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Key already registered")]
+    fn receiver_key_is_registered_once() {
+        let (mut contract, channel_id) = setup();
+
+        testing_env!(context(accounts(1), 0, 10).build());
+        contract.register_public_key(channel_id, public_key(&signing_key(3)));
     }
 }
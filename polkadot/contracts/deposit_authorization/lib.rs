@@ -10,6 +10,7 @@ mod deposit_authorization {
         compliance_officer: AccountId,
         authorizations: Mapping<(AccountId, AccountId, AssetId), Authorization>,
         global_authorizations: Mapping<AccountId, GlobalAuth>,
+        blocked_jurisdictions: u32,
         auth_counter: u64,
     }
 
@@ -79,6 +80,13 @@ mod deposit_authorization {
         kyc_level: u8,
     }
 
+    #[ink(event)]
+    pub struct BlockedJurisdictionsUpdated {
+        #[ink(topic)]
+        officer: AccountId,
+        blocked_jurisdictions: u32,
+    }
+
     impl DepositAuthorization {
         #[ink(constructor)]
         pub fn new(compliance_officer: AccountId) -> Self {
@@ -87,6 +95,7 @@ mod deposit_authorization {
                 compliance_officer,
                 authorizations: Mapping::new(),
                 global_authorizations: Mapping::new(),
+                blocked_jurisdictions: 0,
                 auth_counter: 0,
             }
         }
@@ -204,6 +213,19 @@ mod deposit_authorization {
             self.global_authorizations.insert(account, &global_auth);
         }
 
+        #[ink(message)]
+        pub fn set_blocked_jurisdictions(&mut self, blocked_jurisdictions: u32) {
+            let caller = self.env().caller();
+            assert!(caller == self.compliance_officer, "Only compliance officer");
+
+            self.blocked_jurisdictions = blocked_jurisdictions;
+
+            self.env().emit_event(BlockedJurisdictionsUpdated {
+                officer: caller,
+                blocked_jurisdictions,
+            });
+        }
+
         #[ink(message)]
         pub fn check_authorization(
             &self,
@@ -214,7 +236,10 @@ mod deposit_authorization {
         ) -> bool {
             let global_auth = self.global_authorizations.get(authorized);
             if let Some(ga) = global_auth {
-                if ga.blocked || !ga.aml_verified {
+                if ga.blocked
+                    || !ga.aml_verified
+                    || ga.jurisdictions & self.blocked_jurisdictions != 0
+                {
                     return false;
                 }
             }
@@ -243,9 +268,35 @@ mod deposit_authorization {
             self.global_authorizations.get(account)
         }
 
+        #[ink(message)]
+        pub fn get_blocked_jurisdictions(&self) -> u32 {
+            self.blocked_jurisdictions
+        }
+
         #[ink(message)]
         pub fn get_auth_count(&self) -> u64 {
             self.auth_counter
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn blocked_jurisdiction_fails_check() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = DepositAuthorization::new(accounts.alice);
+
+            contract.create_authorization(accounts.bob, 1, 1000, 100, AuthTier::Basic);
+            contract.set_global_auth(accounts.bob, 1, true, 0b0110);
+            assert!(contract.check_authorization(accounts.alice, accounts.bob, 1, 500));
+
+            contract.set_blocked_jurisdictions(0b0100);
+            assert!(!contract.check_authorization(accounts.alice, accounts.bob, 1, 500));
+
+            contract.set_blocked_jurisdictions(0b1000);
+            assert!(contract.check_authorization(accounts.alice, accounts.bob, 1, 500));
+        }
+    }
 }
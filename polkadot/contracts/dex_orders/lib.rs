@@ -2,14 +2,20 @@
 
 #[ink::contract]
 mod dex_orders {
+    use ink::prelude::vec::Vec;
     use ink::storage::Mapping;
 
+    /// Maximum number of resting orders a single maker may have in the books
+    const MAX_OPEN_ORDERS_PER_MAKER: u32 = 16;
+
     #[ink(storage)]
     pub struct DEXOrders {
         admin: AccountId,
         orders: Mapping<u64, Order>,
         order_counter: u64,
         orderbook: Mapping<(AssetId, AssetId), Vec<u64>>,
+        /// Resting orders per maker, bounded by `MAX_OPEN_ORDERS_PER_MAKER`
+        open_orders: Mapping<AccountId, u32>,
     }
 
     pub type AssetId = u32;
@@ -56,12 +62,61 @@ mod dex_orders {
         amount: Balance,
     }
 
+    #[ink(event)]
+    pub struct OrdersMatched {
+        #[ink(topic)]
+        taker_order_id: u64,
+        #[ink(topic)]
+        maker_order_id: u64,
+        /// Amount of the maker order's sell asset delivered to the taker
+        maker_fill: Balance,
+        /// Amount of the taker order's sell asset delivered to the maker
+        taker_fill: Balance,
+    }
+
     #[ink(event)]
     pub struct OrderCancelled {
         #[ink(topic)]
         order_id: u64,
     }
 
+    /// Full 256-bit product `a * b` as `(high, low)` words. Price products of
+    /// 18-decimal amounts overflow `u128`, and saturating would misprice them.
+    fn wide_mul(a: Balance, b: Balance) -> (u128, u128) {
+        const MASK: u128 = u64::MAX as u128;
+        let (a_hi, a_lo) = (a >> 64, a & MASK);
+        let (b_hi, b_lo) = (b >> 64, b & MASK);
+        let lo_lo = a_lo * b_lo;
+        let lo_hi = a_lo * b_hi;
+        let hi_lo = a_hi * b_lo;
+        let mid = (lo_lo >> 64) + (lo_hi & MASK) + (hi_lo & MASK);
+        let high = a_hi * b_hi + (lo_hi >> 64) + (hi_lo >> 64) + (mid >> 64);
+        (high, (lo_lo & MASK) | (mid << 64))
+    }
+
+    /// `a * b / divisor`, rounded down or up, or `None` if the quotient does not fit
+    fn mul_div(a: Balance, b: Balance, divisor: Balance, round_up: bool) -> Option<Balance> {
+        let (mut remainder, low) = wide_mul(a, b);
+        if remainder >= divisor {
+            return None;
+        }
+        let mut quotient: u128 = 0;
+        for bit in (0..128).rev() {
+            let carry = remainder >> 127;
+            remainder = (remainder << 1) | ((low >> bit) & 1);
+            quotient <<= 1;
+            if carry == 1 || remainder >= divisor {
+                remainder = remainder.wrapping_sub(divisor);
+                quotient |= 1;
+            }
+        }
+        if round_up && remainder > 0 {
+            quotient.checked_add(1)
+        } else {
+            Some(quotient)
+        }
+    }
+
     impl DEXOrders {
         #[ink(constructor)]
         pub fn new() -> Self {
@@ -70,6 +125,7 @@ mod dex_orders {
                 orders: Mapping::new(),
                 order_counter: 0,
                 orderbook: Mapping::new(),
+                open_orders: Mapping::new(),
             }
         }
 
@@ -105,6 +161,13 @@ mod dex_orders {
                 created_at: current_block,
             };
 
+            let open_orders = self.open_orders.get(maker).unwrap_or(0);
+            assert!(open_orders < MAX_OPEN_ORDERS_PER_MAKER, "Too many open orders");
+            self.open_orders.insert(maker, &(open_orders + 1));
+
+            let mut book = self.orderbook.get((sell_asset, buy_asset)).unwrap_or_default();
+            book.push(order_id);
+            self.orderbook.insert((sell_asset, buy_asset), &book);
             self.orders.insert(order_id, &order);
 
             self.env().emit_event(OrderPlaced {
@@ -116,9 +179,25 @@ mod dex_orders {
                 buy_amount,
             });
 
+            self.match_against_book(order_id);
+
             order_id
         }
 
+        /// Cross an open order against the best-priced resting orders on the
+        /// opposite side of its pair. Fills execute at the resting order's price.
+        /// Returns the amount of the order's sell asset filled by this call.
+        #[ink(message)]
+        pub fn match_order(&mut self, order_id: u64) -> Balance {
+            let order = self.orders.get(order_id).expect("Order not found");
+            assert!(
+                matches!(order.status, OrderStatus::Open | OrderStatus::PartiallyFilled),
+                "Order not available"
+            );
+
+            self.match_against_book(order_id)
+        }
+
         #[ink(message, payable)]
         pub fn fill_order(&mut self, order_id: u64, fill_amount: Balance) {
             let taker = self.env().caller();
@@ -150,6 +229,9 @@ mod dex_orders {
             }
 
             self.orders.insert(order_id, &order);
+            if matches!(order.status, OrderStatus::Filled) {
+                self.remove_from_book(&order);
+            }
 
             self.env()
                 .transfer(taker, actual_fill)
@@ -187,6 +269,7 @@ mod dex_orders {
             let refund = order.sell_amount - order.filled;
             order.status = OrderStatus::Cancelled;
             self.orders.insert(order_id, &order);
+            self.remove_from_book(&order);
 
             if refund > 0 {
                 self.env()
@@ -197,6 +280,11 @@ mod dex_orders {
             self.env().emit_event(OrderCancelled { order_id });
         }
 
+        #[ink(message)]
+        pub fn get_orderbook(&self, sell_asset: AssetId, buy_asset: AssetId) -> Vec<u64> {
+            self.orderbook.get((sell_asset, buy_asset)).unwrap_or_default()
+        }
+
         #[ink(message)]
         pub fn get_order(&self, order_id: u64) -> Option<Order> {
             self.orders.get(order_id)
@@ -207,21 +295,349 @@ mod dex_orders {
             self.orders.get(order_id).map(|o| (o.buy_amount, o.sell_amount))
         }
 
+        /// Open and partially filled orders selling `sell_asset` for `buy_asset`
+        #[ink(message)]
+        pub fn get_orders_for_pair(&self, sell_asset: AssetId, buy_asset: AssetId) -> Vec<Order> {
+            self.orderbook
+                .get((sell_asset, buy_asset))
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|id| self.orders.get(id))
+                .filter(|o| matches!(o.status, OrderStatus::Open | OrderStatus::PartiallyFilled))
+                .collect()
+        }
+
+        /// Price `(buy_amount, sell_amount)` of the resting order giving the most
+        /// `sell_asset` per unit of `buy_asset`, or `None` if the book is empty
+        #[ink(message)]
+        pub fn get_best_price(&self, sell_asset: AssetId, buy_asset: AssetId) -> Option<(Balance, Balance)> {
+            self.best_order(sell_asset, buy_asset, None)
+                .map(|o| (o.buy_amount, o.sell_amount))
+        }
+
         #[ink(message)]
         pub fn get_order_count(&self) -> u64 {
             self.order_counter
         }
+
+        fn match_against_book(&mut self, order_id: u64) -> Balance {
+            let mut taker = self.orders.get(order_id).expect("Order not found");
+            let filled_before = taker.filled;
+
+            while taker.filled < taker.sell_amount {
+                // Resting orders selling what the taker buys, for what the taker sells
+                let Some(mut maker) = self.best_order(taker.buy_asset, taker.sell_asset, Some(taker.maker)) else {
+                    break;
+                };
+
+                // Crosses when the maker gives at least the taker's asking rate
+                if wide_mul(maker.sell_amount, taker.sell_amount) < wide_mul(maker.buy_amount, taker.buy_amount) {
+                    break;
+                }
+
+                let taker_remaining = taker.sell_amount - taker.filled;
+                let maker_remaining = maker.sell_amount - maker.filled;
+                let maker_fill = core::cmp::min(
+                    maker_remaining,
+                    mul_div(taker_remaining, maker.sell_amount, maker.buy_amount, false).unwrap_or(Balance::MAX),
+                );
+                // Round up so the maker never receives less than its price; this
+                // cannot exceed `taker_remaining` since `maker_fill` was rounded down
+                let taker_fill = mul_div(maker_fill, maker.buy_amount, maker.sell_amount, true)
+                    .expect("Fill exceeds taker order");
+                if maker_fill == 0 || taker_fill == 0 {
+                    break;
+                }
+
+                maker.filled += maker_fill;
+                taker.filled += taker_fill;
+                Self::update_status(&mut maker);
+                Self::update_status(&mut taker);
+                self.orders.insert(maker.id, &maker);
+                if matches!(maker.status, OrderStatus::Filled) {
+                    self.remove_from_book(&maker);
+                }
+
+                self.env()
+                    .transfer(taker.maker, maker_fill)
+                    .expect("Transfer to taker failed");
+                self.env()
+                    .transfer(maker.maker, taker_fill)
+                    .expect("Transfer to maker failed");
+
+                self.env().emit_event(OrdersMatched {
+                    taker_order_id: taker.id,
+                    maker_order_id: maker.id,
+                    maker_fill,
+                    taker_fill,
+                });
+            }
+
+            self.orders.insert(order_id, &taker);
+            if matches!(taker.status, OrderStatus::Filled) {
+                self.remove_from_book(&taker);
+            }
+
+            taker.filled - filled_before
+        }
+
+        /// Best-priced open order selling `sell_asset` for `buy_asset`, i.e. the one
+        /// giving the most `sell_asset` per unit of `buy_asset`. Ties go to the oldest.
+        fn best_order(
+            &self,
+            sell_asset: AssetId,
+            buy_asset: AssetId,
+            exclude_maker: Option<AccountId>,
+        ) -> Option<Order> {
+            let mut best: Option<Order> = None;
+            for id in self.orderbook.get((sell_asset, buy_asset)).unwrap_or_default() {
+                let Some(order) = self.orders.get(id) else {
+                    continue;
+                };
+                if Some(order.maker) == exclude_maker
+                    || !matches!(order.status, OrderStatus::Open | OrderStatus::PartiallyFilled)
+                {
+                    continue;
+                }
+                let better = match &best {
+                    Some(current) => {
+                        wide_mul(order.sell_amount, current.buy_amount)
+                            > wide_mul(current.sell_amount, order.buy_amount)
+                    }
+                    None => true,
+                };
+                if better {
+                    best = Some(order);
+                }
+            }
+            best
+        }
+
+        fn update_status(order: &mut Order) {
+            order.status = if order.filled >= order.sell_amount {
+                OrderStatus::Filled
+            } else {
+                OrderStatus::PartiallyFilled
+            };
+        }
+
+        fn remove_from_book(&mut self, order: &Order) {
+            let open_orders = self.open_orders.get(order.maker).unwrap_or(0);
+            if open_orders > 1 {
+                self.open_orders.insert(order.maker, &(open_orders - 1));
+            } else {
+                self.open_orders.remove(order.maker);
+            }
+
+            let key = (order.sell_asset, order.buy_asset);
+            let mut book = self.orderbook.get(key).unwrap_or_default();
+            book.retain(|id| *id != order.id);
+            if book.is_empty() {
+                self.orderbook.remove(key);
+            } else {
+                self.orderbook.insert(key, &book);
+            }
+        }
     }
 
     #[cfg(test)]
     mod tests {
         use super::*;
 
+        type Env = ink::env::DefaultEnvironment;
+
         #[ink::test]
         fn test_place_order() {
             let mut contract = DEXOrders::new();
             let order_id = contract.place_order(1, 2, 100, 200);
             assert_eq!(order_id, 1);
         }
+
+        fn place(contract: &mut DEXOrders, maker: AccountId, sell: AssetId, buy: AssetId, sell_amount: Balance, buy_amount: Balance) -> u64 {
+            ink::env::test::set_caller::<Env>(maker);
+            ink::env::test::set_value_transferred::<Env>(sell_amount);
+            let contract_id = ink::env::test::callee::<Env>();
+            let held = ink::env::test::get_account_balance::<Env>(contract_id).unwrap_or(0);
+            ink::env::test::set_account_balance::<Env>(contract_id, held + sell_amount);
+            contract.place_order(sell, buy, sell_amount, buy_amount)
+        }
+
+        #[ink::test]
+        fn crossing_order_matches_resting_order() {
+            let mut contract = DEXOrders::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+
+            let sell_id = place(&mut contract, accounts.alice, 1, 2, 100, 200);
+            assert_eq!(contract.get_orderbook(1, 2), vec![sell_id]);
+
+            let bob_before = ink::env::test::get_account_balance::<Env>(accounts.bob).unwrap();
+            let buy_id = place(&mut contract, accounts.bob, 2, 1, 200, 100);
+
+            let sell = contract.get_order(sell_id).unwrap();
+            let buy = contract.get_order(buy_id).unwrap();
+            assert_eq!(sell.status, OrderStatus::Filled);
+            assert_eq!(buy.status, OrderStatus::Filled);
+            assert_eq!(sell.filled, 100);
+            assert_eq!(buy.filled, 200);
+            assert!(contract.get_orderbook(1, 2).is_empty());
+            assert!(contract.get_orderbook(2, 1).is_empty());
+
+            let bob_after = ink::env::test::get_account_balance::<Env>(accounts.bob).unwrap();
+            assert_eq!(bob_after - bob_before, 100);
+        }
+
+        #[ink::test]
+        fn non_crossing_order_rests() {
+            let mut contract = DEXOrders::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+
+            let sell_id = place(&mut contract, accounts.alice, 1, 2, 100, 200);
+            // Bob only offers 150 of asset 2 for 100 of asset 1
+            let buy_id = place(&mut contract, accounts.bob, 2, 1, 150, 100);
+
+            assert_eq!(contract.get_order(sell_id).unwrap().status, OrderStatus::Open);
+            assert_eq!(contract.get_order(buy_id).unwrap().status, OrderStatus::Open);
+            assert_eq!(contract.get_orderbook(2, 1), vec![buy_id]);
+        }
+
+        #[ink::test]
+        fn best_price_selects_most_favorable_order() {
+            let mut contract = DEXOrders::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+
+            assert_eq!(contract.get_best_price(1, 2), None);
+            assert!(contract.get_orders_for_pair(1, 2).is_empty());
+
+            place(&mut contract, accounts.alice, 1, 2, 100, 300);
+            let best_id = place(&mut contract, accounts.bob, 1, 2, 100, 150);
+            place(&mut contract, accounts.charlie, 1, 2, 50, 100);
+
+            assert_eq!(contract.get_orders_for_pair(1, 2).len(), 3);
+            assert_eq!(contract.get_best_price(1, 2), contract.get_price(best_id));
+            assert_eq!(contract.get_best_price(1, 2), Some((150, 100)));
+            assert_eq!(contract.get_best_price(2, 1), None);
+        }
+
+        #[ink::test]
+        fn cancel_removes_order_from_book() {
+            let mut contract = DEXOrders::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+
+            let first = place(&mut contract, accounts.alice, 1, 2, 100, 200);
+            let second = place(&mut contract, accounts.alice, 1, 2, 50, 100);
+
+            contract.cancel_order(first);
+
+            assert_eq!(contract.get_orderbook(1, 2), vec![second]);
+            let cancelled = contract.get_order(first).unwrap();
+            assert_eq!(cancelled.status, OrderStatus::Cancelled);
+        }
+
+        #[ink::test]
+        fn full_fill_removes_order_from_book() {
+            let mut contract = DEXOrders::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+
+            let order_id = place(&mut contract, accounts.alice, 1, 2, 100, 200);
+
+            ink::env::test::set_caller::<Env>(accounts.bob);
+            ink::env::test::set_value_transferred::<Env>(200);
+            let contract_id = ink::env::test::callee::<Env>();
+            ink::env::test::set_account_balance::<Env>(contract_id, 300);
+            contract.fill_order(order_id, 100);
+
+            assert!(contract.get_orderbook(1, 2).is_empty());
+            assert_eq!(contract.get_order(order_id).unwrap().status, OrderStatus::Filled);
+        }
+
+        #[ink::test]
+        fn large_amounts_do_not_falsely_cross() {
+            let mut contract = DEXOrders::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+            let unit: Balance = 1_000_000_000_000_000_000;
+
+            // Alice asks 2 of asset 2 per asset 1, Bob bids only 1.5; the products
+            // of these 18-decimal amounts would saturate and look like a cross
+            let sell_id = place(&mut contract, accounts.alice, 1, 2, 100_000 * unit, 200_000 * unit);
+            let buy_id = place(&mut contract, accounts.bob, 2, 1, 150_000 * unit, 100_000 * unit);
+
+            assert_eq!(contract.get_order(sell_id).unwrap().status, OrderStatus::Open);
+            assert_eq!(contract.get_order(buy_id).unwrap().status, OrderStatus::Open);
+        }
+
+        #[ink::test]
+        fn large_amounts_match_at_maker_price() {
+            let mut contract = DEXOrders::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+            let unit: Balance = 1_000_000_000_000_000_000;
+
+            let sell_id = place(&mut contract, accounts.alice, 1, 2, 100_000 * unit, 200_000 * unit);
+            // Bob bids 2.5 per unit for half of Alice's order and pays her price of 2
+            let buy_id = place(&mut contract, accounts.bob, 2, 1, 125_000 * unit, 50_000 * unit);
+
+            let sell = contract.get_order(sell_id).unwrap();
+            let buy = contract.get_order(buy_id).unwrap();
+            assert_eq!(sell.filled, 62_500 * unit);
+            assert_eq!(buy.filled, 125_000 * unit);
+            assert_eq!(buy.status, OrderStatus::Filled);
+        }
+
+        #[ink::test]
+        fn wide_math_matches_narrow_math() {
+            assert_eq!(wide_mul(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+            assert_eq!(wide_mul(1 << 64, 1 << 64), (1, 0));
+            assert_eq!(mul_div(7, 3, 2, false), Some(10));
+            assert_eq!(mul_div(7, 3, 2, true), Some(11));
+            assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX, false), Some(u128::MAX));
+            assert_eq!(mul_div(u128::MAX, 2, 1, false), None);
+        }
+
+        #[ink::test]
+        fn partial_match_rounds_in_makers_favour() {
+            let mut contract = DEXOrders::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+
+            // Alice sells 3 of asset 1 for 2 of asset 2
+            let sell_id = place(&mut contract, accounts.alice, 1, 2, 3, 2);
+            let alice_before = ink::env::test::get_account_balance::<Env>(accounts.alice).unwrap();
+            // Bob sells 1 of asset 2 for at least 1 of asset 1
+            let buy_id = place(&mut contract, accounts.bob, 2, 1, 1, 1);
+
+            // Bob gets floor(1 * 3 / 2) = 1; Alice is owed 2/3, rounded up to 1
+            let sell = contract.get_order(sell_id).unwrap();
+            let buy = contract.get_order(buy_id).unwrap();
+            assert_eq!(sell.filled, 1);
+            assert_eq!(buy.filled, 1);
+            let alice_after = ink::env::test::get_account_balance::<Env>(accounts.alice).unwrap();
+            assert_eq!(alice_after - alice_before, 1);
+        }
+
+        #[ink::test]
+        fn open_orders_are_limited_per_maker() {
+            let mut contract = DEXOrders::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+
+            for _ in 0..MAX_OPEN_ORDERS_PER_MAKER {
+                place(&mut contract, accounts.alice, 1, 2, 1, 100);
+            }
+            // Another maker can still use the pair
+            place(&mut contract, accounts.bob, 1, 2, 1, 100);
+
+            // Cancelling frees a slot
+            ink::env::test::set_caller::<Env>(accounts.alice);
+            contract.cancel_order(1);
+            place(&mut contract, accounts.alice, 1, 2, 1, 100);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Too many open orders")]
+        fn open_order_limit_rejects_extra_order() {
+            let mut contract = DEXOrders::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+
+            for _ in 0..=MAX_OPEN_ORDERS_PER_MAKER {
+                place(&mut contract, accounts.alice, 1, 2, 1, 100);
+            }
+        }
     }
 }
@@ -2,8 +2,14 @@
 
 #[ink::contract]
 mod payment_channels {
+    use ink::env::hash::{Blake2x256, HashOutput};
     use ink::storage::Mapping;
 
+    /// Compressed secp256k1 public key
+    pub type EcdsaPublicKey = [u8; 33];
+    /// Recoverable ECDSA signature (r, s, v)
+    pub type EcdsaSignature = [u8; 65];
+
     #[ink(storage)]
     pub struct PaymentChannels {
         admin: AccountId,
@@ -17,8 +23,14 @@ mod payment_channels {
         pub id: u64,
         pub participant_a: AccountId,
         pub participant_b: AccountId,
+        /// Keys the participants sign off-chain channel states with. Participant B registers
+        /// its own key through `register_key`.
+        pub key_a: EcdsaPublicKey,
+        pub key_b: Option<EcdsaPublicKey>,
         pub balance_a: Balance,
         pub balance_b: Balance,
+        /// Value the contract holds for this channel; payouts can never exceed it
+        pub deposited: Balance,
         pub nonce: u64,
         pub status: ChannelStatus,
         pub expiration: u64,
@@ -76,7 +88,12 @@ mod payment_channels {
         }
 
         #[ink(message, payable)]
-        pub fn open_channel(&mut self, participant_b: AccountId, expiration: u64) -> u64 {
+        pub fn open_channel(
+            &mut self,
+            participant_b: AccountId,
+            expiration: u64,
+            key_a: EcdsaPublicKey,
+        ) -> u64 {
             let participant_a = self.env().caller();
             let balance_a = self.env().transferred_value();
 
@@ -90,8 +107,11 @@ mod payment_channels {
                 id: channel_id,
                 participant_a,
                 participant_b,
+                key_a,
+                key_b: None,
                 balance_a,
                 balance_b: 0,
+                deposited: balance_a,
                 nonce: 0,
                 status: ChannelStatus::Open,
                 expiration,
@@ -120,12 +140,37 @@ mod payment_channels {
             assert!(matches!(channel.status, ChannelStatus::Open), "Channel not open");
             assert!(caller == channel.participant_b, "Only participant B can fund");
 
-            channel.balance_b += amount;
+            assert!(amount > 0, "Must deposit funds");
+
+            channel.balance_b = channel.balance_b.checked_add(amount).expect("Overflow");
+            channel.deposited = channel.deposited.checked_add(amount).expect("Overflow");
+            self.channels.insert(channel_id, &channel);
+        }
+
+        /// Set the key participant B signs channel states with. Only B can set it, and only once;
+        /// disputes and closes are rejected until it is registered.
+        #[ink(message)]
+        pub fn register_key(&mut self, channel_id: u64, key_b: EcdsaPublicKey) {
+            let caller = self.env().caller();
+            let mut channel = self.channels.get(channel_id).expect("Channel not found");
+
+            assert!(caller == channel.participant_b, "Only participant B can register");
+            assert!(channel.key_b.is_none(), "Key already registered");
+
+            channel.key_b = Some(key_b);
             self.channels.insert(channel_id, &channel);
         }
 
+        /// Close immediately on a final split both participants agree to: the caller's
+        /// call is its consent, and the counterparty's signature over `close_hash` is theirs.
         #[ink(message)]
-        pub fn cooperative_close(&mut self, channel_id: u64, final_balance_a: Balance, final_balance_b: Balance) {
+        pub fn cooperative_close(
+            &mut self,
+            channel_id: u64,
+            final_balance_a: Balance,
+            final_balance_b: Balance,
+            counterparty_signature: EcdsaSignature,
+        ) {
             let caller = self.env().caller();
             let mut channel = self.channels.get(channel_id).expect("Channel not found");
 
@@ -135,44 +180,59 @@ mod payment_channels {
                 "Not a participant"
             );
             assert!(
-                final_balance_a + final_balance_b == channel.balance_a + channel.balance_b,
+                final_balance_a.checked_add(final_balance_b) == Some(channel.deposited),
                 "Balances must sum correctly"
             );
+            let message = close_hash(self.env().account_id(), channel_id, final_balance_a, final_balance_b);
+            self.verify_counterparty(&channel, caller, &message, &counterparty_signature);
 
-            channel.status = ChannelStatus::Closed;
-            self.channels.insert(channel_id, &channel);
-
-            if final_balance_a > 0 {
-                self.env().transfer(channel.participant_a, final_balance_a).expect("Transfer failed");
-            }
-            if final_balance_b > 0 {
-                self.env().transfer(channel.participant_b, final_balance_b).expect("Transfer failed");
-            }
-
-            self.env().emit_event(ChannelClosed { channel_id });
+            channel.balance_a = final_balance_a;
+            channel.balance_b = final_balance_b;
+            self.close_and_pay_out(channel);
         }
 
+        /// Raise a dispute with the latest off-chain state, which must carry the
+        /// counterparty's signature over `state_hash`. While the dispute window is open
+        /// either participant may replace the state with a higher-nonce one.
         #[ink(message)]
-        pub fn raise_dispute(&mut self, channel_id: u64, nonce: u64, balance_a: Balance, balance_b: Balance) {
+        pub fn raise_dispute(
+            &mut self,
+            channel_id: u64,
+            nonce: u64,
+            balance_a: Balance,
+            balance_b: Balance,
+            counterparty_signature: EcdsaSignature,
+        ) {
             let caller = self.env().caller();
             let mut channel = self.channels.get(channel_id).expect("Channel not found");
 
-            assert!(matches!(channel.status, ChannelStatus::Open), "Channel not open");
+            let in_window = match channel.status {
+                ChannelStatus::Open => true,
+                ChannelStatus::InDispute => self.env().block_number() < channel.dispute_expiration,
+                ChannelStatus::Closed => false,
+            };
+            assert!(in_window, "Channel not disputable");
             assert!(
                 caller == channel.participant_a || caller == channel.participant_b,
                 "Not a participant"
             );
             assert!(nonce > channel.nonce, "Nonce must be higher");
             assert!(
-                balance_a + balance_b == channel.balance_a + channel.balance_b,
+                balance_a.checked_add(balance_b) == Some(channel.deposited),
                 "Balances must sum correctly"
             );
 
-            channel.status = ChannelStatus::InDispute;
+            let message = state_hash(self.env().account_id(), channel_id, nonce, balance_a, balance_b);
+            self.verify_counterparty(&channel, caller, &message, &counterparty_signature);
+
+            // The window is fixed when the dispute opens so counter-submissions cannot extend it
+            if matches!(channel.status, ChannelStatus::Open) {
+                channel.status = ChannelStatus::InDispute;
+                channel.dispute_expiration = self.env().block_number() + 100;
+            }
             channel.nonce = nonce;
             channel.balance_a = balance_a;
             channel.balance_b = balance_b;
-            channel.dispute_expiration = self.env().block_number() + 100;
             self.channels.insert(channel_id, &channel);
 
             self.env().emit_event(DisputeRaised {
@@ -191,18 +251,7 @@ mod payment_channels {
                 "Dispute period not over"
             );
 
-            let mut updated_channel = channel;
-            updated_channel.status = ChannelStatus::Closed;
-            self.channels.insert(channel_id, &updated_channel);
-
-            if updated_channel.balance_a > 0 {
-                self.env().transfer(updated_channel.participant_a, updated_channel.balance_a).expect("Transfer failed");
-            }
-            if updated_channel.balance_b > 0 {
-                self.env().transfer(updated_channel.participant_b, updated_channel.balance_b).expect("Transfer failed");
-            }
-
-            self.env().emit_event(ChannelClosed { channel_id });
+            self.close_and_pay_out(channel);
         }
 
         #[ink(message)]
@@ -214,5 +263,304 @@ mod payment_channels {
         pub fn get_channel_count(&self) -> u64 {
             self.channel_counter
         }
+
+        /// Panics unless `signature` over `message` recovers to the key of the participant
+        /// other than `caller`
+        fn verify_counterparty(&self, channel: &Channel, caller: AccountId, message: &[u8; 32], signature: &EcdsaSignature) {
+            let key_b = channel.key_b.expect("Participant B key not registered");
+            let counterparty_key = if caller == channel.participant_a {
+                key_b
+            } else {
+                channel.key_a
+            };
+            let mut signer = [0u8; 33];
+            assert!(
+                self.env().ecdsa_recover(signature, message, &mut signer).is_ok()
+                    && signer == counterparty_key,
+                "Invalid counterparty signature"
+            );
+        }
+
+        /// Close the channel and pay out its balances from the value held for it
+        fn close_and_pay_out(&mut self, mut channel: Channel) {
+            let payout = channel
+                .balance_a
+                .checked_add(channel.balance_b)
+                .expect("Overflow");
+            assert!(payout <= channel.deposited, "Payout exceeds channel custody");
+
+            channel.status = ChannelStatus::Closed;
+            channel.deposited -= payout;
+            self.channels.insert(channel.id, &channel);
+
+            if channel.balance_a > 0 {
+                self.env().transfer(channel.participant_a, channel.balance_a).expect("Transfer failed");
+            }
+            if channel.balance_b > 0 {
+                self.env().transfer(channel.participant_b, channel.balance_b).expect("Transfer failed");
+            }
+
+            self.env().emit_event(ChannelClosed { channel_id: channel.id });
+        }
+    }
+
+    /// Hash of a channel state as signed off-chain by the participants
+    pub fn state_hash(
+        contract: AccountId,
+        channel_id: u64,
+        nonce: u64,
+        balance_a: Balance,
+        balance_b: Balance,
+    ) -> [u8; 32] {
+        let mut output = <Blake2x256 as HashOutput>::Type::default();
+        ink::env::hash_encoded::<Blake2x256, _>(
+            &(contract, channel_id, nonce, balance_a, balance_b),
+            &mut output,
+        );
+        output
+    }
+
+    /// Hash of a final split signed off-chain to consent to `cooperative_close`. It is
+    /// domain-separated from `state_hash` so a signed interim state cannot close the channel.
+    pub fn close_hash(
+        contract: AccountId,
+        channel_id: u64,
+        balance_a: Balance,
+        balance_b: Balance,
+    ) -> [u8; 32] {
+        let mut output = <Blake2x256 as HashOutput>::Type::default();
+        ink::env::hash_encoded::<Blake2x256, _>(
+            &(b"close", contract, channel_id, balance_a, balance_b),
+            &mut output,
+        );
+        output
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use secp256k1::{Message, PublicKey, SecretKey, SECP256K1};
+
+        type Env = ink::env::DefaultEnvironment;
+
+        fn key(seed: u8) -> SecretKey {
+            SecretKey::from_slice(&[seed; 32]).unwrap()
+        }
+
+        fn public(seed: u8) -> EcdsaPublicKey {
+            PublicKey::from_secret_key(SECP256K1, &key(seed)).serialize()
+        }
+
+        fn sign(seed: u8, channel_id: u64, nonce: u64, balance_a: Balance, balance_b: Balance) -> EcdsaSignature {
+            sign_hash(seed, state_hash(ink::env::test::callee::<Env>(), channel_id, nonce, balance_a, balance_b))
+        }
+
+        fn sign_close(seed: u8, channel_id: u64, balance_a: Balance, balance_b: Balance) -> EcdsaSignature {
+            sign_hash(seed, close_hash(ink::env::test::callee::<Env>(), channel_id, balance_a, balance_b))
+        }
+
+        fn sign_hash(seed: u8, hash: [u8; 32]) -> EcdsaSignature {
+            let (recovery_id, compact) = SECP256K1
+                .sign_ecdsa_recoverable(&Message::from_slice(&hash).unwrap(), &key(seed))
+                .serialize_compact();
+            let mut signature = [0u8; 65];
+            signature[..64].copy_from_slice(&compact);
+            signature[64] = recovery_id.to_i32() as u8;
+            signature
+        }
+
+        fn open_funded(contract: &mut PaymentChannels, deposit_a: Balance, deposit_b: Balance) -> u64 {
+            let accounts = ink::env::test::default_accounts::<Env>();
+            let contract_id = ink::env::test::callee::<Env>();
+            ink::env::test::set_account_balance::<Env>(contract_id, deposit_a + deposit_b);
+
+            ink::env::test::set_caller::<Env>(accounts.alice);
+            ink::env::test::set_value_transferred::<Env>(deposit_a);
+            let channel_id = contract.open_channel(accounts.bob, 1000, public(1));
+
+            ink::env::test::set_caller::<Env>(accounts.bob);
+            ink::env::test::set_value_transferred::<Env>(0);
+            contract.register_key(channel_id, public(2));
+
+            if deposit_b > 0 {
+                ink::env::test::set_caller::<Env>(accounts.bob);
+                ink::env::test::set_value_transferred::<Env>(deposit_b);
+                contract.fund_channel(channel_id);
+            }
+            ink::env::test::set_value_transferred::<Env>(0);
+            channel_id
+        }
+
+        #[ink::test]
+        fn funding_tracks_custody() {
+            let mut contract = PaymentChannels::new();
+            let channel_id = open_funded(&mut contract, 100, 50);
+
+            let channel = contract.get_channel(channel_id).unwrap();
+            assert_eq!(channel.deposited, 150);
+            assert_eq!(channel.balance_a + channel.balance_b, channel.deposited);
+        }
+
+        #[ink::test]
+        fn cooperative_close_pays_out_custody() {
+            let mut contract = PaymentChannels::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+            let channel_id = open_funded(&mut contract, 100, 50);
+            let bob_before = ink::env::test::get_account_balance::<Env>(accounts.bob).unwrap();
+
+            ink::env::test::set_caller::<Env>(accounts.bob);
+            contract.cooperative_close(channel_id, 30, 120, sign_close(1, channel_id, 30, 120));
+
+            let channel = contract.get_channel(channel_id).unwrap();
+            assert_eq!(channel.status, ChannelStatus::Closed);
+            assert_eq!(channel.deposited, 0);
+            let bob_after = ink::env::test::get_account_balance::<Env>(accounts.bob).unwrap();
+            assert_eq!(bob_after - bob_before, 120);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Balances must sum correctly")]
+        fn overfunded_close_rejected() {
+            let mut contract = PaymentChannels::new();
+            let channel_id = open_funded(&mut contract, 100, 50);
+
+            contract.cooperative_close(channel_id, 100, 100, sign_close(1, channel_id, 100, 100));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Balances must sum correctly")]
+        fn underfunded_dispute_rejected() {
+            let mut contract = PaymentChannels::new();
+            // Participant B never funds, so nothing can be assigned to them beyond A's deposit
+            let channel_id = open_funded(&mut contract, 100, 0);
+
+            contract.raise_dispute(channel_id, 1, 100, 50, sign(2, channel_id, 1, 100, 50));
+        }
+
+        #[ink::test]
+        fn co_signed_dispute_succeeds() {
+            let mut contract = PaymentChannels::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+            let channel_id = open_funded(&mut contract, 100, 50);
+
+            ink::env::test::set_caller::<Env>(accounts.alice);
+            contract.raise_dispute(channel_id, 1, 40, 110, sign(2, channel_id, 1, 40, 110));
+
+            let channel = contract.get_channel(channel_id).unwrap();
+            assert_eq!(channel.status, ChannelStatus::InDispute);
+            assert_eq!(channel.balance_a, 40);
+            assert_eq!(channel.balance_b, 110);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Invalid counterparty signature")]
+        fn unilateral_dispute_rejected() {
+            let mut contract = PaymentChannels::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+            let channel_id = open_funded(&mut contract, 100, 50);
+
+            // Alice signs her own favorable split instead of obtaining Bob's signature
+            ink::env::test::set_caller::<Env>(accounts.alice);
+            contract.raise_dispute(channel_id, 1, 150, 0, sign(1, channel_id, 1, 150, 0));
+        }
+    
+        #[ink::test]
+        #[should_panic(expected = "Invalid counterparty signature")]
+        fn one_sided_cooperative_close_rejected() {
+            let mut contract = PaymentChannels::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+            let channel_id = open_funded(&mut contract, 100, 50);
+
+            // Bob picks a split in his favour and signs it himself
+            ink::env::test::set_caller::<Env>(accounts.bob);
+            contract.cooperative_close(channel_id, 0, 150, sign_close(2, channel_id, 0, 150));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Invalid counterparty signature")]
+        fn state_signature_cannot_close() {
+            let mut contract = PaymentChannels::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+            let channel_id = open_funded(&mut contract, 100, 50);
+
+            ink::env::test::set_caller::<Env>(accounts.bob);
+            contract.cooperative_close(channel_id, 30, 120, sign(1, channel_id, 1, 30, 120));
+        }
+
+        #[ink::test]
+        fn newer_state_supersedes_dispute() {
+            let mut contract = PaymentChannels::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+            let channel_id = open_funded(&mut contract, 100, 50);
+
+            // Alice disputes with a stale state; Bob answers with a later one Alice signed
+            ink::env::test::set_caller::<Env>(accounts.alice);
+            contract.raise_dispute(channel_id, 1, 90, 60, sign(2, channel_id, 1, 90, 60));
+            let window_end = contract.get_channel(channel_id).unwrap().dispute_expiration;
+
+            ink::env::test::set_caller::<Env>(accounts.bob);
+            contract.raise_dispute(channel_id, 2, 20, 130, sign(1, channel_id, 2, 20, 130));
+
+            let channel = contract.get_channel(channel_id).unwrap();
+            assert_eq!(channel.status, ChannelStatus::InDispute);
+            assert_eq!((channel.nonce, channel.balance_a, channel.balance_b), (2, 20, 130));
+            assert_eq!(channel.dispute_expiration, window_end);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Channel not disputable")]
+        fn counter_submission_closes_with_window() {
+            let mut contract = PaymentChannels::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+            let channel_id = open_funded(&mut contract, 100, 50);
+
+            ink::env::test::set_caller::<Env>(accounts.alice);
+            contract.raise_dispute(channel_id, 1, 90, 60, sign(2, channel_id, 1, 90, 60));
+            for _ in 0..100 {
+                ink::env::test::advance_block::<Env>();
+            }
+
+            ink::env::test::set_caller::<Env>(accounts.bob);
+            contract.raise_dispute(channel_id, 2, 20, 130, sign(1, channel_id, 2, 20, 130));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Participant B key not registered")]
+        fn dispute_waits_for_b_key() {
+            let mut contract = PaymentChannels::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+            ink::env::test::set_account_balance::<Env>(ink::env::test::callee::<Env>(), 100);
+
+            // Alice cannot supply a key for Bob, so a state she signs alone never verifies
+            ink::env::test::set_caller::<Env>(accounts.alice);
+            ink::env::test::set_value_transferred::<Env>(100);
+            let channel_id = contract.open_channel(accounts.bob, 1000, public(1));
+            ink::env::test::set_value_transferred::<Env>(0);
+            contract.raise_dispute(channel_id, 1, 100, 0, sign(3, channel_id, 1, 100, 0));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Only participant B can register")]
+        fn only_b_registers_key() {
+            let mut contract = PaymentChannels::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+
+            ink::env::test::set_caller::<Env>(accounts.alice);
+            ink::env::test::set_value_transferred::<Env>(100);
+            let channel_id = contract.open_channel(accounts.bob, 1000, public(1));
+            contract.register_key(channel_id, public(3));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Key already registered")]
+        fn b_key_registered_once() {
+            let mut contract = PaymentChannels::new();
+            let accounts = ink::env::test::default_accounts::<Env>();
+            let channel_id = open_funded(&mut contract, 100, 50);
+
+            ink::env::test::set_caller::<Env>(accounts.bob);
+            contract.register_key(channel_id, public(3));
+        }
     }
 }
@@ -8,6 +8,11 @@ mod trust_lines {
     use ink::storage::Mapping;
     use ink::prelude::vec::Vec;
 
+    /// Quality value that leaves amounts unchanged
+    const QUALITY_PAR: u32 = 1000;
+    /// Maximum number of hops in `send_through_path`
+    const MAX_PATH_LENGTH: usize = 6;
+
     /// Trust line structure
     #[derive(scale::Decode, scale::Encode, Clone, Debug)]
     #[cfg_attr(
@@ -19,6 +24,8 @@ mod trust_lines {
         account1: AccountId,
         /// Account 2 (higher address)
         account2: AccountId,
+        /// Account that opened the line; only it may change the line's quality
+        owner: AccountId,
         /// Credit limit from account1 to account2
         limit1: Balance,
         /// Credit limit from account2 to account1
@@ -72,6 +79,16 @@ mod trust_lines {
         new_balance: i128,
     }
 
+    #[ink(event)]
+    pub struct QualityUpdated {
+        #[ink(topic)]
+        account1: AccountId,
+        #[ink(topic)]
+        account2: AccountId,
+        quality_in: u32,
+        quality_out: u32,
+    }
+
     /// Errors
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
@@ -92,6 +109,14 @@ mod trust_lines {
         Unauthorized,
         /// Rippling not enabled
         RipplingDisabled,
+        /// Quality must be between 1 and 1000
+        InvalidQuality,
+        /// Path is empty, too long, or revisits an account
+        InvalidPath,
+        /// Quality deductions leave nothing to deliver
+        ZeroDelivery,
+        /// Path delivers less than the caller's minimum
+        BelowMinDelivered,
     }
 
     pub type Result<T> = core::result::Result<T, Error>;
@@ -136,6 +161,7 @@ mod trust_lines {
             let trust_line = TrustLine {
                 account1,
                 account2,
+                owner: caller,
                 limit1,
                 limit2,
                 balance: 0,
@@ -185,56 +211,125 @@ mod trust_lines {
             Ok(())
         }
 
-        /// Send payment through trust line
+        /// Set the line's quality (scaled by 1000). Only the line's owner may set it.
         #[ink(message)]
-        pub fn send_payment(&mut self, recipient: AccountId, amount: Balance) -> Result<()> {
+        pub fn set_quality(
+            &mut self,
+            counterparty: AccountId,
+            quality_in: u32,
+            quality_out: u32,
+        ) -> Result<()> {
             let caller = self.env().caller();
-
-            if amount == 0 {
-                return Err(Error::InvalidAmount);
-            }
-
-            let (account1, account2) = Self::order_accounts(caller, recipient);
+            let (account1, account2) = Self::order_accounts(caller, counterparty);
 
             let mut trust_line = self
                 .trust_lines
                 .get(&(account1, account2))
                 .ok_or(Error::TrustLineNotFound)?;
 
-            // Calculate new balance
-            let amount_i128 = amount as i128;
-            let new_balance = if caller == account1 {
-                trust_line.balance - amount_i128
-            } else {
-                trust_line.balance + amount_i128
-            };
+            if caller != trust_line.owner {
+                return Err(Error::Unauthorized);
+            }
 
-            // Check credit limits
-            if caller == account1 {
-                let max_negative = -(trust_line.limit1 as i128);
-                if new_balance < max_negative {
-                    return Err(Error::InsufficientCredit);
-                }
-            } else {
-                let max_positive = trust_line.limit2 as i128;
-                if new_balance > max_positive {
-                    return Err(Error::InsufficientCredit);
+            for quality in [quality_in, quality_out] {
+                if quality == 0 || quality > QUALITY_PAR {
+                    return Err(Error::InvalidQuality);
                 }
             }
 
-            trust_line.balance = new_balance;
+            trust_line.quality_in = quality_in;
+            trust_line.quality_out = quality_out;
             self.trust_lines.insert((account1, account2), &trust_line);
 
+            self.env().emit_event(QualityUpdated {
+                account1,
+                account2,
+                quality_in,
+                quality_out,
+            });
+
+            Ok(())
+        }
+
+        /// Send payment through trust line. The recipient is credited the amount
+        /// reduced by the line's `quality_out`.
+        #[ink(message)]
+        pub fn send_payment(&mut self, recipient: AccountId, amount: Balance) -> Result<()> {
+            let caller = self.env().caller();
+
+            if amount == 0 {
+                return Err(Error::InvalidAmount);
+            }
+
+            let (trust_line, delivered) = self.prepare_hop(caller, recipient, amount)?;
+            let new_balance = trust_line.balance;
+            self.trust_lines
+                .insert((trust_line.account1, trust_line.account2), &trust_line);
+
             self.env().emit_event(PaymentSent {
                 from: caller,
                 to: recipient,
-                amount,
+                amount: delivered,
                 new_balance,
             });
 
             Ok(())
         }
 
+        /// Ripple a payment from the caller along `path`, ending at its last account.
+        /// Each hop credits the previous hop's delivery reduced by that line's quality,
+        /// so intermediaries keep the difference. Fails if less than `min_delivered`
+        /// reaches the last account. All hops are checked before any line is written.
+        #[ink(message)]
+        pub fn send_through_path(
+            &mut self,
+            path: Vec<AccountId>,
+            amount: Balance,
+            min_delivered: Balance,
+        ) -> Result<Balance> {
+            let caller = self.env().caller();
+
+            if amount == 0 {
+                return Err(Error::InvalidAmount);
+            }
+            if path.is_empty() || path.len() > MAX_PATH_LENGTH {
+                return Err(Error::InvalidPath);
+            }
+
+            let mut updated: Vec<(AccountId, AccountId, TrustLine, Balance)> = Vec::new();
+            let mut current = caller;
+            let mut remaining = amount;
+            for (i, next) in path.iter().enumerate() {
+                if *next == caller || path[..i].contains(next) {
+                    return Err(Error::InvalidPath);
+                }
+                let (trust_line, delivered) = self.prepare_hop(current, *next, remaining)?;
+                if !trust_line.allow_rippling && path.len() > 1 {
+                    return Err(Error::RipplingDisabled);
+                }
+                updated.push((current, *next, trust_line, delivered));
+                current = *next;
+                remaining = delivered;
+            }
+
+            if remaining < min_delivered {
+                return Err(Error::BelowMinDelivered);
+            }
+
+            for (from, to, trust_line, delivered) in updated {
+                self.trust_lines
+                    .insert((trust_line.account1, trust_line.account2), &trust_line);
+                self.env().emit_event(PaymentSent {
+                    from,
+                    to,
+                    amount: delivered,
+                    new_balance: trust_line.balance,
+                });
+            }
+
+            Ok(remaining)
+        }
+
         /// Close trust line (must have zero balance)
         #[ink(message)]
         pub fn close_trust_line(&mut self, counterparty: AccountId) -> Result<()> {
@@ -289,6 +384,55 @@ mod trust_lines {
             }
         }
 
+        /// Helper: Apply `amount` from `from` to `to` on their line without writing it.
+        /// Returns the updated line and the quality-adjusted amount delivered.
+        fn prepare_hop(
+            &self,
+            from: AccountId,
+            to: AccountId,
+            amount: Balance,
+        ) -> Result<(TrustLine, Balance)> {
+            let (account1, account2) = Self::order_accounts(from, to);
+
+            let mut trust_line = self
+                .trust_lines
+                .get(&(account1, account2))
+                .ok_or(Error::TrustLineNotFound)?;
+
+            let delivered = amount
+                .checked_mul(trust_line.quality_out as Balance)
+                .ok_or(Error::InvalidAmount)?
+                / QUALITY_PAR as Balance;
+            if delivered == 0 {
+                return Err(Error::ZeroDelivery);
+            }
+
+            // Calculate new balance
+            let delivered_i128 = i128::try_from(delivered).map_err(|_| Error::InvalidAmount)?;
+            let new_balance = if from == account1 {
+                trust_line.balance.checked_sub(delivered_i128)
+            } else {
+                trust_line.balance.checked_add(delivered_i128)
+            }
+            .ok_or(Error::InvalidAmount)?;
+
+            // Check credit limits
+            if from == account1 {
+                let max_negative = -(trust_line.limit1 as i128);
+                if new_balance < max_negative {
+                    return Err(Error::InsufficientCredit);
+                }
+            } else {
+                let max_positive = trust_line.limit2 as i128;
+                if new_balance > max_positive {
+                    return Err(Error::InsufficientCredit);
+                }
+            }
+
+            trust_line.balance = new_balance;
+            Ok((trust_line, delivered))
+        }
+
         /// Helper: Order accounts consistently
         fn order_accounts(account1: AccountId, account2: AccountId) -> (AccountId, AccountId) {
             if account1 < account2 {
@@ -334,5 +478,124 @@ mod trust_lines {
                 Err(Error::InsufficientCredit)
             );
         }
+
+        #[ink::test]
+        fn quality_out_reduces_credited_amount() {
+            let mut contract = TrustLines::new();
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            contract.create_trust_line(accounts.bob, 1000, true).unwrap();
+            assert_eq!(
+                contract.set_quality(accounts.bob, 1000, 1001),
+                Err(Error::InvalidQuality)
+            );
+            contract.set_quality(accounts.bob, 1000, 990).unwrap();
+            contract.send_payment(accounts.bob, 100).unwrap();
+
+            let line = contract.get_trust_line(accounts.alice, accounts.bob).unwrap();
+            assert_eq!(line.balance, -99);
+
+            assert_eq!(contract.send_payment(accounts.bob, 1), Err(Error::ZeroDelivery));
+        }
+
+        #[ink::test]
+        fn send_through_path_compounds_quality() {
+            let mut contract = TrustLines::new();
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            contract.create_trust_line(accounts.bob, 1000, true).unwrap();
+            contract.set_quality(accounts.bob, 1000, 990).unwrap();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            contract.create_trust_line(accounts.charlie, 1000, true).unwrap();
+            contract.set_quality(accounts.charlie, 1000, 990).unwrap();
+
+            // Only the account that opened a line may change its quality
+            assert_eq!(
+                contract.set_quality(accounts.alice, 1000, 500),
+                Err(Error::Unauthorized)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                contract.send_through_path(vec![accounts.bob, accounts.charlie], 1000, 981),
+                Err(Error::BelowMinDelivered)
+            );
+            let first = contract.get_trust_line(accounts.alice, accounts.bob).unwrap();
+            assert_eq!(first.balance, 0);
+
+            let delivered = contract
+                .send_through_path(vec![accounts.bob, accounts.charlie], 1000, 980)
+                .unwrap();
+            assert_eq!(delivered, 980);
+
+            let first = contract.get_trust_line(accounts.alice, accounts.bob).unwrap();
+            let second = contract.get_trust_line(accounts.bob, accounts.charlie).unwrap();
+            assert_eq!(first.balance, -990);
+            assert_eq!(second.balance, -980);
+
+            assert_eq!(
+                contract.send_through_path(vec![accounts.bob, accounts.alice], 10, 0),
+                Err(Error::InvalidPath)
+            );
+        }
+
+        #[ink::test]
+        fn send_through_path_rejects_rippling_disabled_hop() {
+            let mut contract = TrustLines::new();
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            contract.create_trust_line(accounts.bob, 1000, true).unwrap();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            contract.create_trust_line(accounts.charlie, 1000, false).unwrap();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                contract.send_through_path(vec![accounts.bob, accounts.charlie], 100, 0),
+                Err(Error::RipplingDisabled)
+            );
+
+            // The first hop validated but must not have been written
+            let first = contract.get_trust_line(accounts.alice, accounts.bob).unwrap();
+            assert_eq!(first.balance, 0);
+        }
+
+        #[ink::test]
+        fn send_through_path_three_hops_works() {
+            let mut contract = TrustLines::new();
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            contract.create_trust_line(accounts.bob, 1000, true).unwrap();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            contract.create_trust_line(accounts.charlie, 1000, true).unwrap();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            contract.create_trust_line(accounts.django, 1000, true).unwrap();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let delivered = contract
+                .send_through_path(vec![accounts.bob, accounts.charlie, accounts.django], 500, 0)
+                .unwrap();
+            assert_eq!(delivered, 500);
+
+            for (from, to) in [
+                (accounts.alice, accounts.bob),
+                (accounts.bob, accounts.charlie),
+                (accounts.charlie, accounts.django),
+            ] {
+                let line = contract.get_trust_line(from, to).unwrap();
+                assert_eq!(line.balance, -500);
+            }
+
+            // A hop over its limit fails without touching earlier hops
+            assert_eq!(
+                contract.send_through_path(vec![accounts.bob, accounts.charlie, accounts.django], 600, 0),
+                Err(Error::InsufficientCredit)
+            );
+            let first = contract.get_trust_line(accounts.alice, accounts.bob).unwrap();
+            assert_eq!(first.balance, -500);
+        }
     }
 }
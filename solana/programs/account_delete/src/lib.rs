@@ -1,14 +1,21 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::system_program;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
 declare_id!("AccountDeleteXXXXXXXXXXXXXXXXXXXXXXXXXX");
 
+pub const MIN_GRACE_PERIOD_SECS: i64 = 60;
+pub const MAX_GRACE_PERIOD_SECS: i64 = 90 * 86400;
+
 #[program]
 pub mod account_delete {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    pub fn initialize(ctx: Context<Initialize>, grace_period_secs: i64) -> Result<()> {
+        require!(is_valid_grace_period(grace_period_secs), AccountDeleteError::InvalidGracePeriod);
         ctx.accounts.config.authority = ctx.accounts.authority.key();
+        ctx.accounts.config.grace_period_secs = grace_period_secs;
         ctx.accounts.config.total_accounts = 0;
         ctx.accounts.config.bump = *ctx.bumps.get("config").unwrap();
         msg!("AccountDelete initialized");
@@ -21,7 +28,7 @@ pub mod account_delete {
 
         account_info.owner = ctx.accounts.owner.key();
         account_info.beneficiary = beneficiary;
-        account_info.active = true;
+        account_info.status = AccountStatus::Active;
         account_info.bump = *ctx.bumps.get("account_info").unwrap();
 
         config.total_accounts += 1;
@@ -37,7 +44,7 @@ pub mod account_delete {
     pub fn set_beneficiary(ctx: Context<UpdateAccount>, new_beneficiary: Pubkey) -> Result<()> {
         let account_info = &mut ctx.accounts.account_info;
 
-        require!(account_info.active, AccountDeleteError::NotActive);
+        require!(account_info.status != AccountStatus::Deleted, AccountDeleteError::AccountDeleted);
 
         account_info.beneficiary = new_beneficiary;
 
@@ -50,11 +57,14 @@ pub mod account_delete {
     }
 
     pub fn request_deletion(ctx: Context<RequestDeletion>) -> Result<()> {
+        let account_info = &mut ctx.accounts.account_info;
+        begin_deletion(account_info)?;
+
         let deletion = &mut ctx.accounts.deletion_request;
         let clock = Clock::get()?;
 
         deletion.owner = ctx.accounts.owner.key();
-        deletion.grace_period_end = clock.unix_timestamp + 86400; // 24 hours grace period
+        deletion.grace_period_end = grace_period_end(&ctx.accounts.config, clock.unix_timestamp);
         deletion.executed = false;
         deletion.bump = *ctx.bumps.get("deletion_request").unwrap();
 
@@ -66,20 +76,67 @@ pub mod account_delete {
         Ok(())
     }
 
-    pub fn execute_deletion(ctx: Context<ExecuteDeletion>) -> Result<()> {
-        let deletion = &mut ctx.accounts.deletion_request;
-        let account_info = &mut ctx.accounts.account_info;
+    /// Sweeps the owner's balances to the beneficiary and deactivates the account.
+    /// `remaining_accounts` holds (owner token account, beneficiary token account) pairs;
+    /// each source is emptied into its paired destination. `lamports` are transferred from
+    /// the owner, who chooses how much to leave behind for fees.
+    pub fn execute_deletion<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteDeletion<'info>>,
+        lamports: u64,
+    ) -> Result<()> {
         let clock = Clock::get()?;
+        let owner = ctx.accounts.owner.key();
+        let beneficiary = ctx.accounts.account_info.beneficiary;
 
-        require!(!deletion.executed, AccountDeleteError::AlreadyExecuted);
+        require!(!ctx.accounts.deletion_request.executed, AccountDeleteError::AlreadyExecuted);
         require!(
-            clock.unix_timestamp >= deletion.grace_period_end,
+            grace_period_over(&ctx.accounts.deletion_request, clock.unix_timestamp),
             AccountDeleteError::GracePeriodNotEnded
         );
-        require!(account_info.active, AccountDeleteError::NotActive);
+        require_pending(&ctx.accounts.account_info)?;
+        require!(ctx.remaining_accounts.len() % 2 == 0, AccountDeleteError::InvalidTokenAccount);
+
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let source = Account::<TokenAccount>::try_from(&pair[0])?;
+            let destination = Account::<TokenAccount>::try_from(&pair[1])?;
+            require!(
+                is_sweep_pair(&owner, &beneficiary, (&source.owner, &source.mint), (&destination.owner, &destination.mint)),
+                AccountDeleteError::InvalidTokenAccount
+            );
+            if source.amount == 0 {
+                continue;
+            }
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: pair[0].clone(),
+                        to: pair[1].clone(),
+                        authority: ctx.accounts.owner.to_account_info(),
+                    },
+                ),
+                source.amount,
+            )?;
+        }
+
+        if lamports > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: ctx.accounts.beneficiary.to_account_info(),
+                    },
+                ),
+                lamports,
+            )?;
+        }
+
+        let deletion = &mut ctx.accounts.deletion_request;
+        let account_info = &mut ctx.accounts.account_info;
 
         deletion.executed = true;
-        account_info.active = false;
+        account_info.status = AccountStatus::Deleted;
 
         emit!(AccountDeleted {
             owner: deletion.owner,
@@ -89,10 +146,14 @@ pub mod account_delete {
         Ok(())
     }
 
+    /// Returns a pending account to `Active`. The request account is closed, so the owner
+    /// may request deletion again later.
     pub fn cancel_deletion(ctx: Context<CancelDeletion>) -> Result<()> {
         let deletion = &ctx.accounts.deletion_request;
 
         require!(!deletion.executed, AccountDeleteError::AlreadyExecuted);
+        require_pending(&ctx.accounts.account_info)?;
+        ctx.accounts.account_info.status = AccountStatus::Active;
 
         emit!(DeletionCancelled {
             owner: deletion.owner,
@@ -102,8 +163,63 @@ pub mod account_delete {
     }
 
     pub fn check_status(ctx: Context<CheckStatus>) -> Result<bool> {
-        Ok(ctx.accounts.account_info.active)
+        Ok(ctx.accounts.account_info.status != AccountStatus::Deleted)
+    }
+
+    pub fn get_status(ctx: Context<CheckStatus>) -> Result<AccountStatus> {
+        Ok(ctx.accounts.account_info.status.clone())
     }
+
+    /// Seconds left before the pending deletion may execute; 0 once the grace period is over.
+    pub fn get_time_until_deletion(ctx: Context<DeletionView>) -> Result<i64> {
+        let clock = Clock::get()?;
+        Ok(time_until_deletion(&ctx.accounts.deletion_request, clock.unix_timestamp))
+    }
+
+    pub fn can_delete(ctx: Context<DeletionView>) -> Result<bool> {
+        let clock = Clock::get()?;
+        Ok(is_deletable(&ctx.accounts.account_info, &ctx.accounts.deletion_request, clock.unix_timestamp))
+    }
+}
+
+fn is_valid_grace_period(grace_period_secs: i64) -> bool {
+    (MIN_GRACE_PERIOD_SECS..=MAX_GRACE_PERIOD_SECS).contains(&grace_period_secs)
+}
+
+fn grace_period_end(config: &Config, requested_at: i64) -> i64 {
+    requested_at + config.grace_period_secs
+}
+
+/// Active -> PendingDeletion
+fn begin_deletion(account_info: &mut AccountInfo) -> Result<()> {
+    require!(account_info.status != AccountStatus::Deleted, AccountDeleteError::AccountDeleted);
+    require!(account_info.status == AccountStatus::Active, AccountDeleteError::NotActive);
+    account_info.status = AccountStatus::PendingDeletion;
+    Ok(())
+}
+
+fn require_pending(account_info: &AccountInfo) -> Result<()> {
+    require!(account_info.status != AccountStatus::Deleted, AccountDeleteError::AccountDeleted);
+    require!(account_info.status == AccountStatus::PendingDeletion, AccountDeleteError::NotPendingDeletion);
+    Ok(())
+}
+
+fn grace_period_over(deletion: &DeletionRequest, now: i64) -> bool {
+    now >= deletion.grace_period_end
+}
+
+fn time_until_deletion(deletion: &DeletionRequest, now: i64) -> i64 {
+    (deletion.grace_period_end - now).max(0)
+}
+
+fn is_deletable(account_info: &AccountInfo, deletion: &DeletionRequest, now: i64) -> bool {
+    account_info.status == AccountStatus::PendingDeletion && !deletion.executed && grace_period_over(deletion, now)
+}
+
+/// Whether a `(owner, mint)` source and destination token account may be swept: the source
+/// belongs to the deleted account's owner, the destination to its beneficiary, in the same mint.
+fn is_sweep_pair(owner: &Pubkey, beneficiary: &Pubkey, source: (&Pubkey, &Pubkey), destination: (&Pubkey, &Pubkey)) -> bool {
+    source.0 == owner && destination.0 == beneficiary && destination.1 == source.1
 }
 
 #[derive(Accounts)]
@@ -171,6 +287,20 @@ pub struct RequestDeletion<'info> {
     )]
     pub deletion_request: Account<'info, DeletionRequest>,
     
+    #[account(
+        mut,
+        seeds = [b"account", owner.key().as_ref()],
+        bump = account_info.bump,
+        has_one = owner
+    )]
+    pub account_info: Account<'info, AccountInfo>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
     #[account(mut)]
     pub owner: Signer<'info>,
     
@@ -195,7 +325,14 @@ pub struct ExecuteDeletion<'info> {
     )]
     pub account_info: Account<'info, AccountInfo>,
     
+    #[account(mut, address = account_info.beneficiary)]
+    pub beneficiary: SystemAccount<'info>,
+    
+    #[account(mut)]
     pub owner: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
@@ -209,6 +346,14 @@ pub struct CancelDeletion<'info> {
     )]
     pub deletion_request: Account<'info, DeletionRequest>,
     
+    #[account(
+        mut,
+        seeds = [b"account", owner.key().as_ref()],
+        bump = account_info.bump,
+        has_one = owner
+    )]
+    pub account_info: Account<'info, AccountInfo>,
+    
     #[account(mut)]
     pub owner: Signer<'info>,
 }
@@ -218,11 +363,23 @@ pub struct CheckStatus<'info> {
     pub account_info: Account<'info, AccountInfo>,
 }
 
+#[derive(Accounts)]
+pub struct DeletionView<'info> {
+    #[account(
+        seeds = [b"deletion", account_info.owner.as_ref()],
+        bump = deletion_request.bump
+    )]
+    pub deletion_request: Account<'info, DeletionRequest>,
+    
+    pub account_info: Account<'info, AccountInfo>,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
     pub authority: Pubkey,
     pub total_accounts: u64,
+    pub grace_period_secs: i64,
     pub bump: u8,
 }
 
@@ -231,10 +388,17 @@ pub struct Config {
 pub struct AccountInfo {
     pub owner: Pubkey,
     pub beneficiary: Pubkey,
-    pub active: bool,
+    pub status: AccountStatus,
     pub bump: u8,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum AccountStatus {
+    Active,
+    PendingDeletion,
+    Deleted,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct DeletionRequest {
@@ -281,4 +445,130 @@ pub enum AccountDeleteError {
     GracePeriodNotEnded,
     #[msg("Account is not active")]
     NotActive,
+    #[msg("Account has been deleted")]
+    AccountDeleted,
+    #[msg("Account is not pending deletion")]
+    NotPendingDeletion,
+    #[msg("Grace period out of bounds")]
+    InvalidGracePeriod,
+    #[msg("Token accounts must be (owner, beneficiary) pairs of the same mint")]
+    InvalidTokenAccount,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account_info() -> AccountInfo {
+        AccountInfo {
+            owner: Pubkey::new_unique(),
+            beneficiary: Pubkey::new_unique(),
+            status: AccountStatus::Active,
+            bump: 0,
+        }
+    }
+
+    fn deletion_request(grace_period_end: i64) -> DeletionRequest {
+        DeletionRequest {
+            owner: Pubkey::new_unique(),
+            grace_period_end,
+            executed: false,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn deletion_before_grace_period_is_rejected() {
+        let deletion = deletion_request(1_000);
+        assert!(!grace_period_over(&deletion, 999));
+        assert!(grace_period_over(&deletion, 1_000));
+    }
+
+    #[test]
+    fn sweep_pairs_move_owner_balances_to_the_beneficiary() {
+        let (owner, beneficiary, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(is_sweep_pair(&owner, &beneficiary, (&owner, &mint), (&beneficiary, &mint)));
+
+        let (stranger, other_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // Someone else's tokens cannot be swept
+        assert!(!is_sweep_pair(&owner, &beneficiary, (&stranger, &mint), (&beneficiary, &mint)));
+        // Only the beneficiary may receive them
+        assert!(!is_sweep_pair(&owner, &beneficiary, (&owner, &mint), (&stranger, &mint)));
+        // Source and destination must share a mint
+        assert!(!is_sweep_pair(&owner, &beneficiary, (&owner, &mint), (&beneficiary, &other_mint)));
+    }
+
+    #[test]
+    fn request_cancel_and_request_again() {
+        let mut account = account_info();
+        assert_eq!(require_pending(&account).unwrap_err(), AccountDeleteError::NotPendingDeletion.into());
+
+        begin_deletion(&mut account).unwrap();
+        assert!(account.status == AccountStatus::PendingDeletion);
+        assert_eq!(begin_deletion(&mut account).unwrap_err(), AccountDeleteError::NotActive.into());
+        require_pending(&account).unwrap();
+
+        // Cancelling returns the account to Active so it can be requested again
+        account.status = AccountStatus::Active;
+        begin_deletion(&mut account).unwrap();
+        require_pending(&account).unwrap();
+    }
+
+    #[test]
+    fn deleted_account_rejects_further_lifecycle_changes() {
+        let mut account = account_info();
+        account.status = AccountStatus::Deleted;
+        assert_eq!(begin_deletion(&mut account).unwrap_err(), AccountDeleteError::AccountDeleted.into());
+        assert_eq!(require_pending(&account).unwrap_err(), AccountDeleteError::AccountDeleted.into());
+        assert!(account.status == AccountStatus::Deleted);
+    }
+
+    #[test]
+    fn time_until_deletion_counts_down_to_the_boundary() {
+        let mut account = account_info();
+        account.status = AccountStatus::PendingDeletion;
+        let deletion = deletion_request(1_000);
+
+        assert_eq!(time_until_deletion(&deletion, 400), 600);
+        assert_eq!(time_until_deletion(&deletion, 999), 1);
+        assert!(!is_deletable(&account, &deletion, 999));
+
+        assert_eq!(time_until_deletion(&deletion, 1_000), 0);
+        assert!(is_deletable(&account, &deletion, 1_000));
+        assert_eq!(time_until_deletion(&deletion, 5_000), 0);
+    }
+
+    #[test]
+    fn cannot_delete_unless_pending_and_unexecuted() {
+        let account = account_info();
+        let mut deletion = deletion_request(1_000);
+        assert!(!is_deletable(&account, &deletion, 2_000));
+
+        let mut pending = account_info();
+        pending.status = AccountStatus::PendingDeletion;
+        deletion.executed = true;
+        assert!(!is_deletable(&pending, &deletion, 2_000));
+    }
+
+    #[test]
+    fn grace_period_is_bounded() {
+        assert!(!is_valid_grace_period(MIN_GRACE_PERIOD_SECS - 1));
+        assert!(is_valid_grace_period(MIN_GRACE_PERIOD_SECS));
+        assert!(is_valid_grace_period(MAX_GRACE_PERIOD_SECS));
+        assert!(!is_valid_grace_period(MAX_GRACE_PERIOD_SECS + 1));
+    }
+
+    #[test]
+    fn deletion_gate_uses_the_configured_grace_period() {
+        let config = Config {
+            authority: Pubkey::new_unique(),
+            total_accounts: 0,
+            grace_period_secs: 3_600,
+            bump: 0,
+        };
+        let deletion = deletion_request(grace_period_end(&config, 10_000));
+        assert_eq!(deletion.grace_period_end, 13_600);
+        assert!(!grace_period_over(&deletion, 13_599));
+        assert!(grace_period_over(&deletion, 13_600));
+    }
 }
@@ -1,5 +1,6 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::clock::Clock;
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
 
 declare_id!("ChecksXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX");
 
@@ -17,45 +18,149 @@ pub mod checks {
     pub fn create_check(ctx: Context<CreateCheck>, amount: u64, expiration: i64, memo: [u8; 32]) -> Result<()> {
         let check = &mut ctx.accounts.check;
         let clock = Clock::get()?;
+        require!(amount > 0, CheckError::InvalidAmount);
         require!(expiration > clock.unix_timestamp, CheckError::InvalidExpiration);
         check.drawer = ctx.accounts.drawer.key();
         check.payee = ctx.accounts.payee.key();
+        check.mint = ctx.accounts.mint.key();
         check.amount = amount;
+        check.cashed_amount = 0;
         check.expiration = expiration;
         check.status = CheckStatus::Active;
         check.memo = memo;
         check.bump = *ctx.bumps.get("check").unwrap();
+        check.vault_bump = *ctx.bumps.get("vault").unwrap();
         ctx.accounts.config.total_checks += 1;
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.drawer_token_account.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.drawer.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
         emit!(CheckCreated { drawer: check.drawer, payee: check.payee, amount });
         Ok(())
     }
 
-    pub fn cash_check(ctx: Context<CashCheck>) -> Result<()> {
-        let check = &mut ctx.accounts.check;
+    /// Draws `cash_amount` from the check. The account stays open as `PartiallyCashed` until the
+    /// full amount has been drawn, at which point it and its vault are closed and their rent
+    /// returned to the drawer.
+    pub fn cash_check(ctx: Context<CashCheck>, cash_amount: u64) -> Result<()> {
+        let check = &ctx.accounts.check;
         let clock = Clock::get()?;
-        require!(matches!(check.status, CheckStatus::Active), CheckError::NotActive);
+        require!(is_open(&check.status), CheckError::NotActive);
         require!(clock.unix_timestamp < check.expiration, CheckError::Expired);
-        check.status = CheckStatus::Cashed;
-        emit!(CheckCashed { drawer: check.drawer, payee: check.payee });
+        require!(cash_amount > 0 && cash_amount <= undrawn(check), CheckError::InvalidAmount);
+        pay_from_vault(&ctx.accounts.token_program, &ctx.accounts.vault, &ctx.accounts.payee_token_account, check.key(), check.vault_bump, cash_amount)?;
+        let check = &mut ctx.accounts.check;
+        record_cash(check, cash_amount);
+        emit!(CheckCashed { drawer: check.drawer, payee: check.payee, amount: cash_amount, cashed_amount: check.cashed_amount });
+        if check.status == CheckStatus::Cashed {
+            close_vault(&ctx.accounts.token_program, &ctx.accounts.vault, &ctx.accounts.drawer, check.key(), check.vault_bump)?;
+            check.close(ctx.accounts.drawer.to_account_info())?;
+        }
         Ok(())
     }
 
+    /// Returns the undrawn tokens to the drawer and closes the check and its vault.
     pub fn cancel_check(ctx: Context<CancelCheck>) -> Result<()> {
+        let check = &ctx.accounts.check;
+        require!(is_open(&check.status), CheckError::NotActive);
+        pay_from_vault(&ctx.accounts.token_program, &ctx.accounts.vault, &ctx.accounts.drawer_token_account, check.key(), check.vault_bump, undrawn(check))?;
+        close_vault(&ctx.accounts.token_program, &ctx.accounts.vault, &ctx.accounts.drawer.to_account_info(), check.key(), check.vault_bump)?;
         let check = &mut ctx.accounts.check;
-        require!(matches!(check.status, CheckStatus::Active), CheckError::NotActive);
         check.status = CheckStatus::Cancelled;
         emit!(CheckCancelled { drawer: check.drawer, payee: check.payee });
         Ok(())
     }
 
+    /// Permissionless: once a check has expired anyone may close it and its vault, returning the
+    /// undrawn tokens and all rent to the drawer.
+    pub fn expire_check(ctx: Context<ExpireCheck>) -> Result<()> {
+        let check = &ctx.accounts.check;
+        let clock = Clock::get()?;
+        require!(is_open(&check.status), CheckError::NotActive);
+        require!(has_expired(check, clock.unix_timestamp), CheckError::NotExpired);
+        pay_from_vault(&ctx.accounts.token_program, &ctx.accounts.vault, &ctx.accounts.drawer_token_account, check.key(), check.vault_bump, undrawn(check))?;
+        close_vault(&ctx.accounts.token_program, &ctx.accounts.vault, &ctx.accounts.drawer, check.key(), check.vault_bump)?;
+        let check = &mut ctx.accounts.check;
+        check.status = CheckStatus::Cancelled;
+        emit!(CheckExpired { drawer: check.drawer, payee: check.payee, caller: ctx.accounts.caller.key() });
+        Ok(())
+    }
+
     pub fn endorse_check(ctx: Context<EndorseCheck>, new_payee: Pubkey) -> Result<()> {
         let check = &mut ctx.accounts.check;
-        require!(matches!(check.status, CheckStatus::Active), CheckError::NotActive);
+        require!(is_open(&check.status), CheckError::NotActive);
         check.payee = new_payee;
         Ok(())
     }
 }
 
+fn is_open(status: &CheckStatus) -> bool {
+    matches!(status, CheckStatus::Active | CheckStatus::PartiallyCashed)
+}
+
+/// Tokens still held in the check's vault
+fn undrawn(check: &Check) -> u64 {
+    check.amount - check.cashed_amount
+}
+
+fn record_cash(check: &mut Check, amount: u64) {
+    check.cashed_amount += amount;
+    check.status = if check.cashed_amount == check.amount {
+        CheckStatus::Cashed
+    } else {
+        CheckStatus::PartiallyCashed
+    };
+}
+
+fn has_expired(check: &Check, now: i64) -> bool {
+    now >= check.expiration
+}
+
+fn pay_from_vault<'info>(
+    token_program: &Program<'info, Token>,
+    vault: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    check_key: Pubkey,
+    vault_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let vault_seeds: &[&[u8]] = &[b"vault", check_key.as_ref(), &[vault_bump]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer { from: vault.to_account_info(), to: to.to_account_info(), authority: vault.to_account_info() },
+            &[vault_seeds],
+        ),
+        amount,
+    )
+}
+
+/// Closes an emptied check vault, returning its rent to `destination`
+fn close_vault<'info>(
+    token_program: &Program<'info, Token>,
+    vault: &Account<'info, TokenAccount>,
+    destination: &AccountInfo<'info>,
+    check_key: Pubkey,
+    vault_bump: u8,
+) -> Result<()> {
+    let vault_seeds: &[&[u8]] = &[b"vault", check_key.as_ref(), &[vault_bump]];
+    token::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount { account: vault.to_account_info(), destination: destination.clone(), authority: vault.to_account_info() },
+        &[vault_seeds],
+    ))
+}
+
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(init, payer = authority, space = 8 + Config::INIT_SPACE, seeds = [b"config"], bump)]
@@ -71,27 +176,74 @@ pub struct CreateCheck<'info> {
     pub check: Account<'info, Check>,
     #[account(mut, seeds = [b"config"], bump = config.bump)]
     pub config: Account<'info, Config>,
+    pub mint: Account<'info, Mint>,
+    #[account(init, payer = drawer, seeds = [b"vault", check.key().as_ref()], bump, token::mint = mint, token::authority = vault)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = drawer)]
+    pub drawer_token_account: Account<'info, TokenAccount>,
     #[account(mut)]
     pub drawer: Signer<'info>,
     /// CHECK: Payee
     pub payee: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
 pub struct CashCheck<'info> {
-    #[account(mut, close = payee, constraint = check.payee == payee.key())]
+    #[account(mut, has_one = drawer, constraint = check.payee == payee.key())]
     pub check: Account<'info, Check>,
+    #[account(mut, seeds = [b"vault", check.key().as_ref()], bump = check.vault_bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = payee_token_account.mint == check.mint @ CheckError::InvalidTokenAccount,
+        constraint = payee_token_account.owner == payee.key() @ CheckError::InvalidTokenAccount
+    )]
+    pub payee_token_account: Account<'info, TokenAccount>,
+    /// CHECK: Drawer receiving the rent once the check is fully drawn; bound by has_one
+    #[account(mut)]
+    pub drawer: AccountInfo<'info>,
     #[account(mut)]
     pub payee: Signer<'info>,
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
 pub struct CancelCheck<'info> {
     #[account(mut, has_one = drawer, close = drawer)]
     pub check: Account<'info, Check>,
+    #[account(mut, seeds = [b"vault", check.key().as_ref()], bump = check.vault_bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = drawer_token_account.mint == check.mint @ CheckError::InvalidTokenAccount,
+        constraint = drawer_token_account.owner == drawer.key() @ CheckError::InvalidTokenAccount
+    )]
+    pub drawer_token_account: Account<'info, TokenAccount>,
     #[account(mut)]
     pub drawer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireCheck<'info> {
+    #[account(mut, has_one = drawer, close = drawer)]
+    pub check: Account<'info, Check>,
+    #[account(mut, seeds = [b"vault", check.key().as_ref()], bump = check.vault_bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = drawer_token_account.mint == check.mint @ CheckError::InvalidTokenAccount,
+        constraint = drawer_token_account.owner == drawer.key() @ CheckError::InvalidTokenAccount
+    )]
+    pub drawer_token_account: Account<'info, TokenAccount>,
+    /// CHECK: Drawer receiving the reclaimed rent; bound by has_one
+    #[account(mut)]
+    pub drawer: AccountInfo<'info>,
+    pub caller: Signer<'info>,
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
@@ -114,16 +266,20 @@ pub struct Config {
 pub struct Check {
     pub drawer: Pubkey,
     pub payee: Pubkey,
+    pub mint: Pubkey,
     pub amount: u64,
+    pub cashed_amount: u64,
     pub expiration: i64,
     pub status: CheckStatus,
     pub memo: [u8; 32],
     pub bump: u8,
+    pub vault_bump: u8,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum CheckStatus {
     Active,
+    PartiallyCashed,
     Cashed,
     Cancelled,
 }
@@ -139,6 +295,8 @@ pub struct CheckCreated {
 pub struct CheckCashed {
     pub drawer: Pubkey,
     pub payee: Pubkey,
+    pub amount: u64,
+    pub cashed_amount: u64,
 }
 
 #[event]
@@ -147,6 +305,13 @@ pub struct CheckCancelled {
     pub payee: Pubkey,
 }
 
+#[event]
+pub struct CheckExpired {
+    pub drawer: Pubkey,
+    pub payee: Pubkey,
+    pub caller: Pubkey,
+}
+
 #[error_code]
 pub enum CheckError {
     #[msg("Invalid expiration")]
@@ -155,4 +320,60 @@ pub enum CheckError {
     NotActive,
     #[msg("Expired")]
     Expired,
+    #[msg("Not expired yet")]
+    NotExpired,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Invalid token account")]
+    InvalidTokenAccount,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(amount: u64, expiration: i64) -> Check {
+        Check {
+            drawer: Pubkey::new_unique(),
+            payee: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            amount,
+            cashed_amount: 0,
+            expiration,
+            status: CheckStatus::Active,
+            memo: [0; 32],
+            bump: 0,
+            vault_bump: 0,
+        }
+    }
+
+    #[test]
+    fn check_expires_at_its_expiration() {
+        let check = check(100, 1_000);
+        assert!(!has_expired(&check, 999));
+        assert!(has_expired(&check, 1_000));
+        assert!(has_expired(&check, 5_000));
+    }
+
+    #[test]
+    fn vault_holds_the_undrawn_amount() {
+        let mut check = check(100, 1_000);
+        assert_eq!(undrawn(&check), 100);
+        check.cashed_amount = 30;
+        assert_eq!(undrawn(&check), 70);
+    }
+
+    #[test]
+    fn partial_cash_stays_open_until_fully_drawn() {
+        let mut check = check(100, 1_000);
+        record_cash(&mut check, 40);
+        assert!(check.status == CheckStatus::PartiallyCashed);
+        assert!(is_open(&check.status));
+        assert_eq!(undrawn(&check), 60);
+
+        record_cash(&mut check, 60);
+        assert!(check.status == CheckStatus::Cashed);
+        assert!(!is_open(&check.status));
+        assert_eq!(undrawn(&check), 0);
+    }
 }
@@ -35,6 +35,7 @@ pub mod deposit_authorization {
         auth.authorized = authorized;
         auth.asset_id = asset_id;
         auth.max_amount = max_amount;
+        auth.used_amount = 0;
         auth.expiration = expiration;
         auth.tier = tier.clone();
         auth.status = AuthStatus::Active;
@@ -55,7 +56,7 @@ pub mod deposit_authorization {
     pub fn revoke_authorization(ctx: Context<RevokeAuth>) -> Result<()> {
         let auth = &mut ctx.accounts.authorization;
         
-        require!(matches!(auth.status, AuthStatus::Active), AuthError::NotActive);
+        require!(matches!(auth.status, AuthStatus::Active | AuthStatus::Suspended), AuthError::NotActive);
         
         auth.status = AuthStatus::Revoked;
 
@@ -68,6 +69,36 @@ pub mod deposit_authorization {
         Ok(())
     }
 
+    pub fn suspend_authorization(ctx: Context<ModerateAuth>) -> Result<()> {
+        let auth = &mut ctx.accounts.authorization;
+
+        suspend(auth)?;
+
+        emit!(AuthSuspended {
+            authorizer: auth.authorizer,
+            authorized: auth.authorized,
+            asset_id: auth.asset_id,
+            by: ctx.accounts.signer.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn reinstate_authorization(ctx: Context<ModerateAuth>) -> Result<()> {
+        let auth = &mut ctx.accounts.authorization;
+
+        reinstate(auth)?;
+
+        emit!(AuthReinstated {
+            authorizer: auth.authorizer,
+            authorized: auth.authorized,
+            asset_id: auth.asset_id,
+            by: ctx.accounts.signer.key(),
+        });
+
+        Ok(())
+    }
+
     pub fn update_tier(ctx: Context<UpdateAuth>, new_tier: AuthTier) -> Result<()> {
         let auth = &mut ctx.accounts.authorization;
         
@@ -91,14 +122,60 @@ pub mod deposit_authorization {
         let auth = &ctx.accounts.authorization;
         let clock = Clock::get()?;
 
-        Ok(
-            matches!(auth.status, AuthStatus::Active)
-                && clock.unix_timestamp < auth.expiration
-                && amount <= auth.max_amount
-        )
+        Ok(is_usable(auth, amount, clock.unix_timestamp))
+    }
+
+    pub fn consume_authorization(ctx: Context<ConsumeAuth>, amount: u64) -> Result<()> {
+        let auth = &mut ctx.accounts.authorization;
+        let clock = Clock::get()?;
+
+        consume(auth, amount, clock.unix_timestamp)?;
+
+        emit!(AuthConsumed {
+            authorizer: auth.authorizer,
+            authorized: auth.authorized,
+            asset_id: auth.asset_id,
+            amount,
+            remaining: auth.max_amount - auth.used_amount,
+        });
+
+        Ok(())
     }
 }
 
+fn is_usable(auth: &Authorization, amount: u64, now: i64) -> bool {
+    matches!(auth.status, AuthStatus::Active)
+        && now < auth.expiration
+        && amount <= auth.max_amount - auth.used_amount
+}
+
+/// Draws `amount` against the authorization's remaining allowance
+fn consume(auth: &mut Authorization, amount: u64, now: i64) -> Result<()> {
+    require!(matches!(auth.status, AuthStatus::Active), AuthError::NotActive);
+    require!(now < auth.expiration, AuthError::Expired);
+    require!(amount > 0, AuthError::InvalidAmount);
+
+    let used_amount = auth.used_amount.checked_add(amount).ok_or(AuthError::ExceedsLimit)?;
+    require!(used_amount <= auth.max_amount, AuthError::ExceedsLimit);
+
+    auth.used_amount = used_amount;
+    Ok(())
+}
+
+/// Active -> Suspended; revoked authorizations stay revoked
+fn suspend(auth: &mut Authorization) -> Result<()> {
+    require!(matches!(auth.status, AuthStatus::Active), AuthError::NotActive);
+    auth.status = AuthStatus::Suspended;
+    Ok(())
+}
+
+/// Suspended -> Active
+fn reinstate(auth: &mut Authorization) -> Result<()> {
+    require!(matches!(auth.status, AuthStatus::Suspended), AuthError::NotSuspended);
+    auth.status = AuthStatus::Active;
+    Ok(())
+}
+
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(
@@ -151,6 +228,24 @@ pub struct RevokeAuth<'info> {
     pub authorizer: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct ModerateAuth<'info> {
+    #[account(mut)]
+    pub authorization: Account<'info, Authorization>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    #[account(
+        constraint = signer.key() == authorization.authorizer
+            || signer.key() == config.compliance_officer @ AuthError::Unauthorized
+    )]
+    pub signer: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateAuth<'info> {
     #[account(
@@ -167,6 +262,17 @@ pub struct CheckAuth<'info> {
     pub authorization: Account<'info, Authorization>,
 }
 
+#[derive(Accounts)]
+pub struct ConsumeAuth<'info> {
+    #[account(
+        mut,
+        has_one = authorized
+    )]
+    pub authorization: Account<'info, Authorization>,
+    
+    pub authorized: Signer<'info>,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
@@ -183,6 +289,7 @@ pub struct Authorization {
     pub authorized: Pubkey,
     pub asset_id: u32,
     pub max_amount: u64,
+    pub used_amount: u64,
     pub expiration: i64,
     pub tier: AuthTier,
     pub status: AuthStatus,
@@ -219,6 +326,31 @@ pub struct AuthRevoked {
     pub asset_id: u32,
 }
 
+#[event]
+pub struct AuthConsumed {
+    pub authorizer: Pubkey,
+    pub authorized: Pubkey,
+    pub asset_id: u32,
+    pub amount: u64,
+    pub remaining: u64,
+}
+
+#[event]
+pub struct AuthSuspended {
+    pub authorizer: Pubkey,
+    pub authorized: Pubkey,
+    pub asset_id: u32,
+    pub by: Pubkey,
+}
+
+#[event]
+pub struct AuthReinstated {
+    pub authorizer: Pubkey,
+    pub authorized: Pubkey,
+    pub asset_id: u32,
+    pub by: Pubkey,
+}
+
 #[event]
 pub struct TierUpdated {
     pub authorizer: Pubkey,
@@ -232,4 +364,85 @@ pub enum AuthError {
     InvalidExpiration,
     #[msg("Authorization not active")]
     NotActive,
+    #[msg("Authorization expired")]
+    Expired,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Amount exceeds authorization limit")]
+    ExceedsLimit,
+    #[msg("Authorization not suspended")]
+    NotSuspended,
+    #[msg("Unauthorized")]
+    Unauthorized,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorization(max_amount: u64, expiration: i64) -> Authorization {
+        Authorization {
+            authorizer: Pubkey::new_unique(),
+            authorized: Pubkey::new_unique(),
+            asset_id: 1,
+            max_amount,
+            used_amount: 0,
+            expiration,
+            tier: AuthTier::Basic,
+            status: AuthStatus::Active,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn consuming_past_the_cap_fails() {
+        let mut auth = authorization(1_000, 100);
+        consume(&mut auth, 600, 0).unwrap();
+        consume(&mut auth, 300, 0).unwrap();
+        assert_eq!(auth.used_amount, 900);
+        assert!(is_usable(&auth, 100, 0));
+        assert!(!is_usable(&auth, 101, 0));
+
+        assert_eq!(consume(&mut auth, 101, 0).unwrap_err(), AuthError::ExceedsLimit.into());
+        assert_eq!(auth.used_amount, 900);
+        consume(&mut auth, 100, 0).unwrap();
+        assert_eq!(consume(&mut auth, u64::MAX, 0).unwrap_err(), AuthError::ExceedsLimit.into());
+    }
+
+    #[test]
+    fn revoked_or_expired_authorization_cannot_be_consumed() {
+        let mut auth = authorization(1_000, 100);
+        assert_eq!(consume(&mut auth, 0, 0).unwrap_err(), AuthError::InvalidAmount.into());
+        assert_eq!(consume(&mut auth, 10, 100).unwrap_err(), AuthError::Expired.into());
+
+        auth.status = AuthStatus::Revoked;
+        assert!(!is_usable(&auth, 10, 0));
+        assert_eq!(consume(&mut auth, 10, 0).unwrap_err(), AuthError::NotActive.into());
+        assert_eq!(auth.used_amount, 0);
+    }
+
+    #[test]
+    fn suspend_reinstate_consume_flow() {
+        let mut auth = authorization(1_000, 100);
+
+        suspend(&mut auth).unwrap();
+        assert!(!is_usable(&auth, 10, 0));
+        assert_eq!(consume(&mut auth, 10, 0).unwrap_err(), AuthError::NotActive.into());
+        assert_eq!(suspend(&mut auth).unwrap_err(), AuthError::NotActive.into());
+
+        reinstate(&mut auth).unwrap();
+        assert!(is_usable(&auth, 10, 0));
+        consume(&mut auth, 10, 0).unwrap();
+        assert_eq!(auth.used_amount, 10);
+        assert_eq!(reinstate(&mut auth).unwrap_err(), AuthError::NotSuspended.into());
+    }
+
+    #[test]
+    fn revoked_authorization_cannot_be_suspended() {
+        let mut auth = authorization(1_000, 100);
+        auth.status = AuthStatus::Revoked;
+        assert_eq!(suspend(&mut auth).unwrap_err(), AuthError::NotActive.into());
+        assert_eq!(reinstate(&mut auth).unwrap_err(), AuthError::NotSuspended.into());
+        assert!(auth.status == AuthStatus::Revoked);
+    }
 }
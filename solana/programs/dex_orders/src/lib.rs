@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
 
 declare_id!("DEXOrdersXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX");
 
@@ -15,6 +16,20 @@ pub mod dex_orders {
         Ok(())
     }
 
+    pub fn register_asset(ctx: Context<RegisterAsset>, asset_id: u32) -> Result<()> {
+        let asset = &mut ctx.accounts.asset;
+        asset.asset_id = asset_id;
+        asset.mint = ctx.accounts.mint.key();
+        asset.bump = *ctx.bumps.get("asset").unwrap();
+
+        emit!(AssetRegistered {
+            asset_id,
+            mint: asset.mint,
+        });
+
+        Ok(())
+    }
+
     pub fn place_order(
         ctx: Context<PlaceOrder>,
         sell_asset: u32,
@@ -26,6 +41,18 @@ pub mod dex_orders {
         require!(sell_amount > 0, OrderError::InvalidAmount);
         require!(buy_amount > 0, OrderError::InvalidAmount);
 
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.maker_sell_account.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.maker.to_account_info(),
+                },
+            ),
+            sell_amount,
+        )?;
+
         let order = &mut ctx.accounts.order;
         let config = &mut ctx.accounts.config;
 
@@ -37,6 +64,7 @@ pub mod dex_orders {
         order.filled = 0;
         order.status = OrderStatus::Open;
         order.bump = *ctx.bumps.get("order").unwrap();
+        order.vault_bump = *ctx.bumps.get("vault").unwrap();
 
         config.total_orders += 1;
 
@@ -53,7 +81,7 @@ pub mod dex_orders {
     }
 
     pub fn fill_order(ctx: Context<FillOrder>, fill_amount: u64) -> Result<()> {
-        let order = &mut ctx.accounts.order;
+        let order = &ctx.accounts.order;
         
         require!(matches!(order.status, OrderStatus::Open | OrderStatus::PartiallyFilled), OrderError::NotOpen);
         require!(fill_amount > 0, OrderError::InvalidAmount);
@@ -61,14 +89,26 @@ pub mod dex_orders {
         let remaining = order.sell_amount - order.filled;
         let actual_fill = fill_amount.min(remaining);
         
-        let required_payment = (actual_fill as u128 * order.buy_amount as u128 / order.sell_amount as u128) as u64;
+        let required_payment = required_payment(order, actual_fill).ok_or(OrderError::InvalidAmount)?;
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.taker_buy_account.to_account_info(),
+                    to: ctx.accounts.maker_buy_account.to_account_info(),
+                    authority: ctx.accounts.taker.to_account_info(),
+                },
+            ),
+            required_payment,
+        )?;
 
-        order.filled += actual_fill;
+        pay_from_vault(&ctx.accounts.token_program, &ctx.accounts.vault, &ctx.accounts.taker_sell_account, order.key(), order.vault_bump, actual_fill)?;
 
-        if order.filled >= order.sell_amount {
-            order.status = OrderStatus::Filled;
-        } else {
-            order.status = OrderStatus::PartiallyFilled;
+        let order = &mut ctx.accounts.order;
+        apply_fill(order, actual_fill);
+        if order.status == OrderStatus::Filled {
+            close_vault(&ctx.accounts.token_program, &ctx.accounts.vault, &ctx.accounts.maker.to_account_info(), order.key(), order.vault_bump)?;
         }
 
         emit!(OrderFilled {
@@ -81,11 +121,52 @@ pub mod dex_orders {
         Ok(())
     }
 
+    /// Permissionless: crosses two resting orders on opposite sides of a pair at the maker
+    /// order's price. Each side is paid out of the other's vault, and a vault is closed to its
+    /// order's maker once that order is completely filled.
+    pub fn match_orders(ctx: Context<MatchOrders>) -> Result<()> {
+        let maker_order = &ctx.accounts.maker_order;
+        let taker_order = &ctx.accounts.taker_order;
+
+        require!(matches!(maker_order.status, OrderStatus::Open | OrderStatus::PartiallyFilled), OrderError::NotOpen);
+        require!(matches!(taker_order.status, OrderStatus::Open | OrderStatus::PartiallyFilled), OrderError::NotOpen);
+
+        let (fill, payment) = match_amounts(maker_order, taker_order).ok_or(OrderError::NoCross)?;
+
+        pay_from_vault(&ctx.accounts.token_program, &ctx.accounts.maker_vault, &ctx.accounts.taker_buy_account, maker_order.key(), maker_order.vault_bump, fill)?;
+        pay_from_vault(&ctx.accounts.token_program, &ctx.accounts.taker_vault, &ctx.accounts.maker_buy_account, taker_order.key(), taker_order.vault_bump, payment)?;
+
+        let maker_order = &mut ctx.accounts.maker_order;
+        apply_fill(maker_order, fill);
+        if maker_order.status == OrderStatus::Filled {
+            close_vault(&ctx.accounts.token_program, &ctx.accounts.maker_vault, &ctx.accounts.maker.to_account_info(), maker_order.key(), maker_order.vault_bump)?;
+        }
+
+        let taker_order = &mut ctx.accounts.taker_order;
+        apply_fill(taker_order, payment);
+        if taker_order.status == OrderStatus::Filled {
+            close_vault(&ctx.accounts.token_program, &ctx.accounts.taker_vault, &ctx.accounts.taker.to_account_info(), taker_order.key(), taker_order.vault_bump)?;
+        }
+
+        emit!(OrdersMatched {
+            maker_order: ctx.accounts.maker_order.key(),
+            taker_order: ctx.accounts.taker_order.key(),
+            amount: fill,
+            payment,
+        });
+
+        Ok(())
+    }
+
     pub fn cancel_order(ctx: Context<CancelOrder>) -> Result<()> {
-        let order = &mut ctx.accounts.order;
+        let order = &ctx.accounts.order;
         
         require!(matches!(order.status, OrderStatus::Open | OrderStatus::PartiallyFilled), OrderError::NotOpen);
-        
+
+        pay_from_vault(&ctx.accounts.token_program, &ctx.accounts.vault, &ctx.accounts.maker_sell_account, order.key(), order.vault_bump, order.sell_amount - order.filled)?;
+        close_vault(&ctx.accounts.token_program, &ctx.accounts.vault, &ctx.accounts.maker.to_account_info(), order.key(), order.vault_bump)?;
+
+        let order = &mut ctx.accounts.order;
         order.status = OrderStatus::Cancelled;
 
         emit!(OrderCancelled {
@@ -102,6 +183,97 @@ pub mod dex_orders {
     }
 }
 
+/// Buy-asset payment owed for `fill` units of the order's sell asset, rounded up so the maker
+/// never receives less than their asking price.
+fn required_payment(order: &Order, fill: u64) -> Option<u64> {
+    let numerator = (fill as u128).checked_mul(order.buy_amount as u128)?;
+    let sell_amount = order.sell_amount as u128;
+    u64::try_from((numerator + sell_amount - 1) / sell_amount).ok()
+}
+
+/// Whether `a` and `b` trade the same pair in opposite directions and each accepts the
+/// other's price, i.e. `a.buy / a.sell <= b.sell / b.buy`.
+fn orders_cross(a: &Order, b: &Order) -> bool {
+    a.sell_asset == b.buy_asset
+        && a.buy_asset == b.sell_asset
+        && (a.buy_amount as u128) * (b.buy_amount as u128) <= (a.sell_amount as u128) * (b.sell_amount as u128)
+}
+
+/// Amounts exchanged when `taker` is matched against `maker` at the maker's price:
+/// `(maker sell units delivered, taker sell units paid)`, or `None` if nothing can trade.
+fn match_amounts(maker: &Order, taker: &Order) -> Option<(u64, u64)> {
+    if !orders_cross(maker, taker) {
+        return None;
+    }
+    let taker_remaining = taker.sell_amount - taker.filled;
+    let affordable = taker_remaining as u128 * maker.sell_amount as u128 / maker.buy_amount as u128;
+    let fill = (maker.sell_amount - maker.filled).min(u64::try_from(affordable).unwrap_or(u64::MAX));
+    if fill == 0 {
+        return None;
+    }
+    let payment = required_payment(maker, fill)?;
+    // Rounding the payment up must not push the taker past its own limit price
+    if payment as u128 * taker.buy_amount as u128 > fill as u128 * taker.sell_amount as u128 {
+        return None;
+    }
+    Some((fill, payment))
+}
+
+fn apply_fill(order: &mut Order, amount: u64) {
+    order.filled += amount;
+    order.status = if order.filled >= order.sell_amount {
+        OrderStatus::Filled
+    } else {
+        OrderStatus::PartiallyFilled
+    };
+}
+
+fn pay_from_vault<'info>(
+    token_program: &Program<'info, Token>,
+    vault: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    order_key: Pubkey,
+    vault_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let vault_seeds: &[&[u8]] = &[b"vault", order_key.as_ref(), &[vault_bump]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: vault.to_account_info(),
+                to: to.to_account_info(),
+                authority: vault.to_account_info(),
+            },
+            &[vault_seeds],
+        ),
+        amount,
+    )
+}
+
+/// Close an emptied order vault, returning its rent to `destination`
+fn close_vault<'info>(
+    token_program: &Program<'info, Token>,
+    vault: &Account<'info, TokenAccount>,
+    destination: &AccountInfo<'info>,
+    order_key: Pubkey,
+    vault_bump: u8,
+) -> Result<()> {
+    let vault_seeds: &[&[u8]] = &[b"vault", order_key.as_ref(), &[vault_bump]];
+    token::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: vault.to_account_info(),
+            destination: destination.clone(),
+            authority: vault.to_account_info(),
+        },
+        &[vault_seeds],
+    ))
+}
+
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(
@@ -120,6 +292,34 @@ pub struct Initialize<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(asset_id: u32)]
+pub struct RegisterAsset<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AssetMint::INIT_SPACE,
+        seeds = [b"asset", asset_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub asset: Account<'info, AssetMint>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(sell_asset: u32)]
 pub struct PlaceOrder<'info> {
     #[account(
         init,
@@ -137,10 +337,38 @@ pub struct PlaceOrder<'info> {
     )]
     pub config: Account<'info, Config>,
     
+    #[account(
+        seeds = [b"asset", sell_asset.to_le_bytes().as_ref()],
+        bump = sell_asset_mint.bump
+    )]
+    pub sell_asset_mint: Account<'info, AssetMint>,
+    
+    #[account(address = sell_asset_mint.mint)]
+    pub sell_mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = maker,
+        seeds = [b"vault", order.key().as_ref()],
+        bump,
+        token::mint = sell_mint,
+        token::authority = vault
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        token::mint = sell_mint,
+        token::authority = maker
+    )]
+    pub maker_sell_account: Account<'info, TokenAccount>,
+    
     #[account(mut)]
     pub maker: Signer<'info>,
     
+    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
@@ -148,7 +376,98 @@ pub struct FillOrder<'info> {
     #[account(mut)]
     pub order: Account<'info, Order>,
     
+    #[account(
+        mut,
+        seeds = [b"vault", order.key().as_ref()],
+        bump = order.vault_bump
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        seeds = [b"asset", order.buy_asset.to_le_bytes().as_ref()],
+        bump = buy_asset_mint.bump
+    )]
+    pub buy_asset_mint: Account<'info, AssetMint>,
+    
+    #[account(
+        mut,
+        constraint = taker_buy_account.mint == buy_asset_mint.mint @ OrderError::InvalidTokenAccount,
+        constraint = taker_buy_account.owner == taker.key() @ OrderError::InvalidTokenAccount
+    )]
+    pub taker_buy_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = taker_sell_account.mint == vault.mint @ OrderError::InvalidTokenAccount
+    )]
+    pub taker_sell_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = maker_buy_account.mint == buy_asset_mint.mint @ OrderError::InvalidTokenAccount,
+        constraint = maker_buy_account.owner == order.maker @ OrderError::InvalidTokenAccount
+    )]
+    pub maker_buy_account: Account<'info, TokenAccount>,
+    
+    /// Receives the vault's rent once the order is completely filled
+    #[account(mut, address = order.maker @ OrderError::InvalidTokenAccount)]
+    pub maker: SystemAccount<'info>,
+    
     pub taker: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct MatchOrders<'info> {
+    #[account(mut)]
+    pub maker_order: Account<'info, Order>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", maker_order.key().as_ref()],
+        bump = maker_order.vault_bump
+    )]
+    pub maker_vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = taker_order.key() != maker_order.key() @ OrderError::NoCross
+    )]
+    pub taker_order: Account<'info, Order>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", taker_order.key().as_ref()],
+        bump = taker_order.vault_bump
+    )]
+    pub taker_vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = maker_buy_account.mint == taker_vault.mint @ OrderError::InvalidTokenAccount,
+        constraint = maker_buy_account.owner == maker_order.maker @ OrderError::InvalidTokenAccount
+    )]
+    pub maker_buy_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = taker_buy_account.mint == maker_vault.mint @ OrderError::InvalidTokenAccount,
+        constraint = taker_buy_account.owner == taker_order.maker @ OrderError::InvalidTokenAccount
+    )]
+    pub taker_buy_account: Account<'info, TokenAccount>,
+    
+    /// Receives the maker order's vault rent once it is completely filled
+    #[account(mut, address = maker_order.maker @ OrderError::InvalidTokenAccount)]
+    pub maker: SystemAccount<'info>,
+    
+    /// Receives the taker order's vault rent once it is completely filled
+    #[account(mut, address = taker_order.maker @ OrderError::InvalidTokenAccount)]
+    pub taker: SystemAccount<'info>,
+    
+    pub matcher: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
@@ -160,8 +479,24 @@ pub struct CancelOrder<'info> {
     )]
     pub order: Account<'info, Order>,
     
+    #[account(
+        mut,
+        seeds = [b"vault", order.key().as_ref()],
+        bump = order.vault_bump
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = maker_sell_account.mint == vault.mint @ OrderError::InvalidTokenAccount,
+        constraint = maker_sell_account.owner == maker.key() @ OrderError::InvalidTokenAccount
+    )]
+    pub maker_sell_account: Account<'info, TokenAccount>,
+    
     #[account(mut)]
     pub maker: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
@@ -177,6 +512,14 @@ pub struct Config {
     pub bump: u8,
 }
 
+#[account]
+#[derive(InitSpace)]
+pub struct AssetMint {
+    pub asset_id: u32,
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Order {
@@ -188,6 +531,7 @@ pub struct Order {
     pub filled: u64,
     pub status: OrderStatus,
     pub bump: u8,
+    pub vault_bump: u8,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
@@ -198,6 +542,12 @@ pub enum OrderStatus {
     Cancelled,
 }
 
+#[event]
+pub struct AssetRegistered {
+    pub asset_id: u32,
+    pub mint: Pubkey,
+}
+
 #[event]
 pub struct OrderPlaced {
     pub maker: Pubkey,
@@ -216,6 +566,14 @@ pub struct OrderFilled {
     pub payment: u64,
 }
 
+#[event]
+pub struct OrdersMatched {
+    pub maker_order: Pubkey,
+    pub taker_order: Pubkey,
+    pub amount: u64,
+    pub payment: u64,
+}
+
 #[event]
 pub struct OrderCancelled {
     pub maker: Pubkey,
@@ -230,4 +588,89 @@ pub enum OrderError {
     NotOpen,
     #[msg("Invalid amount specified")]
     InvalidAmount,
+    #[msg("Invalid token account for this order")]
+    InvalidTokenAccount,
+    #[msg("Orders do not cross")]
+    NoCross,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(sell_asset: u32, buy_asset: u32, sell_amount: u64, buy_amount: u64) -> Order {
+        Order {
+            maker: Pubkey::new_unique(),
+            sell_asset,
+            buy_asset,
+            sell_amount,
+            buy_amount,
+            filled: 0,
+            status: OrderStatus::Open,
+            bump: 0,
+            vault_bump: 0,
+        }
+    }
+
+    #[test]
+    fn required_payment_rounds_up_for_the_maker() {
+        // 3 units for 10: each unit costs 3.33, so the taker pays 4 for one
+        let ask = order(1, 2, 3, 10);
+        assert_eq!(required_payment(&ask, 1), Some(4));
+        assert_eq!(required_payment(&ask, 3), Some(10));
+
+        let even = order(1, 2, 100, 50);
+        assert_eq!(required_payment(&even, 40), Some(20));
+    }
+
+    #[test]
+    fn partial_then_full_fill_transitions_status() {
+        let mut ask = order(1, 2, 100, 50);
+        apply_fill(&mut ask, 40);
+        assert!(ask.status == OrderStatus::PartiallyFilled);
+        apply_fill(&mut ask, 60);
+        assert!(ask.status == OrderStatus::Filled);
+        assert_eq!(ask.filled, 100);
+    }
+
+    #[test]
+    fn crossing_requires_opposite_pair_and_compatible_prices() {
+        // Sells 100 of asset 1 at 0.5 of asset 2 each
+        let ask = order(1, 2, 100, 50);
+        // Pays up to 0.6 of asset 2 per unit of asset 1
+        assert!(orders_cross(&ask, &order(2, 1, 60, 100)));
+        // Only pays 0.4 per unit
+        assert!(!orders_cross(&ask, &order(2, 1, 40, 100)));
+        // Same direction never crosses
+        assert!(!orders_cross(&ask, &order(1, 2, 100, 10)));
+        assert_eq!(match_amounts(&ask, &order(2, 1, 40, 100)), None);
+    }
+
+    #[test]
+    fn match_fills_at_maker_price_when_taker_is_smaller() {
+        let ask = order(1, 2, 100, 50);
+        let bid = order(2, 1, 30, 50);
+        // The bid's 30 buys 60 at the maker's price of 0.5
+        assert_eq!(match_amounts(&ask, &bid), Some((60, 30)));
+    }
+
+    #[test]
+    fn match_fills_remaining_maker_when_taker_is_larger() {
+        let mut ask = order(1, 2, 100, 50);
+        apply_fill(&mut ask, 80);
+        let bid = order(2, 1, 60, 100);
+        assert_eq!(match_amounts(&ask, &bid), Some((20, 10)));
+    }
+
+    #[test]
+    fn match_rejects_rounding_past_the_taker_limit() {
+        // The maker asks 3.33 per unit and the taker bids 3.5
+        let ask = order(1, 2, 3, 10);
+        assert_eq!(match_amounts(&ask, &order(2, 1, 7, 2)), Some((2, 7)));
+
+        // With 4 left the taker affords one unit, but its rounded-up price of 4 is over the bid
+        let mut bid = order(2, 1, 7, 2);
+        bid.filled = 3;
+        assert_eq!(match_amounts(&ask, &bid), None);
+    }
 }
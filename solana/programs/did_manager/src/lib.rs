@@ -34,10 +34,26 @@ pub mod did_manager {
 
     pub fn deactivate_did(ctx: Context<DeactivateDID>) -> Result<()> {
         let did_doc = &mut ctx.accounts.did_document;
-        did_doc.active = false;
+        deactivate(did_doc)?;
         emit!(DIDDeactivated { controller: did_doc.controller });
         Ok(())
     }
+
+    pub fn resolve_did(ctx: Context<ResolveDID>) -> Result<[u8; 32]> {
+        resolve(&ctx.accounts.did_document)
+    }
+}
+
+/// Deactivation is one-way: nothing sets `active` back to true
+fn deactivate(did_doc: &mut DIDDocument) -> Result<()> {
+    require!(did_doc.active, DIDError::AlreadyDeactivated);
+    did_doc.active = false;
+    Ok(())
+}
+
+fn resolve(did_doc: &DIDDocument) -> Result<[u8; 32]> {
+    require!(did_doc.active, DIDError::NotActive);
+    Ok(did_doc.public_key)
 }
 
 #[derive(Accounts)]
@@ -74,6 +90,12 @@ pub struct DeactivateDID<'info> {
     pub controller: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct ResolveDID<'info> {
+    #[account(seeds = [b"did", did_document.controller.as_ref()], bump = did_document.bump)]
+    pub did_document: Account<'info, DIDDocument>,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
@@ -110,4 +132,26 @@ pub struct DIDDeactivated {
 pub enum DIDError {
     #[msg("DID not active")]
     NotActive,
+    #[msg("DID already deactivated")]
+    AlreadyDeactivated,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_returns_key_until_deactivated() {
+        let mut did_doc = DIDDocument {
+            controller: Pubkey::new_unique(),
+            public_key: [9; 32],
+            active: true,
+            bump: 0,
+        };
+        assert_eq!(resolve(&did_doc).unwrap(), [9; 32]);
+
+        deactivate(&mut did_doc).unwrap();
+        assert_eq!(resolve(&did_doc).unwrap_err(), DIDError::NotActive.into());
+        assert_eq!(deactivate(&mut did_doc).unwrap_err(), DIDError::AlreadyDeactivated.into());
+    }
 }
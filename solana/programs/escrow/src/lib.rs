@@ -1,5 +1,6 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::{clock::Clock, hash::hash};
+use anchor_lang::system_program;
 
 declare_id!("EscrowXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX");
 
@@ -15,15 +16,25 @@ pub mod escrow {
         Ok(())
     }
 
-    pub fn create_escrow(ctx: Context<CreateEscrow>, amount: u64, hash_lock: [u8; 32], time_lock: i64) -> Result<()> {
-        let escrow = &mut ctx.accounts.escrow;
+    pub fn create_escrow(ctx: Context<CreateEscrow>, amount: u64, hash_lock: [u8; 32], time_lock: i64, unlock_at: i64) -> Result<()> {
         let clock = Clock::get()?;
         require!(time_lock > clock.unix_timestamp, EscrowError::InvalidTimeLock);
+        require!(unlock_at >= 0 && unlock_at < time_lock, EscrowError::InvalidUnlockTime);
+        require!(amount > 0, EscrowError::InvalidAmount);
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer { from: ctx.accounts.sender.to_account_info(), to: ctx.accounts.escrow.to_account_info() },
+            ),
+            amount,
+        )?;
+        let escrow = &mut ctx.accounts.escrow;
         escrow.sender = ctx.accounts.sender.key();
         escrow.recipient = ctx.accounts.recipient.key();
         escrow.amount = amount;
         escrow.hash_lock = hash_lock;
         escrow.time_lock = time_lock;
+        escrow.unlock_at = unlock_at;
         escrow.status = EscrowStatus::Active;
         escrow.bump = *ctx.bumps.get("escrow").unwrap();
         let config = &mut ctx.accounts.config;
@@ -35,9 +46,10 @@ pub mod escrow {
     pub fn complete_escrow(ctx: Context<CompleteEscrow>, preimage: [u8; 32]) -> Result<()> {
         let escrow = &mut ctx.accounts.escrow;
         require!(matches!(escrow.status, EscrowStatus::Active), EscrowError::NotActive);
-        let computed_hash = hash(&preimage).to_bytes();
-        require!(computed_hash == escrow.hash_lock, EscrowError::InvalidPreimage);
+        require!(claim_unlocked(escrow, Clock::get()?.unix_timestamp), EscrowError::StillLocked);
+        require!(preimage_matches(escrow, &preimage), EscrowError::InvalidPreimage);
         escrow.status = EscrowStatus::Completed;
+        release_lamports(&escrow.to_account_info(), &ctx.accounts.recipient.to_account_info(), escrow.amount)?;
         emit!(EscrowCompleted { sender: escrow.sender, recipient: escrow.recipient });
         Ok(())
     }
@@ -46,13 +58,36 @@ pub mod escrow {
         let escrow = &mut ctx.accounts.escrow;
         let clock = Clock::get()?;
         require!(matches!(escrow.status, EscrowStatus::Active), EscrowError::NotActive);
-        require!(clock.unix_timestamp >= escrow.time_lock, EscrowError::TimeLockNotExpired);
+        require!(refund_open(escrow, clock.unix_timestamp), EscrowError::TimeLockNotExpired);
         escrow.status = EscrowStatus::Refunded;
+        release_lamports(&escrow.to_account_info(), &ctx.accounts.sender.to_account_info(), escrow.amount)?;
         emit!(EscrowRefunded { sender: escrow.sender, recipient: escrow.recipient });
         Ok(())
     }
 }
 
+fn preimage_matches(escrow: &Escrow, preimage: &[u8; 32]) -> bool {
+    hash(preimage).to_bytes() == escrow.hash_lock
+}
+
+/// An `unlock_at` of 0 means the recipient may claim immediately
+fn claim_unlocked(escrow: &Escrow, now: i64) -> bool {
+    escrow.unlock_at == 0 || now >= escrow.unlock_at
+}
+
+fn refund_open(escrow: &Escrow, now: i64) -> bool {
+    now >= escrow.time_lock
+}
+
+/// Moves the escrowed lamports out of the program-owned escrow account. Rent stays
+/// behind and is returned by the `close` constraint.
+fn release_lamports(vault: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let remaining = vault.lamports().checked_sub(amount).ok_or(EscrowError::InsufficientVaultBalance)?;
+    **vault.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? = to.lamports().checked_add(amount).ok_or(EscrowError::InvalidAmount)?;
+    Ok(())
+}
+
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(init, payer = authority, space = 8 + Config::INIT_SPACE, seeds = [b"config"], bump)]
@@ -107,6 +142,8 @@ pub struct Escrow {
     pub amount: u64,
     pub hash_lock: [u8; 32],
     pub time_lock: i64,
+    /// Earliest time the recipient may claim; 0 means no claim delay.
+    pub unlock_at: i64,
     pub status: EscrowStatus,
     pub bump: u8,
 }
@@ -147,4 +184,70 @@ pub enum EscrowError {
     InvalidPreimage,
     #[msg("Time lock not expired")]
     TimeLockNotExpired,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Insufficient vault balance")]
+    InsufficientVaultBalance,
+    #[msg("Invalid unlock time")]
+    InvalidUnlockTime,
+    #[msg("Escrow still locked")]
+    StillLocked,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREIMAGE: [u8; 32] = [42; 32];
+
+    fn escrow(time_lock: i64, unlock_at: i64) -> Escrow {
+        Escrow {
+            sender: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            amount: 1_000,
+            hash_lock: hash(&PREIMAGE).to_bytes(),
+            time_lock,
+            unlock_at,
+            status: EscrowStatus::Active,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn recipient_receives_amount_on_correct_preimage() {
+        let escrow = escrow(1_000, 0);
+        assert!(preimage_matches(&escrow, &PREIMAGE));
+        assert!(!preimage_matches(&escrow, &[0; 32]));
+
+        let (vault_key, recipient_key, owner) = (Pubkey::new_unique(), escrow.recipient, Pubkey::new_unique());
+        // Rent of 500 stays behind for the close constraint
+        let (mut vault_lamports, mut recipient_lamports) = (1_500u64, 0u64);
+        let (mut vault_data, mut recipient_data) = (vec![], vec![]);
+        let vault = AccountInfo::new(&vault_key, false, true, &mut vault_lamports, &mut vault_data, &owner, false, 0);
+        let recipient = AccountInfo::new(&recipient_key, false, true, &mut recipient_lamports, &mut recipient_data, &owner, false, 0);
+
+        release_lamports(&vault, &recipient, escrow.amount).unwrap();
+        assert_eq!(recipient.lamports(), 1_000);
+        assert_eq!(vault.lamports(), 500);
+        assert!(release_lamports(&vault, &recipient, escrow.amount).is_err());
+    }
+
+    #[test]
+    fn sender_refund_opens_at_time_lock() {
+        let escrow = escrow(1_000, 0);
+        assert!(!refund_open(&escrow, 999));
+        assert!(refund_open(&escrow, 1_000));
+    }
+
+    #[test]
+    fn claim_waits_for_unlock_at() {
+        let delayed = escrow(1_000, 500);
+        assert!(!claim_unlocked(&delayed, 499));
+        assert!(claim_unlocked(&delayed, 500));
+        // The preimage is still required once unlocked
+        assert!(!preimage_matches(&delayed, &[0; 32]));
+
+        // Zero keeps the old immediate-claim behaviour
+        assert!(claim_unlocked(&escrow(1_000, 0), 0));
+    }
 }
@@ -1,5 +1,8 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::{self as sysvar_instructions, load_current_index_checked, load_instruction_at_checked};
+use anchor_lang::system_program;
 
 declare_id!("PaymentChannelsXXXXXXXXXXXXXXXXXXXXXXXXX");
 
@@ -16,9 +19,16 @@ pub mod payment_channels {
     }
 
     pub fn open_channel(ctx: Context<OpenChannel>, amount: u64, expiration: i64) -> Result<()> {
-        let channel = &mut ctx.accounts.channel;
         let clock = Clock::get()?;
         require!(expiration > clock.unix_timestamp, ChannelError::InvalidExpiration);
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer { from: ctx.accounts.participant_a.to_account_info(), to: ctx.accounts.channel.to_account_info() },
+            ),
+            amount,
+        )?;
+        let channel = &mut ctx.accounts.channel;
         channel.participant_a = ctx.accounts.participant_a.key();
         channel.participant_b = ctx.accounts.participant_b.key();
         channel.balance_a = amount;
@@ -35,9 +45,16 @@ pub mod payment_channels {
     }
 
     pub fn fund_channel(ctx: Context<FundChannel>, amount: u64) -> Result<()> {
+        require!(matches!(ctx.accounts.channel.status, ChannelStatus::Open), ChannelError::ChannelNotOpen);
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer { from: ctx.accounts.participant_b.to_account_info(), to: ctx.accounts.channel.to_account_info() },
+            ),
+            amount,
+        )?;
         let channel = &mut ctx.accounts.channel;
-        require!(matches!(channel.status, ChannelStatus::Open), ChannelError::ChannelNotOpen);
-        channel.balance_b += amount;
+        channel.balance_b = channel.balance_b.checked_add(amount).ok_or(ChannelError::InvalidBalances)?;
         Ok(())
     }
 
@@ -46,16 +63,22 @@ pub mod payment_channels {
         require!(matches!(channel.status, ChannelStatus::Open), ChannelError::ChannelNotOpen);
         require!(final_balance_a + final_balance_b == channel.balance_a + channel.balance_b, ChannelError::InvalidBalances);
         channel.status = ChannelStatus::Closed;
-        emit!(ChannelClosed { participant_a: channel.participant_a, participant_b: channel.participant_b });
+        let vault = channel.to_account_info();
+        release_lamports(&vault, &ctx.accounts.participant_a.to_account_info(), final_balance_a)?;
+        release_lamports(&vault, &ctx.accounts.participant_b.to_account_info(), final_balance_b)?;
+        emit!(ChannelClosed { participant_a: channel.participant_a, participant_b: channel.participant_b, balance_a: final_balance_a, balance_b: final_balance_b });
         Ok(())
     }
 
     pub fn raise_dispute(ctx: Context<RaiseDispute>, nonce: u64, balance_a: u64, balance_b: u64) -> Result<()> {
         let channel = &mut ctx.accounts.channel;
         let clock = Clock::get()?;
+        let counterparty = counterparty_of(channel, &ctx.accounts.participant.key()).ok_or(ChannelError::Unauthorized)?;
         require!(matches!(channel.status, ChannelStatus::Open), ChannelError::ChannelNotOpen);
         require!(nonce > channel.nonce, ChannelError::InvalidNonce);
         require!(balance_a + balance_b == channel.balance_a + channel.balance_b, ChannelError::InvalidBalances);
+        let message = state_message(&channel.key(), nonce, balance_a, balance_b);
+        verify_counterparty_signature(&ctx.accounts.instructions, &counterparty, &message)?;
         channel.status = ChannelStatus::InDispute;
         channel.nonce = nonce;
         channel.balance_a = balance_a;
@@ -71,11 +94,77 @@ pub mod payment_channels {
         require!(matches!(channel.status, ChannelStatus::InDispute), ChannelError::NoDispute);
         require!(clock.unix_timestamp >= channel.dispute_expiration, ChannelError::DisputePeriodNotOver);
         channel.status = ChannelStatus::Closed;
-        emit!(ChannelClosed { participant_a: channel.participant_a, participant_b: channel.participant_b });
+        let vault = channel.to_account_info();
+        release_lamports(&vault, &ctx.accounts.participant_a.to_account_info(), channel.balance_a)?;
+        release_lamports(&vault, &ctx.accounts.participant_b.to_account_info(), channel.balance_b)?;
+        emit!(ChannelClosed { participant_a: channel.participant_a, participant_b: channel.participant_b, balance_a: channel.balance_a, balance_b: channel.balance_b });
         Ok(())
     }
 }
 
+/// The other participant of the channel, or `None` if `participant` is not part of it
+fn counterparty_of(channel: &Channel, participant: &Pubkey) -> Option<Pubkey> {
+    if *participant == channel.participant_a {
+        Some(channel.participant_b)
+    } else if *participant == channel.participant_b {
+        Some(channel.participant_a)
+    } else {
+        None
+    }
+}
+
+/// Off-chain channel state signed by a participant:
+/// `channel (32 bytes) || nonce (u64 LE) || balance_a (u64 LE) || balance_b (u64 LE)`.
+pub fn state_message(channel: &Pubkey, nonce: u64, balance_a: u64, balance_b: u64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(56);
+    message.extend_from_slice(channel.as_ref());
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(&balance_a.to_le_bytes());
+    message.extend_from_slice(&balance_b.to_le_bytes());
+    message
+}
+
+/// Checks that the instruction immediately before this one is an Ed25519 program
+/// verification of `message` by `signer`. The Ed25519 program has already rejected
+/// the transaction if the signature is bad, so only the key and message need matching.
+fn verify_counterparty_signature(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let current = load_current_index_checked(instructions)?;
+    require!(current > 0, ChannelError::MissingStateSignature);
+    let ix = load_instruction_at_checked((current - 1) as usize, instructions)?;
+    require!(ix.program_id == ed25519_program::ID, ChannelError::MissingStateSignature);
+    require!(ed25519_data_matches(&ix.data, signer, message), ChannelError::InvalidStateSignature);
+    Ok(())
+}
+
+/// Whether Ed25519 program instruction data verifies exactly one signature by `signer` over
+/// `message`, with the key, signature and message all carried in that instruction.
+fn ed25519_data_matches(data: &[u8], signer: &Pubkey, message: &[u8]) -> bool {
+    if data.len() < 16 || data[0] != 1 {
+        return false;
+    }
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as usize;
+    let this_ix = u16::MAX as usize;
+    if read_u16(4) != this_ix || read_u16(8) != this_ix || read_u16(14) != this_ix {
+        return false;
+    }
+    let (pubkey_offset, message_offset, message_len) = (read_u16(6), read_u16(10), read_u16(12));
+    data.get(pubkey_offset..pubkey_offset + 32) == Some(signer.as_ref())
+        && message_len == message.len()
+        && data.get(message_offset..message_offset + message_len) == Some(message)
+}
+
+/// Moves escrowed lamports out of the program-owned channel account. Rent stays
+/// behind and is returned by the `close` constraint.
+fn release_lamports(vault: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let remaining = vault.lamports().checked_sub(amount).ok_or(ChannelError::InsufficientVaultBalance)?;
+    **vault.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? = to.lamports().checked_add(amount).ok_or(ChannelError::InvalidBalances)?;
+    Ok(())
+}
+
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(init, payer = authority, space = 8 + Config::INIT_SPACE, seeds = [b"config"], bump)]
@@ -104,14 +193,17 @@ pub struct FundChannel<'info> {
     pub channel: Account<'info, Channel>,
     #[account(mut)]
     pub participant_b: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct CooperativeClose<'info> {
-    #[account(mut, seeds = [b"channel", channel.participant_a.as_ref(), channel.participant_b.as_ref()], bump = channel.bump, close = participant)]
+    #[account(mut, seeds = [b"channel", channel.participant_a.as_ref(), channel.participant_b.as_ref()], bump = channel.bump, close = participant_a)]
     pub channel: Account<'info, Channel>,
-    #[account(mut)]
-    pub participant: Signer<'info>,
+    #[account(mut, constraint = channel.participant_a == participant_a.key())]
+    pub participant_a: Signer<'info>,
+    #[account(mut, constraint = channel.participant_b == participant_b.key())]
+    pub participant_b: Signer<'info>,
 }
 
 #[derive(Accounts)]
@@ -119,6 +211,9 @@ pub struct RaiseDispute<'info> {
     #[account(mut, seeds = [b"channel", channel.participant_a.as_ref(), channel.participant_b.as_ref()], bump = channel.bump)]
     pub channel: Account<'info, Channel>,
     pub participant: Signer<'info>,
+    /// CHECK: Instructions sysvar
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
@@ -126,8 +221,11 @@ pub struct SettleDispute<'info> {
     #[account(mut, seeds = [b"channel", channel.participant_a.as_ref(), channel.participant_b.as_ref()], bump = channel.bump, close = participant_a)]
     pub channel: Account<'info, Channel>,
     /// CHECK: Participant A
-    #[account(mut)]
+    #[account(mut, constraint = channel.participant_a == participant_a.key())]
     pub participant_a: AccountInfo<'info>,
+    /// CHECK: Participant B
+    #[account(mut, constraint = channel.participant_b == participant_b.key())]
+    pub participant_b: AccountInfo<'info>,
 }
 
 #[account]
@@ -176,6 +274,8 @@ pub struct DisputeRaised {
 pub struct ChannelClosed {
     pub participant_a: Pubkey,
     pub participant_b: Pubkey,
+    pub balance_a: u64,
+    pub balance_b: u64,
 }
 
 #[error_code]
@@ -192,4 +292,121 @@ pub enum ChannelError {
     NoDispute,
     #[msg("Dispute period not over")]
     DisputePeriodNotOver,
+    #[msg("Insufficient vault balance")]
+    InsufficientVaultBalance,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Missing counterparty state signature")]
+    MissingStateSignature,
+    #[msg("Invalid counterparty state signature")]
+    InvalidStateSignature,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(participant_a: Pubkey, participant_b: Pubkey) -> Channel {
+        Channel {
+            participant_a,
+            participant_b,
+            balance_a: 1_000,
+            balance_b: 0,
+            nonce: 0,
+            status: ChannelStatus::Open,
+            expiration: 0,
+            dispute_expiration: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn only_participants_have_a_counterparty() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let channel = channel(a, b);
+        assert_eq!(counterparty_of(&channel, &a), Some(b));
+        assert_eq!(counterparty_of(&channel, &b), Some(a));
+        // A third party cannot raise a dispute
+        assert_eq!(counterparty_of(&channel, &Pubkey::new_unique()), None);
+    }
+
+    /// Ed25519 program instruction data in the layout the native program expects:
+    /// a 16-byte header of offsets, then the public key, signature and message.
+    fn ed25519_data(signer: &Pubkey, message: &[u8], instruction_index: u16) -> Vec<u8> {
+        let (pubkey_offset, signature_offset, message_offset) = (16u16, 48u16, 112u16);
+        let mut data = vec![1u8, 0];
+        for field in [
+            signature_offset,
+            instruction_index,
+            pubkey_offset,
+            instruction_index,
+            message_offset,
+            message.len() as u16,
+            instruction_index,
+        ] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[7u8; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn state_message_layout() {
+        let channel = Pubkey::new_unique();
+        let message = state_message(&channel, 3, 400, 600);
+        assert_eq!(message.len(), 56);
+        assert_eq!(&message[..32], channel.as_ref());
+        assert_eq!(&message[32..40], &3u64.to_le_bytes());
+        assert_eq!(&message[40..48], &400u64.to_le_bytes());
+        assert_eq!(&message[48..56], &600u64.to_le_bytes());
+    }
+
+    #[test]
+    fn counterparty_signed_state_is_accepted() {
+        let counterparty = Pubkey::new_unique();
+        let message = state_message(&Pubkey::new_unique(), 2, 300, 700);
+        let data = ed25519_data(&counterparty, &message, u16::MAX);
+        assert!(ed25519_data_matches(&data, &counterparty, &message));
+    }
+
+    #[test]
+    fn unilaterally_claimed_state_is_rejected() {
+        let (participant, counterparty) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let channel = Pubkey::new_unique();
+        let message = state_message(&channel, 2, 300, 700);
+
+        // Signed by the disputing participant rather than the counterparty
+        let own = ed25519_data(&participant, &message, u16::MAX);
+        assert!(!ed25519_data_matches(&own, &counterparty, &message));
+
+        // Counterparty signed a different state than the one claimed
+        let signed = ed25519_data(&counterparty, &state_message(&channel, 2, 700, 300), u16::MAX);
+        assert!(!ed25519_data_matches(&signed, &counterparty, &message));
+
+        // Key or message taken from another instruction
+        let elsewhere = ed25519_data(&counterparty, &message, 0);
+        assert!(!ed25519_data_matches(&elsewhere, &counterparty, &message));
+
+        assert!(!ed25519_data_matches(&[], &counterparty, &message));
+    }
+
+    #[test]
+    fn release_lamports_moves_escrow_out_of_the_vault() {
+        let (vault_key, to_key, owner) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut vault_lamports, mut to_lamports) = (1_500u64, 0u64);
+        let (mut vault_data, mut to_data) = (vec![], vec![]);
+        let vault = AccountInfo::new(&vault_key, false, true, &mut vault_lamports, &mut vault_data, &owner, false, 0);
+        let to = AccountInfo::new(&to_key, false, true, &mut to_lamports, &mut to_data, &owner, false, 0);
+
+        release_lamports(&vault, &to, 1_000).unwrap();
+        assert_eq!(vault.lamports(), 500);
+        assert_eq!(to.lamports(), 1_000);
+
+        // Zero payouts are a no-op and the vault can never be overdrawn
+        release_lamports(&vault, &to, 0).unwrap();
+        assert!(release_lamports(&vault, &to, 501).is_err());
+        assert_eq!(vault.lamports(), 500);
+    }
 }
@@ -1,7 +1,10 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
 
 declare_id!("SignerListXXXXXXXXXXXXXXXXXXXXXXXXXXXXX");
 
+pub const MAX_SIGNERS: usize = 32;
+
 #[program]
 pub mod signer_list {
     use super::*;
@@ -14,13 +17,17 @@ pub mod signer_list {
         Ok(())
     }
 
-    pub fn create_signer_list(ctx: Context<CreateSignerList>) -> Result<()> {
+    pub fn create_signer_list(ctx: Context<CreateSignerList>, quorum: u32) -> Result<()> {
+        require!(quorum > 0, SignerListError::InvalidQuorum);
+
         let list = &mut ctx.accounts.signer_list;
         let config = &mut ctx.accounts.config;
 
         list.owner = ctx.accounts.owner.key();
+        list.quorum = quorum;
         list.total_weight = 0;
         list.signer_count = 0;
+        list.proposal_count = 0;
         list.bump = *ctx.bumps.get("signer_list").unwrap();
 
         config.total_lists += 1;
@@ -36,6 +43,13 @@ pub mod signer_list {
         require!(weight > 0, SignerListError::InvalidWeight);
 
         let list = &mut ctx.accounts.signer_list;
+        require!((list.signer_count as usize) < MAX_SIGNERS, SignerListError::TooManySigners);
+
+        let entry = &mut ctx.accounts.signer_entry;
+        entry.signer_list = list.key();
+        entry.signer = signer;
+        entry.weight = weight;
+        entry.bump = *ctx.bumps.get("signer_entry").unwrap();
 
         list.total_weight += weight;
         list.signer_count += 1;
@@ -49,8 +63,9 @@ pub mod signer_list {
         Ok(())
     }
 
-    pub fn remove_signer(ctx: Context<RemoveSigner>, weight: u32) -> Result<()> {
+    pub fn remove_signer(ctx: Context<RemoveSigner>) -> Result<()> {
         let list = &mut ctx.accounts.signer_list;
+        let weight = ctx.accounts.signer_entry.weight;
 
         require!(list.total_weight >= weight, SignerListError::InsufficientWeight);
         require!(list.signer_count > 0, SignerListError::NoSigners);
@@ -60,28 +75,50 @@ pub mod signer_list {
 
         emit!(SignerRemoved {
             owner: list.owner,
+            signer: ctx.accounts.signer_entry.signer,
             weight,
         });
 
         Ok(())
     }
 
+    /// Creates a spend proposal and escrows `amount` lamports in the proposal account, which
+    /// acts as the vault until execution. If it has not executed by `deadline` the owner may
+    /// cancel it and recover the escrow.
     pub fn create_proposal(
         ctx: Context<CreateProposal>,
         target: Pubkey,
         amount: u64,
+        deadline: i64,
     ) -> Result<()> {
+        require!(amount > 0, SignerListError::InvalidAmount);
+        require!(deadline > Clock::get()?.unix_timestamp, SignerListError::InvalidDeadline);
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer { from: ctx.accounts.owner.to_account_info(), to: ctx.accounts.proposal.to_account_info() },
+            ),
+            amount,
+        )?;
+
         let proposal = &mut ctx.accounts.proposal;
+        let list = &mut ctx.accounts.signer_list;
 
         proposal.owner = ctx.accounts.owner.key();
+        proposal.proposal_id = list.proposal_count;
         proposal.target = target;
         proposal.amount = amount;
         proposal.approvals_weight = 0;
+        proposal.approvers = Vec::new();
         proposal.executed = false;
+        proposal.deadline = deadline;
         proposal.bump = *ctx.bumps.get("proposal").unwrap();
 
+        list.proposal_count += 1;
+
         emit!(ProposalCreated {
             owner: proposal.owner,
+            proposal_id: proposal.proposal_id,
             target,
             amount,
         });
@@ -89,31 +126,53 @@ pub mod signer_list {
         Ok(())
     }
 
-    pub fn approve_proposal(ctx: Context<ApproveProposal>, weight: u32) -> Result<()> {
+    /// Adds the approver's registered weight to the proposal. The approver must hold a signer
+    /// entry on the owner's list and may approve each proposal only once.
+    pub fn approve_proposal(ctx: Context<ApproveProposal>) -> Result<()> {
         let proposal = &mut ctx.accounts.proposal;
+        let approver = ctx.accounts.approver.key();
+        let weight = ctx.accounts.signer_entry.weight;
 
-        require!(!proposal.executed, SignerListError::AlreadyExecuted);
-        require!(weight > 0, SignerListError::InvalidWeight);
-
-        proposal.approvals_weight += weight;
+        record_approval(proposal, approver, weight)?;
 
         emit!(ProposalApproved {
             owner: proposal.owner,
-            approver: ctx.accounts.approver.key(),
+            approver,
             weight,
+            approvals_weight: proposal.approvals_weight,
+            approver_count: proposal.approvers.len() as u32,
         });
 
         Ok(())
     }
 
-    pub fn execute_proposal(ctx: Context<ExecuteProposal>, quorum: u32) -> Result<()> {
+    /// Pays the escrowed amount to the target once approvals reach the list's quorum.
+    ///
+    /// The approvers' signer entries are passed as remaining accounts and their weight is
+    /// recomputed from the current list, so approvals from signers removed (or re-weighted)
+    /// since approving no longer count at their old weight.
+    pub fn execute_proposal<'info>(ctx: Context<'_, '_, '_, 'info, ExecuteProposal<'info>>) -> Result<()> {
+        let list_key = ctx.accounts.signer_list.key();
+        let quorum = ctx.accounts.signer_list.quorum;
+
+        let mut entries = Vec::with_capacity(ctx.remaining_accounts.len());
+        for info in ctx.remaining_accounts.iter() {
+            let entry = Account::<SignerEntry>::try_from(info)?;
+            require_keys_eq!(entry.signer_list, list_key, SignerListError::InvalidSignerEntry);
+            entries.push((entry.signer, entry.weight));
+        }
+
         let proposal = &mut ctx.accounts.proposal;
 
         require!(!proposal.executed, SignerListError::AlreadyExecuted);
-        require!(proposal.approvals_weight >= quorum, SignerListError::QuorumNotMet);
+        let approvals_weight = current_approval_weight(&proposal.approvers, &entries);
+        require!(approvals_weight >= quorum, SignerListError::QuorumNotMet);
 
         proposal.executed = true;
 
+        let vault = proposal.to_account_info();
+        release_lamports(&vault, &ctx.accounts.target.to_account_info(), proposal.amount)?;
+
         emit!(ProposalExecuted {
             owner: proposal.owner,
             target: proposal.target,
@@ -123,11 +182,69 @@ pub mod signer_list {
         Ok(())
     }
 
+    /// Cancels a proposal that missed its deadline, closing it and returning the escrowed
+    /// lamports and rent to the owner.
+    pub fn cancel_proposal(ctx: Context<CancelProposal>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require!(!proposal.executed, SignerListError::AlreadyExecuted);
+        require!(deadline_passed(proposal, Clock::get()?.unix_timestamp), SignerListError::DeadlineNotReached);
+
+        emit!(ProposalCancelled {
+            owner: proposal.owner,
+            proposal_id: proposal.proposal_id,
+            amount: proposal.amount,
+        });
+
+        Ok(())
+    }
+
     pub fn get_total_weight(ctx: Context<GetTotalWeight>) -> Result<u32> {
         Ok(ctx.accounts.signer_list.total_weight)
     }
 }
 
+fn record_approval(proposal: &mut Proposal, approver: Pubkey, weight: u32) -> Result<()> {
+    require!(!proposal.executed, SignerListError::AlreadyExecuted);
+    require!(!proposal.approvers.contains(&approver), SignerListError::AlreadyApproved);
+    require!(proposal.approvers.len() < MAX_SIGNERS, SignerListError::TooManySigners);
+
+    proposal.approvers.push(approver);
+    proposal.approvals_weight += weight;
+    Ok(())
+}
+
+/// Sum of the current weights of the approvers present in `entries`, counting each signer
+/// once. Approvers without an entry have since been removed and contribute nothing.
+fn current_approval_weight(approvers: &[Pubkey], entries: &[(Pubkey, u32)]) -> u32 {
+    let mut counted: Vec<Pubkey> = Vec::with_capacity(entries.len());
+    let mut weight: u32 = 0;
+    for (signer, entry_weight) in entries {
+        if approvers.contains(signer) && !counted.contains(signer) {
+            counted.push(*signer);
+            weight = weight.saturating_add(*entry_weight);
+        }
+    }
+    weight
+}
+
+/// Address of the owner's `proposal_id`-th proposal, matching the `CreateProposal` seeds
+pub fn proposal_address(owner: &Pubkey, proposal_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"proposal", owner.as_ref(), proposal_id.to_le_bytes().as_ref()], &crate::ID).0
+}
+
+fn deadline_passed(proposal: &Proposal, now: i64) -> bool {
+    now >= proposal.deadline
+}
+
+/// Moves the escrowed lamports out of the program-owned proposal account. Rent stays
+/// behind with the proposal.
+fn release_lamports(vault: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let remaining = vault.lamports().checked_sub(amount).ok_or(SignerListError::InsufficientVaultBalance)?;
+    **vault.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? = to.lamports().checked_add(amount).ok_or(SignerListError::InvalidAmount)?;
+    Ok(())
+}
+
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(
@@ -170,6 +287,7 @@ pub struct CreateSignerList<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(signer: Pubkey)]
 pub struct AddSigner<'info> {
     #[account(
         mut,
@@ -179,7 +297,19 @@ pub struct AddSigner<'info> {
     )]
     pub signer_list: Account<'info, SignerList>,
     
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + SignerEntry::INIT_SPACE,
+        seeds = [b"signer", signer_list.key().as_ref(), signer.as_ref()],
+        bump
+    )]
+    pub signer_entry: Account<'info, SignerEntry>,
+    
+    #[account(mut)]
     pub owner: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
@@ -192,6 +322,16 @@ pub struct RemoveSigner<'info> {
     )]
     pub signer_list: Account<'info, SignerList>,
     
+    #[account(
+        mut,
+        seeds = [b"signer", signer_list.key().as_ref(), signer_entry.signer.as_ref()],
+        bump = signer_entry.bump,
+        has_one = signer_list,
+        close = owner
+    )]
+    pub signer_entry: Account<'info, SignerEntry>,
+    
+    #[account(mut)]
     pub owner: Signer<'info>,
 }
 
@@ -201,11 +341,19 @@ pub struct CreateProposal<'info> {
         init,
         payer = owner,
         space = 8 + Proposal::INIT_SPACE,
-        seeds = [b"proposal", owner.key().as_ref(), &owner.key().to_bytes()[..8]],
+        seeds = [b"proposal", owner.key().as_ref(), signer_list.proposal_count.to_le_bytes().as_ref()],
         bump
     )]
     pub proposal: Account<'info, Proposal>,
     
+    #[account(
+        mut,
+        seeds = [b"signer_list", owner.key().as_ref()],
+        bump = signer_list.bump,
+        has_one = owner
+    )]
+    pub signer_list: Account<'info, SignerList>,
+    
     #[account(mut)]
     pub owner: Signer<'info>,
     
@@ -217,6 +365,19 @@ pub struct ApproveProposal<'info> {
     #[account(mut)]
     pub proposal: Account<'info, Proposal>,
     
+    #[account(
+        seeds = [b"signer_list", proposal.owner.as_ref()],
+        bump = signer_list.bump
+    )]
+    pub signer_list: Account<'info, SignerList>,
+    
+    #[account(
+        seeds = [b"signer", signer_list.key().as_ref(), approver.key().as_ref()],
+        bump = signer_entry.bump,
+        has_one = signer_list
+    )]
+    pub signer_entry: Account<'info, SignerEntry>,
+    
     pub approver: Signer<'info>,
 }
 
@@ -228,6 +389,30 @@ pub struct ExecuteProposal<'info> {
     )]
     pub proposal: Account<'info, Proposal>,
     
+    #[account(
+        seeds = [b"signer_list", owner.key().as_ref()],
+        bump = signer_list.bump,
+        has_one = owner
+    )]
+    pub signer_list: Account<'info, SignerList>,
+    
+    /// CHECK: Recipient of the proposal amount; bound to proposal.target
+    #[account(mut, address = proposal.target)]
+    pub target: AccountInfo<'info>,
+    
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelProposal<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        close = owner
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(mut)]
     pub owner: Signer<'info>,
 }
 
@@ -249,7 +434,18 @@ pub struct Config {
 pub struct SignerList {
     pub owner: Pubkey,
     pub total_weight: u32,
+    pub quorum: u32,
     pub signer_count: u32,
+    pub proposal_count: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct SignerEntry {
+    pub signer_list: Pubkey,
+    pub signer: Pubkey,
+    pub weight: u32,
     pub bump: u8,
 }
 
@@ -257,10 +453,14 @@ pub struct SignerList {
 #[derive(InitSpace)]
 pub struct Proposal {
     pub owner: Pubkey,
+    pub proposal_id: u64,
     pub target: Pubkey,
     pub amount: u64,
     pub approvals_weight: u32,
+    #[max_len(MAX_SIGNERS)]
+    pub approvers: Vec<Pubkey>,
     pub executed: bool,
+    pub deadline: i64,
     pub bump: u8,
 }
 
@@ -279,12 +479,14 @@ pub struct SignerAdded {
 #[event]
 pub struct SignerRemoved {
     pub owner: Pubkey,
+    pub signer: Pubkey,
     pub weight: u32,
 }
 
 #[event]
 pub struct ProposalCreated {
     pub owner: Pubkey,
+    pub proposal_id: u64,
     pub target: Pubkey,
     pub amount: u64,
 }
@@ -294,6 +496,15 @@ pub struct ProposalApproved {
     pub owner: Pubkey,
     pub approver: Pubkey,
     pub weight: u32,
+    pub approvals_weight: u32,
+    pub approver_count: u32,
+}
+
+#[event]
+pub struct ProposalCancelled {
+    pub owner: Pubkey,
+    pub proposal_id: u64,
+    pub amount: u64,
 }
 
 #[event]
@@ -315,4 +526,103 @@ pub enum SignerListError {
     InsufficientWeight,
     #[msg("No signers in list")]
     NoSigners,
+    #[msg("Signer has already approved this proposal")]
+    AlreadyApproved,
+    #[msg("Signer list is full")]
+    TooManySigners,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Quorum must be greater than zero")]
+    InvalidQuorum,
+    #[msg("Insufficient vault balance")]
+    InsufficientVaultBalance,
+    #[msg("Signer entry does not belong to this list")]
+    InvalidSignerEntry,
+    #[msg("Deadline must be in the future")]
+    InvalidDeadline,
+    #[msg("Proposal deadline has not passed")]
+    DeadlineNotReached,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposal(deadline: i64) -> Proposal {
+        Proposal {
+            owner: Pubkey::new_unique(),
+            proposal_id: 0,
+            target: Pubkey::new_unique(),
+            amount: 1_000,
+            approvals_weight: 0,
+            approvers: Vec::new(),
+            executed: false,
+            deadline,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn approval_weight_uses_current_entries() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let approvers = vec![alice, bob];
+
+        assert_eq!(current_approval_weight(&approvers, &[(alice, 2), (bob, 3)]), 5);
+        // Bob was removed after approving: the entry is gone and so is its weight
+        assert_eq!(current_approval_weight(&approvers, &[(alice, 2)]), 2);
+        // Alice was re-weighted after approving
+        assert_eq!(current_approval_weight(&approvers, &[(alice, 1), (bob, 3)]), 4);
+    }
+
+    #[test]
+    fn approval_weight_ignores_non_approvers_and_duplicates() {
+        let alice = Pubkey::new_unique();
+        let carol = Pubkey::new_unique();
+        let approvers = vec![alice];
+
+        assert_eq!(current_approval_weight(&approvers, &[(carol, 10)]), 0);
+        assert_eq!(current_approval_weight(&approvers, &[(alice, 2), (alice, 2)]), 2);
+    }
+
+    #[test]
+    fn proposal_cancellable_only_from_deadline() {
+        let proposal = proposal(100);
+        assert!(!deadline_passed(&proposal, 99));
+        assert!(deadline_passed(&proposal, 100));
+        assert!(deadline_passed(&proposal, 101));
+    }
+
+    #[test]
+    fn a_signer_cannot_approve_twice() {
+        let mut proposal = proposal(100);
+        let alice = Pubkey::new_unique();
+
+        record_approval(&mut proposal, alice, 2).unwrap();
+        assert_eq!(record_approval(&mut proposal, alice, 2).unwrap_err(), SignerListError::AlreadyApproved.into());
+        assert_eq!(proposal.approvals_weight, 2);
+        assert_eq!(proposal.approvers, vec![alice]);
+    }
+
+    #[test]
+    fn distinct_signers_accumulate_weight() {
+        let mut proposal = proposal(100);
+        record_approval(&mut proposal, Pubkey::new_unique(), 2).unwrap();
+        record_approval(&mut proposal, Pubkey::new_unique(), 3).unwrap();
+        assert_eq!(proposal.approvals_weight, 5);
+        assert_eq!(proposal.approvers.len(), 2);
+
+        proposal.executed = true;
+        assert_eq!(record_approval(&mut proposal, Pubkey::new_unique(), 1).unwrap_err(), SignerListError::AlreadyExecuted.into());
+    }
+
+    #[test]
+    fn successive_proposals_from_one_owner_have_distinct_addresses() {
+        let owner = Pubkey::new_unique();
+        let first = proposal_address(&owner, 0);
+        let second = proposal_address(&owner, 1);
+        assert_ne!(first, second);
+        assert_eq!(first, proposal_address(&owner, 0));
+        assert_ne!(first, proposal_address(&Pubkey::new_unique(), 0));
+    }
 }
@@ -2,6 +2,8 @@ use anchor_lang::prelude::*;
 
 declare_id!("TrustL1nesXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX");
 
+pub const MAX_PATH_LENGTH: usize = 6;
+
 #[program]
 pub mod trust_lines {
     use super::*;
@@ -23,23 +25,70 @@ pub mod trust_lines {
         trust_line.owner = ctx.accounts.owner.key();
         trust_line.counterparty = ctx.accounts.counterparty.key();
         trust_line.limit = limit;
+        trust_line.counterparty_limit = 0;
         trust_line.balance = 0;
         trust_line.quality_in = quality_in;
         trust_line.quality_out = quality_out;
         trust_line.authorized = true;
+        trust_line.counterparty_authorized = false;
         trust_line.bump = *ctx.bumps.get("trust_line").unwrap();
         config.total_lines += 1;
         emit!(TrustLineCreated { owner: trust_line.owner, counterparty: trust_line.counterparty, limit });
         Ok(())
     }
 
+    /// Counterparty consent to the line. Payments are refused until this has been called.
+    /// `limit` is how far the counterparty lets the owner's balance go negative.
+    pub fn authorize_trust_line(ctx: Context<AuthorizeTrustLine>, limit: u64) -> Result<()> {
+        let trust_line = &mut ctx.accounts.trust_line;
+        authorize(trust_line, limit);
+        emit!(TrustLineAuthorized { owner: trust_line.owner, counterparty: trust_line.counterparty });
+        Ok(())
+    }
+
+    /// `balance` is what the counterparty owes the owner. A payment from the owner lowers it,
+    /// down to `-counterparty_limit`; a payment from the counterparty raises it, up to `limit`.
     pub fn send_payment(ctx: Context<SendPayment>, amount: u64) -> Result<()> {
+        let sender = ctx.accounts.sender.key();
         let trust_line = &mut ctx.accounts.trust_line;
-        require!(trust_line.authorized, TrustLineError::NotAuthorized);
-        let signed_amount = amount as i64;
-        require!(trust_line.balance + signed_amount <= trust_line.limit as i64, TrustLineError::LimitExceeded);
-        trust_line.balance += signed_amount;
-        emit!(PaymentSent { from: trust_line.owner, to: trust_line.counterparty, amount, new_balance: trust_line.balance });
+        let to = apply_payment(trust_line, sender, amount)?;
+        emit!(PaymentSent { from: sender, to, amount, new_balance: trust_line.balance });
+        Ok(())
+    }
+
+    /// Ripples `amount` from the sender to `destination` across the trust lines passed, in
+    /// order, as `remaining_accounts`. Each line must join the current holder to the next party.
+    /// Every hop is validated before any line is written, and any failure reverts the path.
+    pub fn ripple_payment<'info>(
+        ctx: Context<'_, '_, '_, 'info, RipplePayment<'info>>,
+        amount: u64,
+        destination: Pubkey,
+    ) -> Result<()> {
+        let path = ctx.remaining_accounts;
+        require!(!path.is_empty() && path.len() <= MAX_PATH_LENGTH, TrustLineError::InvalidPath);
+
+        let sender = ctx.accounts.sender.key();
+        let mut lines: Vec<Account<'info, TrustLine>> = Vec::with_capacity(path.len());
+        for (i, info) in path.iter().enumerate() {
+            require!(info.is_writable, TrustLineError::InvalidPath);
+            require!(path[..i].iter().all(|seen| seen.key != info.key), TrustLineError::InvalidPath);
+            let line = Account::<TrustLine>::try_from(info)?;
+            let expected = Pubkey::create_program_address(
+                &[b"trust_line", line.owner.as_ref(), line.counterparty.as_ref(), &[line.bump]],
+                ctx.program_id,
+            )
+            .map_err(|_| TrustLineError::InvalidPath)?;
+            require!(expected == info.key(), TrustLineError::InvalidPath);
+            lines.push(line);
+        }
+        let current = ripple(lines.iter_mut().map(|line| &mut **line), sender, amount)?;
+        require!(current == destination, TrustLineError::InvalidPath);
+
+        for line in lines.iter() {
+            line.exit(ctx.program_id)?;
+        }
+
+        emit!(RipplePaymentSent { from: sender, to: destination, amount, hops: lines.len() as u8 });
         Ok(())
     }
 
@@ -53,6 +102,46 @@ pub mod trust_lines {
     }
 }
 
+fn authorize(line: &mut TrustLine, limit: u64) {
+    line.counterparty_authorized = true;
+    line.counterparty_limit = limit;
+}
+
+/// Moves `amount` across `line` from `from` (either party) to the other party, enforcing the
+/// receiving side's limit. `balance` is what the counterparty owes the owner: a payment from
+/// the owner lowers it, down to `-counterparty_limit`; one from the counterparty raises it, up
+/// to `limit`. Returns the recipient.
+fn apply_payment(line: &mut TrustLine, from: Pubkey, amount: u64) -> Result<Pubkey> {
+    require!(line.authorized, TrustLineError::NotAuthorized);
+    require!(line.counterparty_authorized, TrustLineError::CounterpartyNotAuthorized);
+    let signed_amount = i64::try_from(amount).map_err(|_| TrustLineError::Overflow)?;
+    if from == line.owner {
+        let floor = i64::try_from(line.counterparty_limit).map_err(|_| TrustLineError::Overflow)?;
+        let new_balance = line.balance.checked_sub(signed_amount).ok_or(TrustLineError::Overflow)?;
+        require!(new_balance >= -floor, TrustLineError::LimitExceeded);
+        line.balance = new_balance;
+        Ok(line.counterparty)
+    } else if from == line.counterparty {
+        let ceiling = i64::try_from(line.limit).map_err(|_| TrustLineError::Overflow)?;
+        let new_balance = line.balance.checked_add(signed_amount).ok_or(TrustLineError::Overflow)?;
+        require!(new_balance <= ceiling, TrustLineError::LimitExceeded);
+        line.balance = new_balance;
+        Ok(line.owner)
+    } else {
+        err!(TrustLineError::InvalidPath)
+    }
+}
+
+/// Pushes `amount` along `lines` in order, starting from `from`. Each hop pays the holder
+/// reached by the previous one. Returns the final recipient.
+fn ripple<'a>(lines: impl IntoIterator<Item = &'a mut TrustLine>, from: Pubkey, amount: u64) -> Result<Pubkey> {
+    let mut current = from;
+    for line in lines {
+        current = apply_payment(line, current, amount)?;
+    }
+    Ok(current)
+}
+
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(init, payer = authority, space = 8 + Config::INIT_SPACE, seeds = [b"config"], bump)]
@@ -75,13 +164,30 @@ pub struct CreateTrustLine<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct AuthorizeTrustLine<'info> {
+    #[account(mut, seeds = [b"trust_line", trust_line.owner.as_ref(), counterparty.key().as_ref()], bump = trust_line.bump, has_one = counterparty)]
+    pub trust_line: Account<'info, TrustLine>,
+    pub counterparty: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct SendPayment<'info> {
-    #[account(mut, seeds = [b"trust_line", sender.key().as_ref(), trust_line.counterparty.as_ref()], bump = trust_line.bump, constraint = trust_line.owner == sender.key())]
+    #[account(
+        mut,
+        seeds = [b"trust_line", trust_line.owner.as_ref(), trust_line.counterparty.as_ref()],
+        bump = trust_line.bump,
+        constraint = trust_line.owner == sender.key() || trust_line.counterparty == sender.key() @ TrustLineError::NotAuthorized
+    )]
     pub trust_line: Account<'info, TrustLine>,
     pub sender: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct RipplePayment<'info> {
+    pub sender: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct CloseTrustLine<'info> {
     #[account(mut, seeds = [b"trust_line", owner.key().as_ref(), trust_line.counterparty.as_ref()], bump = trust_line.bump, has_one = owner, close = owner)]
@@ -106,10 +212,12 @@ pub struct TrustLine {
     pub owner: Pubkey,
     pub counterparty: Pubkey,
     pub limit: u64,
+    pub counterparty_limit: u64,
     pub balance: i64,
     pub quality_in: u32,
     pub quality_out: u32,
     pub authorized: bool,
+    pub counterparty_authorized: bool,
     pub bump: u8,
 }
 
@@ -120,6 +228,12 @@ pub struct TrustLineCreated {
     pub limit: u64,
 }
 
+#[event]
+pub struct TrustLineAuthorized {
+    pub owner: Pubkey,
+    pub counterparty: Pubkey,
+}
+
 #[event]
 pub struct PaymentSent {
     pub from: Pubkey,
@@ -128,6 +242,14 @@ pub struct PaymentSent {
     pub new_balance: i64,
 }
 
+#[event]
+pub struct RipplePaymentSent {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub hops: u8,
+}
+
 #[event]
 pub struct TrustLineClosed {
     pub owner: Pubkey,
@@ -144,4 +266,113 @@ pub enum TrustLineError {
     LimitExceeded,
     #[msg("Non-zero balance")]
     NonZeroBalance,
+    #[msg("Counterparty has not authorized the trust line")]
+    CounterpartyNotAuthorized,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Invalid payment path")]
+    InvalidPath,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A line as created by `owner`, not yet authorized by `counterparty`
+    fn trust_line(owner: Pubkey, counterparty: Pubkey, limit: u64) -> TrustLine {
+        TrustLine {
+            owner,
+            counterparty,
+            limit,
+            counterparty_limit: 0,
+            balance: 0,
+            quality_in: 100,
+            quality_out: 100,
+            authorized: true,
+            counterparty_authorized: false,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn payment_waits_for_counterparty_authorization() {
+        let (owner, counterparty) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut line = trust_line(owner, counterparty, 1_000);
+
+        assert_eq!(
+            apply_payment(&mut line, counterparty, 100).unwrap_err(),
+            TrustLineError::CounterpartyNotAuthorized.into()
+        );
+        assert_eq!(line.balance, 0);
+
+        authorize(&mut line, 500);
+        assert_eq!(apply_payment(&mut line, counterparty, 100).unwrap(), owner);
+        assert_eq!(line.balance, 100);
+    }
+
+    #[test]
+    fn payment_rejects_overflow_and_limit_breach() {
+        let (owner, counterparty) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut line = trust_line(owner, counterparty, 1_000);
+        authorize(&mut line, 500);
+
+        assert_eq!(
+            apply_payment(&mut line, counterparty, u64::MAX).unwrap_err(),
+            TrustLineError::Overflow.into()
+        );
+        assert_eq!(
+            apply_payment(&mut line, counterparty, 1_001).unwrap_err(),
+            TrustLineError::LimitExceeded.into()
+        );
+        assert_eq!(
+            apply_payment(&mut line, owner, 501).unwrap_err(),
+            TrustLineError::LimitExceeded.into()
+        );
+        assert_eq!(line.balance, 0);
+    }
+
+    #[test]
+    fn payments_settle_in_both_directions() {
+        let (owner, counterparty) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut line = trust_line(owner, counterparty, 1_000);
+        authorize(&mut line, 500);
+
+        assert_eq!(apply_payment(&mut line, counterparty, 1_000).unwrap(), owner);
+        assert_eq!(line.balance, 1_000);
+        assert_eq!(apply_payment(&mut line, owner, 1_500).unwrap(), counterparty);
+        assert_eq!(line.balance, -500);
+        assert_eq!(apply_payment(&mut line, counterparty, 200).unwrap(), owner);
+        assert_eq!(line.balance, -300);
+        assert_eq!(
+            apply_payment(&mut line, Pubkey::new_unique(), 1).unwrap_err(),
+            TrustLineError::InvalidPath.into()
+        );
+    }
+
+    #[test]
+    fn ripple_moves_balances_along_the_chain() {
+        let (alice, bob, carol) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        // bob trusts alice, carol trusts bob
+        let mut bob_alice = trust_line(bob, alice, 1_000);
+        authorize(&mut bob_alice, 0);
+        let mut carol_bob = trust_line(carol, bob, 1_000);
+        authorize(&mut carol_bob, 0);
+
+        let mut lines = [bob_alice, carol_bob];
+        assert_eq!(ripple(lines.iter_mut(), alice, 300).unwrap(), carol);
+        assert_eq!(lines[0].balance, 300);
+        assert_eq!(lines[1].balance, 300);
+    }
+
+    #[test]
+    fn ripple_rejects_an_over_limit_hop() {
+        let (alice, bob, carol) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut bob_alice = trust_line(bob, alice, 1_000);
+        authorize(&mut bob_alice, 0);
+        let mut carol_bob = trust_line(carol, bob, 100);
+        authorize(&mut carol_bob, 0);
+
+        let mut lines = [bob_alice, carol_bob];
+        assert_eq!(ripple(lines.iter_mut(), alice, 300).unwrap_err(), TrustLineError::LimitExceeded.into());
+    }
 }
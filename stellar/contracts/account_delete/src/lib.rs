@@ -4,16 +4,19 @@ use soroban_sdk::{contract, contracterror, contractimpl, contracttype, token, Ad
 
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct AccountInfo { pub owner: Address, pub created_at: u64, pub deleted: bool, pub deleted_at: Option<u64>, pub beneficiary: Option<Address> }
+pub struct AccountInfo { pub owner: Address, pub created_at: u64, pub deleted: bool, pub deleted_at: Option<u64>, pub beneficiary: Option<Address>, pub pending_beneficiary: Option<Address>, pub deletion_requested_at: Option<u64> }
 
 #[contracttype]
 #[derive(Clone)]
-pub enum DataKey { Account(Address), Admin }
+pub enum DataKey { Account(Address), Admin, MinAccountAge }
 
 #[contracterror]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 #[repr(u32)]
-pub enum Error { NotFound = 1, Unauthorized = 2, AlreadyDeleted = 3, TooYoung = 4 }
+pub enum Error { NotFound = 1, Unauthorized = 2, AlreadyDeleted = 3, TooYoung = 4, NotRequested = 5, GracePeriodActive = 6, AlreadyRequested = 7, NoPendingBeneficiary = 8 }
+
+const DELETION_GRACE_PERIOD: u64 = 86400;
+const DEFAULT_MIN_ACCOUNT_AGE: u64 = 86400;
 
 #[contract]
 pub struct AccountDeleteContract;
@@ -24,51 +27,129 @@ impl AccountDeleteContract {
 
     pub fn register_account(env: Env) -> Result<(), Error> {
         let owner = env.invoker(); owner.require_auth();
-        let account = AccountInfo { owner: owner.clone(), created_at: env.ledger().timestamp(), deleted: false, deleted_at: None, beneficiary: None };
+        let account = AccountInfo { owner: owner.clone(), created_at: env.ledger().timestamp(), deleted: false, deleted_at: None, beneficiary: None, pending_beneficiary: None, deletion_requested_at: None };
         env.storage().persistent().set(&DataKey::Account(owner.clone()), &account);
         env.storage().persistent().extend_ttl(&DataKey::Account(owner.clone()), 518400, 518400);
         env.events().publish((symbol_short!("registered"), owner), ());
         Ok(())
     }
 
+    pub fn set_min_account_age(env: Env, min_age: u64) -> Result<(), Error> {
+        let admin: Address = env.storage().instance().get(&DataKey::Admin).ok_or(Error::Unauthorized)?;
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::MinAccountAge, &min_age);
+        env.events().publish((symbol_short!("min_age"),), min_age);
+        Ok(())
+    }
+
+    pub fn get_min_account_age(env: Env) -> u64 { env.storage().instance().get(&DataKey::MinAccountAge).unwrap_or(DEFAULT_MIN_ACCOUNT_AGE) }
+
+    /// Nominates a beneficiary. The nomination only takes effect once the beneficiary calls
+    /// `accept_beneficiary`; until then deletion sweeps funds back to the owner.
     pub fn set_beneficiary(env: Env, beneficiary: Address) -> Result<(), Error> {
         let owner = env.invoker(); owner.require_auth();
         let mut account: AccountInfo = env.storage().persistent().get(&DataKey::Account(owner.clone())).ok_or(Error::NotFound)?;
         if account.deleted { return Err(Error::AlreadyDeleted); }
-        account.beneficiary = Some(beneficiary.clone());
+        account.beneficiary = None;
+        account.pending_beneficiary = Some(beneficiary.clone());
         env.storage().persistent().set(&DataKey::Account(owner.clone()), &account);
         env.events().publish((symbol_short!("beneficiary"), owner, beneficiary), ());
         Ok(())
     }
 
-    pub fn delete_account(env: Env, tokens: soroban_sdk::Vec<Address>) -> Result<(), Error> {
+    pub fn accept_beneficiary(env: Env, owner: Address) -> Result<(), Error> {
+        let beneficiary = env.invoker(); beneficiary.require_auth();
+        let mut account: AccountInfo = env.storage().persistent().get(&DataKey::Account(owner.clone())).ok_or(Error::NotFound)?;
+        if account.deleted { return Err(Error::AlreadyDeleted); }
+        match account.pending_beneficiary.clone() {
+            Some(pending) if pending == beneficiary => {}
+            Some(_) => return Err(Error::Unauthorized),
+            None => return Err(Error::NoPendingBeneficiary),
+        }
+        account.beneficiary = Some(beneficiary.clone());
+        account.pending_beneficiary = None;
+        env.storage().persistent().set(&DataKey::Account(owner.clone()), &account);
+        env.events().publish((symbol_short!("ben_acc"), owner, beneficiary), ());
+        Ok(())
+    }
+
+    pub fn request_deletion(env: Env) -> Result<(), Error> {
+        let owner = env.invoker(); owner.require_auth();
+        let mut account: AccountInfo = env.storage().persistent().get(&DataKey::Account(owner.clone())).ok_or(Error::NotFound)?;
+        if account.deleted { return Err(Error::AlreadyDeleted); }
+        if account.deletion_requested_at.is_some() { return Err(Error::AlreadyRequested); }
+        let now = env.ledger().timestamp();
+        account.deletion_requested_at = Some(now);
+        env.storage().persistent().set(&DataKey::Account(owner.clone()), &account);
+        env.events().publish((symbol_short!("del_req"), owner), now + DELETION_GRACE_PERIOD);
+        Ok(())
+    }
+
+    pub fn cancel_deletion(env: Env) -> Result<(), Error> {
         let owner = env.invoker(); owner.require_auth();
         let mut account: AccountInfo = env.storage().persistent().get(&DataKey::Account(owner.clone())).ok_or(Error::NotFound)?;
         if account.deleted { return Err(Error::AlreadyDeleted); }
-        let age = env.ledger().timestamp() - account.created_at;
-        if age < 86400 { return Err(Error::TooYoung); }
+        if account.deletion_requested_at.is_none() { return Err(Error::NotRequested); }
+        account.deletion_requested_at = None;
+        env.storage().persistent().set(&DataKey::Account(owner.clone()), &account);
+        env.events().publish((symbol_short!("del_cncl"), owner), ());
+        Ok(())
+    }
+
+    /// Sweeps each token balance to the confirmed beneficiary (or back to the owner if none has
+    /// accepted) and marks the account deleted. A token whose balance lookup or transfer fails
+    /// is skipped and returned instead of aborting.
+    pub fn delete_account(env: Env, tokens: soroban_sdk::Vec<Address>) -> Result<soroban_sdk::Vec<Address>, Error> {
+        let owner = env.invoker(); owner.require_auth();
+        let mut account: AccountInfo = env.storage().persistent().get(&DataKey::Account(owner.clone())).ok_or(Error::NotFound)?;
+        if account.deleted { return Err(Error::AlreadyDeleted); }
+        Self::check_deletable(&env, &account)?;
         
         let beneficiary = account.beneficiary.clone().unwrap_or(owner.clone());
+        let mut failed = soroban_sdk::Vec::new(&env);
         for token_addr in tokens.iter() {
-            let token_client = token::Client::new(&env, &token_addr);
-            let balance = token_client.balance(&owner);
-            if balance > 0 { token_client.transfer(&owner, &beneficiary, &balance); }
+            if !Self::sweep_token(&env, &token_addr, &owner, &beneficiary) { failed.push_back(token_addr); }
         }
         
         account.deleted = true;
         account.deleted_at = Some(env.ledger().timestamp());
         env.storage().persistent().set(&DataKey::Account(owner.clone()), &account);
-        env.events().publish((symbol_short!("deleted"), owner), beneficiary);
+        env.events().publish((symbol_short!("deleted"), owner), (beneficiary, failed.clone()));
+        Ok(failed)
+    }
+
+    fn check_deletable(env: &Env, account: &AccountInfo) -> Result<(), Error> {
+        let now = env.ledger().timestamp();
+        if now - account.created_at < Self::get_min_account_age(env.clone()) { return Err(Error::TooYoung); }
+        let requested_at = account.deletion_requested_at.ok_or(Error::NotRequested)?;
+        if now < requested_at + DELETION_GRACE_PERIOD { return Err(Error::GracePeriodActive); }
         Ok(())
     }
 
+    fn sweep_token(env: &Env, token_addr: &Address, from: &Address, to: &Address) -> bool {
+        let token_client = token::Client::new(env, token_addr);
+        let balance = match token_client.try_balance(from) { Ok(Ok(balance)) => balance, _ => return false };
+        if balance <= 0 { return true; }
+        matches!(token_client.try_transfer(from, to, &balance), Ok(Ok(())))
+    }
+
     pub fn get_account(env: Env, owner: Address) -> Option<AccountInfo> { env.storage().persistent().get(&DataKey::Account(owner)) }
+
+    /// Seconds since the account was registered, or 0 if it is not registered.
+    pub fn get_account_age(env: Env, owner: Address) -> u64 {
+        match Self::get_account(env.clone(), owner) { Some(account) => env.ledger().timestamp() - account.created_at, None => 0 }
+    }
+
+    /// Whether `delete_account` would currently pass its age, request and grace-period checks.
+    pub fn can_delete(env: Env, owner: Address) -> bool {
+        match Self::get_account(env.clone(), owner) { Some(account) if !account.deleted => Self::check_deletable(&env, &account).is_ok(), _ => false }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use soroban_sdk::{testutils::Address as _, Address, Env, vec};
+    use soroban_sdk::{testutils::{Address as _, Ledger}, Address, Env, vec};
 
     #[test]
     fn test_register_and_delete() {
@@ -83,6 +164,8 @@ mod test {
         client.register_account();
 
         env.ledger().with_mut(|li| li.timestamp = 100000);
+        client.request_deletion();
+        env.ledger().with_mut(|li| li.timestamp = 100000 + 86400);
 
         let tokens = vec![&env];
         client.delete_account(&tokens);
@@ -90,4 +173,150 @@ mod test {
         let account = client.get_account(&owner).unwrap();
         assert_eq!(account.deleted, true);
     }
+
+    #[test]
+    fn test_grace_period_and_cancel() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, AccountDeleteContract);
+        let client = AccountDeleteContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let owner = Address::generate(&env);
+        client.register_account();
+        env.ledger().with_mut(|li| li.timestamp = 100000);
+
+        let tokens = vec![&env];
+        assert_eq!(client.try_delete_account(&tokens), Err(Ok(Error::NotRequested)));
+
+        client.request_deletion();
+        env.ledger().with_mut(|li| li.timestamp = 100000 + 3600);
+        assert_eq!(client.try_delete_account(&tokens), Err(Ok(Error::GracePeriodActive)));
+
+        client.cancel_deletion();
+        assert_eq!(client.get_account(&owner).unwrap().deletion_requested_at, None);
+        env.ledger().with_mut(|li| li.timestamp = 100000 + 86400);
+        assert_eq!(client.try_delete_account(&tokens), Err(Ok(Error::NotRequested)));
+    }
+
+    #[test]
+    fn test_delete_reports_failed_tokens() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, AccountDeleteContract);
+        let client = AccountDeleteContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let owner = Address::generate(&env);
+        client.register_account();
+
+        let sweepable = env.register_stellar_asset_contract(admin.clone());
+        token::StellarAssetClient::new(&env, &sweepable).mint(&owner, &1000);
+        let broken = Address::generate(&env);
+
+        env.ledger().with_mut(|li| li.timestamp = 100000);
+        client.request_deletion();
+        env.ledger().with_mut(|li| li.timestamp = 100000 + 86400);
+
+        let failed = client.delete_account(&vec![&env, sweepable.clone(), broken.clone()]);
+        assert_eq!(failed, vec![&env, broken]);
+        assert_eq!(client.get_account(&owner).unwrap().deleted, true);
+    }
+
+    #[test]
+    fn test_custom_min_account_age() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, AccountDeleteContract);
+        let client = AccountDeleteContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+        assert_eq!(client.get_min_account_age(), 86400);
+        client.set_min_account_age(&200000);
+        assert_eq!(client.get_min_account_age(), 200000);
+
+        let owner = Address::generate(&env);
+        env.ledger().with_mut(|li| li.timestamp = 1000);
+        client.register_account();
+        client.request_deletion();
+        assert_eq!(client.can_delete(&owner), false);
+
+        env.ledger().with_mut(|li| li.timestamp = 1000 + 86400);
+        assert_eq!(client.get_account_age(&owner), 86400);
+        assert_eq!(client.can_delete(&owner), false);
+        let tokens = vec![&env];
+        assert_eq!(client.try_delete_account(&tokens), Err(Ok(Error::TooYoung)));
+
+        env.ledger().with_mut(|li| li.timestamp = 1000 + 200000);
+        assert_eq!(client.can_delete(&owner), true);
+        client.delete_account(&tokens);
+        assert_eq!(client.can_delete(&owner), false);
+        assert_eq!(client.get_account_age(&Address::generate(&env)), 0);
+    }
+
+    #[test]
+    fn test_confirmed_beneficiary_receives_sweep() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, AccountDeleteContract);
+        let client = AccountDeleteContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let owner = Address::generate(&env);
+        let beneficiary = Address::generate(&env);
+        client.register_account();
+        client.set_beneficiary(&beneficiary);
+        let account = client.get_account(&owner).unwrap();
+        assert_eq!(account.pending_beneficiary, Some(beneficiary.clone()));
+        assert_eq!(account.beneficiary, None);
+
+        client.accept_beneficiary(&owner);
+        let account = client.get_account(&owner).unwrap();
+        assert_eq!(account.beneficiary, Some(beneficiary.clone()));
+        assert_eq!(account.pending_beneficiary, None);
+        assert_eq!(client.try_accept_beneficiary(&owner), Err(Ok(Error::NoPendingBeneficiary)));
+
+        let asset = env.register_stellar_asset_contract(admin.clone());
+        token::StellarAssetClient::new(&env, &asset).mint(&owner, &1000);
+
+        env.ledger().with_mut(|li| li.timestamp = 100000);
+        client.request_deletion();
+        env.ledger().with_mut(|li| li.timestamp = 100000 + 86400);
+        client.delete_account(&vec![&env, asset.clone()]);
+
+        let token_client = token::Client::new(&env, &asset);
+        assert_eq!(token_client.balance(&beneficiary), 1000);
+        assert_eq!(token_client.balance(&owner), 0);
+    }
+
+    #[test]
+    fn test_unconfirmed_beneficiary_falls_back_to_owner() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, AccountDeleteContract);
+        let client = AccountDeleteContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let owner = Address::generate(&env);
+        let beneficiary = Address::generate(&env);
+        client.register_account();
+        client.set_beneficiary(&beneficiary);
+
+        let asset = env.register_stellar_asset_contract(admin.clone());
+        token::StellarAssetClient::new(&env, &asset).mint(&owner, &1000);
+
+        env.ledger().with_mut(|li| li.timestamp = 100000);
+        client.request_deletion();
+        env.ledger().with_mut(|li| li.timestamp = 100000 + 86400);
+        client.delete_account(&vec![&env, asset.clone()]);
+
+        let token_client = token::Client::new(&env, &asset);
+        assert_eq!(token_client.balance(&beneficiary), 0);
+        assert_eq!(token_client.balance(&owner), 1000);
+        assert_eq!(client.get_account(&owner).unwrap().beneficiary, None);
+    }
 }
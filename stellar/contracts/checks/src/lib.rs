@@ -33,8 +33,10 @@ pub struct Check {
     pub token: Address,
     pub amount: i128,
     pub max_amount: Option<i128>,
+    pub per_cash_limit: Option<i128>,
     pub cashed_amount: i128,
     pub expires_at: Option<u32>,
+    pub valid_from: Option<u32>,
     pub status: CheckStatus,
     pub memo: Option<BytesN<32>>,
     pub created_at: u64,
@@ -61,6 +63,9 @@ pub enum Error {
     InsufficientFunds = 6,
     ExceedsMaxAmount = 7,
     NotPayee = 8,
+    ExceedsPerCashLimit = 9,
+    NotYetValid = 10,
+    InvalidWindow = 11,
 }
 
 #[contract]
@@ -79,9 +84,10 @@ impl ChecksContract {
         token: Address,
         amount: i128,
         duration: Option<u32>,
+        valid_from: Option<u32>,
         memo: Option<BytesN<32>>,
     ) -> Result<u64, Error> {
-        Self::create_check_internal(env, CheckType::Bearer, token, amount, None, duration, memo)
+        Self::create_check_internal(env, CheckType::Bearer, token, amount, None, None, duration, valid_from, memo)
     }
 
     pub fn create_payee_check(
@@ -90,10 +96,12 @@ impl ChecksContract {
         token: Address,
         amount: i128,
         max_amount: Option<i128>,
+        per_cash_limit: Option<i128>,
         duration: Option<u32>,
+        valid_from: Option<u32>,
         memo: Option<BytesN<32>>,
     ) -> Result<u64, Error> {
-        Self::create_check_internal(env, CheckType::PayeeSpecific(payee), token, amount, max_amount, duration, memo)
+        Self::create_check_internal(env, CheckType::PayeeSpecific(payee), token, amount, max_amount, per_cash_limit, duration, valid_from, memo)
     }
 
     fn create_check_internal(
@@ -102,7 +110,9 @@ impl ChecksContract {
         token: Address,
         amount: i128,
         max_amount: Option<i128>,
+        per_cash_limit: Option<i128>,
         duration: Option<u32>,
+        valid_from: Option<u32>,
         memo: Option<BytesN<32>>,
     ) -> Result<u64, Error> {
         let issuer = env.invoker();
@@ -118,6 +128,12 @@ impl ChecksContract {
             }
         }
 
+        if let Some(limit) = per_cash_limit {
+            if limit <= 0 {
+                return Err(Error::InvalidAmount);
+            }
+        }
+
         let token_client = token::Client::new(&env, &token);
         token_client.transfer(&issuer, &env.current_contract_address(), &amount);
 
@@ -126,6 +142,13 @@ impl ChecksContract {
 
         let expires_at = duration.map(|d| env.ledger().sequence() + d);
 
+        // A post-dated check must become cashable before it expires
+        if let (Some(from), Some(exp)) = (valid_from, expires_at) {
+            if from >= exp {
+                return Err(Error::InvalidWindow);
+            }
+        }
+
         let check = Check {
             id: check_id,
             issuer: issuer.clone(),
@@ -133,8 +156,10 @@ impl ChecksContract {
             token: token.clone(),
             amount,
             max_amount,
+            per_cash_limit,
             cashed_amount: 0,
             expires_at,
+            valid_from,
             status: CheckStatus::Pending,
             memo,
             created_at: env.ledger().timestamp(),
@@ -177,6 +202,12 @@ impl ChecksContract {
             }
         }
 
+        if let Some(from) = check.valid_from {
+            if env.ledger().sequence() < from {
+                return Err(Error::NotYetValid);
+            }
+        }
+
         match &check.check_type {
             CheckType::Bearer => {},
             CheckType::PayeeSpecific(payee) => {
@@ -192,6 +223,12 @@ impl ChecksContract {
             return Err(Error::InvalidAmount);
         }
 
+        if let Some(limit) = check.per_cash_limit {
+            if amount_to_cash > limit {
+                return Err(Error::ExceedsPerCashLimit);
+            }
+        }
+
         let new_cashed = check.cashed_amount.checked_add(amount_to_cash)
             .ok_or(Error::InvalidAmount)?;
 
@@ -205,9 +242,7 @@ impl ChecksContract {
             }
         }
 
-        let token_client = token::Client::new(&env, &check.token);
-        token_client.transfer(&env.current_contract_address(), &caller, &amount_to_cash);
-
+        // Record the cash before paying out so a re-entrant token sees the updated check
         check.cashed_amount = new_cashed;
 
         if check.cashed_amount >= check.amount {
@@ -217,6 +252,9 @@ impl ChecksContract {
 
         env.storage().persistent().set(&DataKey::Check(check_id), &check);
 
+        let token_client = token::Client::new(&env, &check.token);
+        token_client.transfer(&env.current_contract_address(), &caller, &amount_to_cash);
+
         env.events().publish(
             (symbol_short!("cashed"), check_id),
             amount_to_cash,
@@ -272,6 +310,70 @@ impl ChecksContract {
         env.storage().persistent().get(&DataKey::Check(check_id))
     }
 
+    /// Dry run of `cash_check`: whether `caller` could cash `amount` (or the full
+    /// remainder when `None`) right now. Never mutates state.
+    pub fn can_cash(
+        env: Env,
+        check_id: u64,
+        caller: Address,
+        amount: Option<i128>,
+    ) -> Result<bool, Error> {
+        let check: Check = env.storage()
+            .persistent()
+            .get(&DataKey::Check(check_id))
+            .ok_or(Error::NotFound)?;
+
+        if !matches!(check.status, CheckStatus::Pending) {
+            return Ok(false);
+        }
+
+        if let Some(exp) = check.expires_at {
+            if env.ledger().sequence() >= exp {
+                return Ok(false);
+            }
+        }
+
+        if let Some(from) = check.valid_from {
+            if env.ledger().sequence() < from {
+                return Ok(false);
+            }
+        }
+
+        if let CheckType::PayeeSpecific(payee) = &check.check_type {
+            if caller != *payee {
+                return Ok(false);
+            }
+        }
+
+        let amount_to_cash = amount.unwrap_or(check.amount - check.cashed_amount);
+        if amount_to_cash <= 0 {
+            return Ok(false);
+        }
+
+        if let Some(limit) = check.per_cash_limit {
+            if amount_to_cash > limit {
+                return Ok(false);
+            }
+        }
+
+        let new_cashed = match check.cashed_amount.checked_add(amount_to_cash) {
+            Some(v) => v,
+            None => return Ok(false),
+        };
+
+        if new_cashed > check.amount {
+            return Ok(false);
+        }
+
+        if let Some(max) = check.max_amount {
+            if new_cashed > max {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
     pub fn get_remaining_amount(env: Env, check_id: u64) -> Result<i128, Error> {
         let check: Check = env.storage()
             .persistent()
@@ -284,7 +386,40 @@ impl ChecksContract {
 #[cfg(test)]
 mod test {
     use super::*;
-    use soroban_sdk::{testutils::Address as _, Address, Env};
+    use soroban_sdk::{symbol_short, testutils::{Address as _, Ledger}, Address, Env};
+
+    /// Token stub that, once armed, reads the check as stored from inside `transfer`
+    #[contract]
+    pub struct ObservingToken;
+
+    #[contractimpl]
+    impl ObservingToken {
+        pub fn arm(env: Env, checks: Address, check_id: u64) {
+            env.storage().instance().set(&symbol_short!("target"), &(checks, check_id));
+        }
+
+        pub fn transfer(env: Env, _from: Address, _to: Address, amount: i128) {
+            let paid: i128 = env.storage().instance().get(&symbol_short!("paid")).unwrap_or(0);
+            env.storage().instance().set(&symbol_short!("paid"), &(paid + amount));
+
+            let target: Option<(Address, u64)> = env.storage().instance().get(&symbol_short!("target"));
+            if let Some((checks, check_id)) = target {
+                // The host rejects a call back into the checks contract, so read its storage directly
+                let check = env.as_contract(&checks, || ChecksContract::get_check(env.clone(), check_id))
+                    .map(|check| (check.status, check.cashed_amount));
+                env.storage().instance().set(&symbol_short!("seen"), &check);
+            }
+        }
+
+        pub fn paid(env: Env) -> i128 {
+            env.storage().instance().get(&symbol_short!("paid")).unwrap_or(0)
+        }
+
+        /// Check status and cashed amount as stored when the armed transfer ran
+        pub fn observed(env: Env) -> Option<(CheckStatus, i128)> {
+            env.storage().instance().get(&symbol_short!("seen")).unwrap_or(None)
+        }
+    }
 
     #[test]
     fn test_bearer_check() {
@@ -295,7 +430,7 @@ mod test {
         let client = ChecksContractClient::new(&env, &contract_id);
 
         let token = Address::generate(&env);
-        let check_id = client.create_bearer_check(&token, &1000, &None, &None);
+        let check_id = client.create_bearer_check(&token, &1000, &None, &None, &None);
 
         // Anyone can cash a bearer check
         client.cash_check(&check_id, &Some(500));
@@ -321,7 +456,7 @@ mod test {
         let payee = Address::generate(&env);
         let token = Address::generate(&env);
 
-        let check_id = client.create_payee_check(&payee, &token, &1000, &None, &None, &None);
+        let check_id = client.create_payee_check(&payee, &token, &1000, &None, &None, &None, &None, &None);
 
         // Payee cashes the check
         client.cash_check(&check_id, &None);
@@ -343,7 +478,7 @@ mod test {
         let payee = Address::generate(&env);
         let token = Address::generate(&env);
 
-        let check_id = client.create_payee_check(&payee, &token, &1000, &None, &None, &None);
+        let check_id = client.create_payee_check(&payee, &token, &1000, &None, &None, &None, &None, &None);
 
         // Wrong person tries to cash - should fail
         let wrong_person = Address::generate(&env);
@@ -360,7 +495,7 @@ mod test {
         let client = ChecksContractClient::new(&env, &contract_id);
 
         let token = Address::generate(&env);
-        let check_id = client.create_bearer_check(&token, &1000, &None, &None);
+        let check_id = client.create_bearer_check(&token, &1000, &None, &None, &None);
 
         // Issuer cancels the check
         client.cancel_check(&check_id);
@@ -381,7 +516,7 @@ mod test {
         let token = Address::generate(&env);
 
         // Create check with max amount
-        let check_id = client.create_payee_check(&payee, &token, &1000, &Some(800), &None, &None);
+        let check_id = client.create_payee_check(&payee, &token, &1000, &Some(800), &None, &None, &None, &None);
 
         // Cash 500
         client.cash_check(&check_id, &Some(500));
@@ -408,7 +543,7 @@ mod test {
         let token = Address::generate(&env);
 
         // Create check that expires in 10 ledgers
-        let check_id = client.create_bearer_check(&token, &1000, &Some(10), &None);
+        let check_id = client.create_bearer_check(&token, &1000, &Some(10), &None, &None);
 
         // Advance ledger past expiration
         env.ledger().with_mut(|li| li.sequence_number += 20);
@@ -420,4 +555,112 @@ mod test {
         let check = client.get_check(&check_id).unwrap();
         assert_eq!(check.status, CheckStatus::Expired);
     }
+
+    #[test]
+    fn test_cash_check_commits_state_before_transfer() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, ChecksContract);
+        let client = ChecksContractClient::new(&env, &contract_id);
+        let token_id = env.register_contract(None, ObservingToken);
+        let token = ObservingTokenClient::new(&env, &token_id);
+
+        let check_id = client.create_bearer_check(&token_id, &1000, &None, &None, &None);
+        token.arm(&contract_id, &check_id);
+
+        client.cash_check(&check_id, &None);
+
+        // The payout transfer already saw the check as cashed
+        assert_eq!(token.observed(), Some((CheckStatus::Cashed, 1000)));
+        assert_eq!(token.paid(), 2000);
+        let check = client.get_check(&check_id).unwrap();
+        assert_eq!(check.cashed_amount, 1000);
+        assert_eq!(check.status, CheckStatus::Cashed);
+    }
+
+    #[test]
+    fn test_can_cash() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, ChecksContract);
+        let client = ChecksContractClient::new(&env, &contract_id);
+
+        let payee = Address::generate(&env);
+        let stranger = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        // Anyone can cash a bearer check
+        let bearer_id = client.create_bearer_check(&token, &1000, &None, &None, &None);
+        assert!(client.can_cash(&bearer_id, &stranger, &None));
+        assert!(client.can_cash(&bearer_id, &payee, &Some(500)));
+        assert!(!client.can_cash(&bearer_id, &payee, &Some(1500)));
+
+        // Only the named payee can cash a payee check
+        let payee_id = client.create_payee_check(&payee, &token, &1000, &None, &None, &None, &None, &None);
+        assert!(client.can_cash(&payee_id, &payee, &None));
+        assert!(!client.can_cash(&payee_id, &stranger, &None));
+
+        // Nobody can cash once expired
+        let expiring_id = client.create_bearer_check(&token, &1000, &Some(10), &None, &None);
+        env.ledger().with_mut(|li| li.sequence_number += 20);
+        assert!(!client.can_cash(&expiring_id, &stranger, &None));
+        assert_eq!(client.get_check(&expiring_id).unwrap().status, CheckStatus::Pending);
+    }
+
+    #[test]
+    fn test_per_cash_limit() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, ChecksContract);
+        let client = ChecksContractClient::new(&env, &contract_id);
+
+        let payee = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        let check_id = client.create_payee_check(&payee, &token, &1000, &None, &Some(400), &None, &None, &None);
+
+        client.cash_check(&check_id, &Some(400));
+
+        let result = client.try_cash_check(&check_id, &Some(500));
+        assert_eq!(result, Err(Ok(Error::ExceedsPerCashLimit)));
+        // Cashing the whole remainder at once is also over the limit
+        assert_eq!(client.try_cash_check(&check_id, &None), Err(Ok(Error::ExceedsPerCashLimit)));
+
+        client.cash_check(&check_id, &Some(400));
+        client.cash_check(&check_id, &Some(200));
+
+        let check = client.get_check(&check_id).unwrap();
+        assert_eq!(check.cashed_amount, 1000);
+        assert_eq!(check.status, CheckStatus::Cashed);
+    }
+
+    #[test]
+    fn test_post_dated_check() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, ChecksContract);
+        let client = ChecksContractClient::new(&env, &contract_id);
+
+        let payee = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        let valid_from = env.ledger().sequence() + 50;
+        let check_id = client.create_payee_check(&payee, &token, &1000, &None, &None, &Some(100), &Some(valid_from), &None);
+        assert_eq!(client.get_check(&check_id).unwrap().valid_from, Some(valid_from));
+
+        assert_eq!(client.try_cash_check(&check_id, &None), Err(Ok(Error::NotYetValid)));
+        assert_eq!(client.can_cash(&check_id, &payee, &None), false);
+
+        env.ledger().with_mut(|li| li.sequence_number += 50);
+        assert_eq!(client.can_cash(&check_id, &payee, &None), true);
+        client.cash_check(&check_id, &None);
+        assert_eq!(client.get_check(&check_id).unwrap().status, CheckStatus::Cashed);
+
+        let result = client.try_create_bearer_check(&token, &1000, &Some(10), &Some(env.ledger().sequence() + 10), &None);
+        assert_eq!(result, Err(Ok(Error::InvalidWindow)));
+    }
 }
@@ -8,6 +8,10 @@ use soroban_sdk::{
     Address, Env, Vec, vec,
 };
 
+/// Longest authorization (seconds) an ordinary authorizer may grant; anything
+/// longer, or without expiry, must come from the compliance officer
+const ROUTINE_MAX_DURATION: u32 = 30 * 86_400;
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum AuthorizationStatus {
@@ -33,6 +37,8 @@ pub enum DataKey {
     Authorization(Address, Address, Address),
     AuthorizedAccounts(Address, Address),
     Admin,
+    ComplianceOfficer,
+    Blocked(Address),
 }
 
 #[contracterror]
@@ -44,6 +50,7 @@ pub enum Error {
     AlreadyAuthorized = 3,
     NotAuthorized = 4,
     Expired = 5,
+    OfficerRequired = 6,
 }
 
 #[contract]
@@ -51,22 +58,106 @@ pub struct DepositAuthorizationContract;
 
 #[contractimpl]
 impl DepositAuthorizationContract {
-    pub fn initialize(env: Env, admin: Address) {
+    pub fn initialize(env: Env, admin: Address, compliance_officer: Address) {
         admin.require_auth();
         env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage().instance().set(&DataKey::ComplianceOfficer, &compliance_officer);
+    }
+
+    /// Replace the compliance officer. Admin only.
+    pub fn set_compliance_officer(env: Env, compliance_officer: Address) -> Result<(), Error> {
+        let admin: Address = env.storage().instance().get(&DataKey::Admin).ok_or(Error::NotFound)?;
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::ComplianceOfficer, &compliance_officer);
+        env.events().publish((symbol_short!("officer"),), compliance_officer);
+        Ok(())
+    }
+
+    pub fn get_compliance_officer(env: Env) -> Option<Address> {
+        env.storage().instance().get(&DataKey::ComplianceOfficer)
+    }
+
+    /// Block an account globally, overriding every authorization it holds.
+    /// Admin or compliance officer only.
+    pub fn block_account(env: Env, account: Address) -> Result<(), Error> {
+        Self::require_admin_or_officer(&env)?;
+        env.storage().persistent().set(&DataKey::Blocked(account.clone()), &true);
+        env.events().publish((symbol_short!("block"), account), ());
+        Ok(())
+    }
+
+    /// Lift a global block. Admin or compliance officer only.
+    pub fn unblock_account(env: Env, account: Address) -> Result<(), Error> {
+        Self::require_admin_or_officer(&env)?;
+        env.storage().persistent().remove(&DataKey::Blocked(account.clone()));
+        env.events().publish((symbol_short!("unblock"), account), ());
+        Ok(())
+    }
+
+    pub fn is_blocked(env: Env, account: Address) -> bool {
+        env.storage().persistent().has(&DataKey::Blocked(account))
+    }
+
+    fn require_admin_or_officer(env: &Env) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+        let admin: Option<Address> = env.storage().instance().get(&DataKey::Admin);
+        if admin.as_ref() != Some(&caller) && Self::get_compliance_officer(env.clone()).as_ref() != Some(&caller) {
+            return Err(Error::Unauthorized);
+        }
+        Ok(())
+    }
+
+    /// Long-lived and non-expiring authorizations are reserved for the compliance officer
+    fn check_duration_role(env: &Env, authorizer: &Address, duration: Option<u32>) -> Result<(), Error> {
+        let routine = duration.map_or(false, |d| d <= ROUTINE_MAX_DURATION);
+        if !routine && Self::get_compliance_officer(env.clone()).as_ref() != Some(authorizer) {
+            return Err(Error::OfficerRequired);
+        }
+        Ok(())
     }
 
     pub fn authorize_account(env: Env, account: Address, token: Address, duration: Option<u32>) -> Result<(), Error> {
         let authorizer = env.invoker();
         authorizer.require_auth();
 
+        Self::check_duration_role(&env, &authorizer, duration)?;
+
         let key = DataKey::Authorization(authorizer.clone(), account.clone(), token.clone());
         if env.storage().persistent().has(&key) {
             return Err(Error::AlreadyAuthorized);
         }
 
         let expires_at = duration.map(|d| env.ledger().timestamp() + (d as u64));
+        Self::store_authorization(&env, &authorizer, &account, &token, expires_at);
+        Ok(())
+    }
+
+    /// Authorize `account` for several tokens at once. All-or-nothing: fails with
+    /// `AlreadyAuthorized` if any token (or a repeat within `tokens`) already has an
+    /// authorization from the caller.
+    pub fn authorize_batch(env: Env, account: Address, tokens: Vec<Address>, duration: Option<u32>) -> Result<(), Error> {
+        let authorizer = env.invoker();
+        authorizer.require_auth();
+
+        Self::check_duration_role(&env, &authorizer, duration)?;
+
+        for (i, token) in tokens.iter().enumerate() {
+            let key = DataKey::Authorization(authorizer.clone(), account.clone(), token.clone());
+            if env.storage().persistent().has(&key) || tokens.first_index_of(&token) != Some(i as u32) {
+                return Err(Error::AlreadyAuthorized);
+            }
+        }
+
+        let expires_at = duration.map(|d| env.ledger().timestamp() + (d as u64));
+        for token in tokens.iter() {
+            Self::store_authorization(&env, &authorizer, &account, &token, expires_at);
+        }
+        Ok(())
+    }
 
+    fn store_authorization(env: &Env, authorizer: &Address, account: &Address, token: &Address, expires_at: Option<u64>) {
+        let key = DataKey::Authorization(authorizer.clone(), account.clone(), token.clone());
         let auth = Authorization {
             authorizer: authorizer.clone(),
             authorized_account: account.clone(),
@@ -80,12 +171,11 @@ impl DepositAuthorizationContract {
         env.storage().persistent().extend_ttl(&key, 518400, 518400);
 
         let accounts_key = DataKey::AuthorizedAccounts(authorizer.clone(), token.clone());
-        let mut accounts: Vec<Address> = env.storage().persistent().get(&accounts_key).unwrap_or(vec![&env]);
+        let mut accounts: Vec<Address> = env.storage().persistent().get(&accounts_key).unwrap_or(vec![env]);
         accounts.push_back(account.clone());
         env.storage().persistent().set(&accounts_key, &accounts);
 
-        env.events().publish((symbol_short!("authorize"), authorizer, account), token);
-        Ok(())
+        env.events().publish((symbol_short!("authorize"), authorizer.clone(), account.clone()), token.clone());
     }
 
     pub fn revoke_authorization(env: Env, account: Address, token: Address) -> Result<(), Error> {
@@ -98,14 +188,26 @@ impl DepositAuthorizationContract {
         auth.status = AuthorizationStatus::Revoked;
         env.storage().persistent().set(&key, &auth);
 
+        let accounts_key = DataKey::AuthorizedAccounts(authorizer.clone(), token.clone());
+        let mut accounts: Vec<Address> = env.storage().persistent().get(&accounts_key).unwrap_or(vec![&env]);
+        if let Some(index) = accounts.first_index_of(&account) {
+            accounts.remove(index);
+            env.storage().persistent().set(&accounts_key, &accounts);
+        }
+
         env.events().publish((symbol_short!("revoke"), authorizer, account), token);
         Ok(())
     }
 
+    /// Whether `account` holds an active, unexpired authorization and is not blocked
     pub fn is_authorized(env: Env, authorizer: Address, account: Address, token: Address) -> Result<bool, Error> {
-        let key = DataKey::Authorization(authorizer, account, token);
+        let key = DataKey::Authorization(authorizer, account.clone(), token);
         let auth: Authorization = env.storage().persistent().get(&key).ok_or(Error::NotFound)?;
 
+        if Self::is_blocked(env.clone(), account) {
+            return Ok(false);
+        }
+
         if !matches!(auth.status, AuthorizationStatus::Authorized) {
             return Ok(false);
         }
@@ -128,6 +230,19 @@ impl DepositAuthorizationContract {
         let key = DataKey::AuthorizedAccounts(authorizer, token);
         env.storage().persistent().get(&key).unwrap_or(vec![&env])
     }
+
+    /// Like `get_authorized_accounts`, but drops entries that have expired as of the
+    /// current ledger timestamp.
+    pub fn get_active_authorized_accounts(env: Env, authorizer: Address, token: Address) -> Vec<Address> {
+        let accounts = Self::get_authorized_accounts(env.clone(), authorizer.clone(), token.clone());
+        let mut active = vec![&env];
+        for account in accounts.iter() {
+            if Self::is_authorized(env.clone(), authorizer.clone(), account.clone(), token.clone()).unwrap_or(false) {
+                active.push_back(account);
+            }
+        }
+        active
+    }
 }
 
 #[cfg(test)]
@@ -142,9 +257,9 @@ mod test {
         let contract_id = env.register_contract(None, DepositAuthorizationContract);
         let client = DepositAuthorizationContractClient::new(&env, &contract_id);
         let admin = Address::generate(&env);
-        client.initialize(&admin);
-
         let authorizer = Address::generate(&env);
+        client.initialize(&admin, &authorizer);
+
         let account = Address::generate(&env);
         let token = Address::generate(&env);
 
@@ -161,9 +276,9 @@ mod test {
         let contract_id = env.register_contract(None, DepositAuthorizationContract);
         let client = DepositAuthorizationContractClient::new(&env, &contract_id);
         let admin = Address::generate(&env);
-        client.initialize(&admin);
-
         let authorizer = Address::generate(&env);
+        client.initialize(&admin, &authorizer);
+
         let account = Address::generate(&env);
         let token = Address::generate(&env);
 
@@ -173,4 +288,116 @@ mod test {
         let is_auth = client.is_authorized(&authorizer, &account, &token);
         assert_eq!(is_auth, false);
     }
+
+    #[test]
+    fn test_active_accounts_exclude_revoked() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DepositAuthorizationContract);
+        let client = DepositAuthorizationContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        let authorizer = Address::generate(&env);
+        client.initialize(&admin, &authorizer);
+
+        let account_a = Address::generate(&env);
+        let account_b = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        client.authorize_account(&account_a, &token, &None);
+        client.authorize_account(&account_b, &token, &None);
+        client.revoke_authorization(&account_a, &token);
+
+        let active = client.get_active_authorized_accounts(&authorizer, &token);
+        assert_eq!(active.len(), 1);
+        assert_eq!(active.get(0).unwrap(), account_b);
+        assert_eq!(client.get_authorized_accounts(&authorizer, &token).len(), 1);
+    }
+
+    #[test]
+    fn test_authorize_batch() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DepositAuthorizationContract);
+        let client = DepositAuthorizationContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        let authorizer = Address::generate(&env);
+        client.initialize(&admin, &authorizer);
+
+        let account = Address::generate(&env);
+        let tokens = vec![&env, Address::generate(&env), Address::generate(&env), Address::generate(&env)];
+
+        client.authorize_batch(&account, &tokens, &None);
+
+        for token in tokens.iter() {
+            assert!(client.is_authorized(&authorizer, &account, &token));
+            assert_eq!(client.get_authorized_accounts(&authorizer, &token).len(), 1);
+        }
+
+        // Re-authorizing any of them fails the whole batch
+        let extra = Address::generate(&env);
+        let overlapping = vec![&env, extra.clone(), tokens.get(1).unwrap()];
+        let result = client.try_authorize_batch(&account, &overlapping, &None);
+        assert_eq!(result, Err(Ok(Error::AlreadyAuthorized)));
+        assert_eq!(client.get_authorization(&authorizer, &account, &extra), None);
+    }
+
+    #[test]
+    fn test_officer_role_split() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DepositAuthorizationContract);
+        let client = DepositAuthorizationContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        let officer = Address::generate(&env);
+        client.initialize(&admin, &officer);
+        assert_eq!(client.get_compliance_officer(), Some(officer));
+
+        let authorizer = Address::generate(&env);
+        let account = Address::generate(&env);
+        let token = Address::generate(&env);
+        let other_token = Address::generate(&env);
+
+        // Routine authorizations are open to any authorizer
+        client.authorize_account(&account, &token, &Some(ROUTINE_MAX_DURATION));
+        assert!(client.is_authorized(&authorizer, &account, &token));
+
+        // Long-lived and permanent ones need the officer
+        let long = Some(ROUTINE_MAX_DURATION + 1);
+        assert_eq!(client.try_authorize_account(&account, &other_token, &long), Err(Ok(Error::OfficerRequired)));
+        assert_eq!(client.try_authorize_account(&account, &other_token, &None), Err(Ok(Error::OfficerRequired)));
+        let tokens = vec![&env, other_token.clone()];
+        assert_eq!(client.try_authorize_batch(&account, &tokens, &None), Err(Ok(Error::OfficerRequired)));
+
+        client.set_compliance_officer(&authorizer);
+        client.authorize_account(&account, &other_token, &None);
+        assert!(client.is_authorized(&authorizer, &account, &other_token));
+    }
+
+    #[test]
+    fn test_block_overrides_authorization() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DepositAuthorizationContract);
+        let client = DepositAuthorizationContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        let authorizer = Address::generate(&env);
+        client.initialize(&admin, &authorizer);
+
+        let account = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        client.authorize_account(&account, &token, &None);
+        assert!(client.is_authorized(&authorizer, &account, &token));
+
+        client.block_account(&account);
+        assert!(client.is_blocked(&account));
+        assert!(!client.is_authorized(&authorizer, &account, &token));
+        assert_eq!(client.get_active_authorized_accounts(&authorizer, &token).len(), 0);
+        // The authorization itself is untouched
+        assert_eq!(client.get_authorization(&authorizer, &account, &token).unwrap().status, AuthorizationStatus::Authorized);
+
+        client.unblock_account(&account);
+        assert!(!client.is_blocked(&account));
+        assert!(client.is_authorized(&authorizer, &account, &token));
+    }
 }
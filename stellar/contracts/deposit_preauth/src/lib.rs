@@ -18,12 +18,34 @@ pub struct Preauth {
     pub used: bool,
     pub created_at: u64,
     pub used_at: Option<u64>,
+    /// Ledger from which the preauth can no longer be used
+    pub expires_at: Option<u32>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReusablePreauth {
+    pub creator: Address,
+    pub authorized_depositor: Address,
+    pub token: Address,
+    pub max_per_use: i128,
+    pub max_total: i128,
+    pub max_uses: u32,
+    pub total_used: i128,
+    pub use_count: u32,
+    pub revoked: bool,
+    pub created_at: u64,
+    pub last_used_at: Option<u64>,
+    /// Ledger from which the preauth can no longer be used
+    pub expires_at: Option<u32>,
 }
 
 #[contracttype]
 #[derive(Clone)]
 pub enum DataKey {
     Preauth(BytesN<32>),
+    ReusablePreauth(BytesN<32>),
+    NextPreauthId,
     Admin,
 }
 
@@ -35,6 +57,11 @@ pub enum Error {
     Unauthorized = 2,
     AlreadyUsed = 3,
     ExceedsMaxAmount = 4,
+    UsesExhausted = 5,
+    ExceedsMaxTotal = 6,
+    InvalidLimits = 7,
+    InvalidAmount = 8,
+    Expired = 9,
 }
 
 #[contract]
@@ -45,13 +72,14 @@ impl DepositPreauthContract {
     pub fn initialize(env: Env, admin: Address) {
         admin.require_auth();
         env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage().instance().set(&DataKey::NextPreauthId, &1u64);
     }
 
-    pub fn create_preauth(env: Env, depositor: Address, token: Address, max_amount: Option<i128>) -> BytesN<32> {
+    pub fn create_preauth(env: Env, depositor: Address, token: Address, max_amount: Option<i128>, expires_at: Option<u32>) -> BytesN<32> {
         let creator = env.invoker();
         creator.require_auth();
 
-        let preauth_id = env.crypto().sha256(&(creator.clone(), depositor.clone(), token.clone(), env.ledger().timestamp()).try_into_val(&env).unwrap());
+        let preauth_id = Self::next_preauth_id(&env, &creator, &depositor, &token);
 
         let preauth = Preauth {
             creator: creator.clone(),
@@ -61,6 +89,7 @@ impl DepositPreauthContract {
             used: false,
             created_at: env.ledger().timestamp(),
             used_at: None,
+            expires_at,
         };
 
         env.storage().persistent().set(&DataKey::Preauth(preauth_id.clone()), &preauth);
@@ -70,10 +99,65 @@ impl DepositPreauthContract {
         preauth_id
     }
 
+    pub fn create_reusable_preauth(
+        env: Env,
+        depositor: Address,
+        token: Address,
+        max_per_use: i128,
+        max_total: i128,
+        max_uses: u32,
+        expires_at: Option<u32>,
+    ) -> Result<BytesN<32>, Error> {
+        let creator = env.invoker();
+        creator.require_auth();
+
+        if max_per_use <= 0 || max_total < max_per_use || max_uses == 0 {
+            return Err(Error::InvalidLimits);
+        }
+
+        let preauth_id = Self::next_preauth_id(&env, &creator, &depositor, &token);
+
+        let preauth = ReusablePreauth {
+            creator: creator.clone(),
+            authorized_depositor: depositor.clone(),
+            token: token.clone(),
+            max_per_use,
+            max_total,
+            max_uses,
+            total_used: 0,
+            use_count: 0,
+            revoked: false,
+            created_at: env.ledger().timestamp(),
+            last_used_at: None,
+            expires_at,
+        };
+
+        env.storage().persistent().set(&DataKey::ReusablePreauth(preauth_id.clone()), &preauth);
+        env.storage().persistent().extend_ttl(&DataKey::ReusablePreauth(preauth_id.clone()), 518400, 518400);
+
+        env.events().publish((symbol_short!("created"), creator, depositor), preauth_id.clone());
+        Ok(preauth_id)
+    }
+
+    fn next_preauth_id(env: &Env, creator: &Address, depositor: &Address, token: &Address) -> BytesN<32> {
+        let nonce: u64 = env.storage().instance().get(&DataKey::NextPreauthId).unwrap_or(1);
+        env.storage().instance().set(&DataKey::NextPreauthId, &(nonce + 1));
+
+        env.crypto().sha256(&(creator.clone(), depositor.clone(), token.clone(), env.ledger().timestamp(), nonce).try_into_val(env).unwrap())
+    }
+
     pub fn use_preauth(env: Env, preauth_id: BytesN<32>, amount: i128) -> Result<(), Error> {
         let caller = env.invoker();
         caller.require_auth();
 
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        if env.storage().persistent().has(&DataKey::ReusablePreauth(preauth_id.clone())) {
+            return Self::use_reusable_preauth(env, caller, preauth_id, amount);
+        }
+
         let mut preauth: Preauth = env.storage().persistent().get(&DataKey::Preauth(preauth_id.clone())).ok_or(Error::NotFound)?;
 
         if caller != preauth.authorized_depositor {
@@ -84,6 +168,10 @@ impl DepositPreauthContract {
             return Err(Error::AlreadyUsed);
         }
 
+        if Self::is_expired(&env, preauth.expires_at) {
+            return Err(Error::Expired);
+        }
+
         if let Some(max) = preauth.max_amount {
             if amount > max {
                 return Err(Error::ExceedsMaxAmount);
@@ -98,10 +186,62 @@ impl DepositPreauthContract {
         Ok(())
     }
 
+    fn use_reusable_preauth(env: Env, caller: Address, preauth_id: BytesN<32>, amount: i128) -> Result<(), Error> {
+        let key = DataKey::ReusablePreauth(preauth_id.clone());
+        let mut preauth: ReusablePreauth = env.storage().persistent().get(&key).ok_or(Error::NotFound)?;
+
+        if caller != preauth.authorized_depositor {
+            return Err(Error::Unauthorized);
+        }
+
+        if preauth.revoked {
+            return Err(Error::AlreadyUsed);
+        }
+
+        if Self::is_expired(&env, preauth.expires_at) {
+            return Err(Error::Expired);
+        }
+
+        if preauth.use_count >= preauth.max_uses {
+            return Err(Error::UsesExhausted);
+        }
+
+        if amount > preauth.max_per_use {
+            return Err(Error::ExceedsMaxAmount);
+        }
+
+        if preauth.total_used + amount > preauth.max_total {
+            return Err(Error::ExceedsMaxTotal);
+        }
+
+        preauth.total_used += amount;
+        preauth.use_count += 1;
+        preauth.last_used_at = Some(env.ledger().timestamp());
+        env.storage().persistent().set(&key, &preauth);
+
+        env.events().publish((symbol_short!("used"), preauth_id), amount);
+        Ok(())
+    }
+
     pub fn revoke_preauth(env: Env, preauth_id: BytesN<32>) -> Result<(), Error> {
         let caller = env.invoker();
         caller.require_auth();
 
+        let reusable_key = DataKey::ReusablePreauth(preauth_id.clone());
+        if let Some(mut preauth) = env.storage().persistent().get::<_, ReusablePreauth>(&reusable_key) {
+            if caller != preauth.creator {
+                return Err(Error::Unauthorized);
+            }
+            if preauth.revoked {
+                return Err(Error::AlreadyUsed);
+            }
+            preauth.revoked = true;
+            env.storage().persistent().set(&reusable_key, &preauth);
+
+            env.events().publish((symbol_short!("revoked"), preauth_id), ());
+            return Ok(());
+        }
+
         let mut preauth: Preauth = env.storage().persistent().get(&DataKey::Preauth(preauth_id.clone())).ok_or(Error::NotFound)?;
 
         if caller != preauth.creator {
@@ -119,15 +259,37 @@ impl DepositPreauthContract {
         Ok(())
     }
 
+    /// Whether the preauth (single-use or reusable) could still be used
+    pub fn is_valid(env: Env, preauth_id: BytesN<32>) -> bool {
+        if let Some(preauth) = Self::get_reusable_preauth(env.clone(), preauth_id.clone()) {
+            return !preauth.revoked
+                && !Self::is_expired(&env, preauth.expires_at)
+                && preauth.use_count < preauth.max_uses
+                && preauth.total_used < preauth.max_total;
+        }
+        match Self::get_preauth(env.clone(), preauth_id) {
+            Some(preauth) => !preauth.used && !Self::is_expired(&env, preauth.expires_at),
+            None => false,
+        }
+    }
+
+    fn is_expired(env: &Env, expires_at: Option<u32>) -> bool {
+        expires_at.map_or(false, |ledger| env.ledger().sequence() >= ledger)
+    }
+
     pub fn get_preauth(env: Env, preauth_id: BytesN<32>) -> Option<Preauth> {
         env.storage().persistent().get(&DataKey::Preauth(preauth_id))
     }
+
+    pub fn get_reusable_preauth(env: Env, preauth_id: BytesN<32>) -> Option<ReusablePreauth> {
+        env.storage().persistent().get(&DataKey::ReusablePreauth(preauth_id))
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use soroban_sdk::{testutils::Address as _, Address, Env};
+    use soroban_sdk::{testutils::{Address as _, Ledger}, Address, Env};
 
     #[test]
     fn test_create_and_use_preauth() {
@@ -141,7 +303,8 @@ mod test {
         let depositor = Address::generate(&env);
         let token = Address::generate(&env);
 
-        let preauth_id = client.create_preauth(&depositor, &token, &Some(1000));
+        let preauth_id = client.create_preauth(&depositor, &token, &Some(1000), &None);
+        assert_eq!(client.try_use_preauth(&preauth_id, &-1), Err(Ok(Error::InvalidAmount)));
         client.use_preauth(&preauth_id, &500);
 
         let preauth = client.get_preauth(&preauth_id).unwrap();
@@ -161,8 +324,108 @@ mod test {
         let depositor = Address::generate(&env);
         let token = Address::generate(&env);
 
-        let preauth_id = client.create_preauth(&depositor, &token, &Some(1000));
+        let preauth_id = client.create_preauth(&depositor, &token, &Some(1000), &None);
         client.use_preauth(&preauth_id, &500);
         client.use_preauth(&preauth_id, &300);
     }
+
+    #[test]
+    fn test_same_ledger_preauths_do_not_collide() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DepositPreauthContract);
+        let client = DepositPreauthContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let depositor = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        let first = client.create_preauth(&depositor, &token, &Some(1000), &None);
+        let second = client.create_preauth(&depositor, &token, &Some(1000), &None);
+
+        assert_ne!(first, second);
+        assert!(client.get_preauth(&first).is_some());
+        assert!(client.get_preauth(&second).is_some());
+    }
+
+    #[test]
+    fn test_reusable_preauth_limits() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DepositPreauthContract);
+        let client = DepositPreauthContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let depositor = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        let preauth_id = client.create_reusable_preauth(&depositor, &token, &400, &1000, &3, &None);
+
+        assert_eq!(client.try_use_preauth(&preauth_id, &500), Err(Ok(Error::ExceedsMaxAmount)));
+
+        // A negative amount must not claw back the total budget
+        assert_eq!(client.try_use_preauth(&preauth_id, &-400), Err(Ok(Error::InvalidAmount)));
+        assert_eq!(client.try_use_preauth(&preauth_id, &0), Err(Ok(Error::InvalidAmount)));
+
+        client.use_preauth(&preauth_id, &400);
+        client.use_preauth(&preauth_id, &400);
+        assert_eq!(client.try_use_preauth(&preauth_id, &300), Err(Ok(Error::ExceedsMaxTotal)));
+
+        client.use_preauth(&preauth_id, &200);
+        assert_eq!(client.try_use_preauth(&preauth_id, &1), Err(Ok(Error::UsesExhausted)));
+
+        let preauth = client.get_reusable_preauth(&preauth_id).unwrap();
+        assert_eq!(preauth.total_used, 1000);
+        assert_eq!(preauth.use_count, 3);
+    }
+
+    #[test]
+    fn test_preauth_expiry() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DepositPreauthContract);
+        let client = DepositPreauthContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let depositor = Address::generate(&env);
+        let token = Address::generate(&env);
+        let expires_at = env.ledger().sequence() + 10;
+
+        let early = client.create_preauth(&depositor, &token, &Some(1000), &Some(expires_at));
+        let late = client.create_preauth(&depositor, &token, &Some(1000), &Some(expires_at));
+        assert!(client.is_valid(&early));
+
+        client.use_preauth(&early, &500);
+        assert!(!client.is_valid(&early));
+
+        env.ledger().with_mut(|li| li.sequence_number = expires_at);
+        assert!(!client.is_valid(&late));
+        assert_eq!(client.try_use_preauth(&late, &500), Err(Ok(Error::Expired)));
+        assert!(!client.get_preauth(&late).unwrap().used);
+    }
+
+    #[test]
+    fn test_reusable_preauth_expiry() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DepositPreauthContract);
+        let client = DepositPreauthContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let depositor = Address::generate(&env);
+        let token = Address::generate(&env);
+        let expires_at = env.ledger().sequence() + 10;
+
+        let preauth_id = client.create_reusable_preauth(&depositor, &token, &400, &1000, &3, &Some(expires_at));
+        client.use_preauth(&preauth_id, &400);
+        assert!(client.is_valid(&preauth_id));
+
+        env.ledger().with_mut(|li| li.sequence_number = expires_at);
+        assert!(!client.is_valid(&preauth_id));
+        assert_eq!(client.try_use_preauth(&preauth_id, &400), Err(Ok(Error::Expired)));
+    }
 }
@@ -4,10 +4,19 @@
 //! Production-grade Soroban implementation
 
 use soroban_sdk::{
-    contract, contracterror, contractimpl, contracttype,
+    contract, contractclient, contracterror, contractimpl, contracttype,
     token, Address, Env, Vec, vec,
 };
 
+/// Price scale used for pairs that have not been configured: prices carry 6 decimals
+const DEFAULT_PRICE_SCALE: i128 = 1_000_000;
+
+/// The part of the Escrow contract that linked orders depend on
+#[contractclient(name = "EscrowClient")]
+pub trait EscrowInterface {
+    fn is_completed(env: Env, escrow_id: u64) -> bool;
+}
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum OrderSide {
@@ -15,6 +24,13 @@ pub enum OrderSide {
     Sell,
 }
 
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TimeInForce {
+    ImmediateOrCancel,
+    FillOrKill,
+}
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum OrderStatus {
@@ -37,6 +53,22 @@ pub struct Order {
     pub filled: i128,
     pub status: OrderStatus,
     pub created_at: u64,
+    /// Escrow that must be `Completed` before this order can trade
+    pub linked_escrow: Option<u64>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Trade {
+    pub id: u64,
+    pub maker_order_id: u64,
+    pub taker_order_id: u64,
+    pub base_token: Address,
+    pub quote_token: Address,
+    pub amount: i128,
+    pub price: i128,
+    pub timestamp: u64,
+    pub ledger: u32,
 }
 
 #[contracttype]
@@ -44,10 +76,17 @@ pub struct Order {
 pub enum DataKey {
     Order(u64),
     NextOrderId,
+    Trade(u64),
+    NextTradeId,
+    PairTrades(Address, Address),
+    OrderTrades(u64),
+    PriceScale(Address, Address),
     BuyOrders(Address, Address),
     SellOrders(Address, Address),
     Admin,
     FeeRate,
+    Paused,
+    EscrowContract,
 }
 
 #[contracterror]
@@ -60,6 +99,10 @@ pub enum Error {
     InvalidPrice = 4,
     OrderNotOpen = 5,
     InsufficientFunds = 6,
+    CannotFill = 7,
+    ScaleAlreadySet = 8,
+    Paused = 9,
+    EscrowNotSet = 10,
 }
 
 #[contract]
@@ -71,47 +114,87 @@ impl DEXOrdersContract {
         admin.require_auth();
         env.storage().instance().set(&DataKey::Admin, &admin);
         env.storage().instance().set(&DataKey::NextOrderId, &1u64);
+        env.storage().instance().set(&DataKey::NextTradeId, &1u64);
         env.storage().instance().set(&DataKey::FeeRate, &fee_rate);
     }
 
+    /// Halt or resume order creation and matching. Cancellations keep working while paused.
+    pub fn set_paused(env: Env, paused: bool) -> Result<(), Error> {
+        let admin: Address = env.storage().instance().get(&DataKey::Admin).ok_or(Error::NotFound)?;
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::Paused, &paused);
+        env.events().publish((symbol_short!("paused"),), paused);
+        Ok(())
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+    }
+
+    /// Set the Escrow contract that linked orders are checked against. Admin only.
+    pub fn set_escrow_contract(env: Env, escrow: Address) -> Result<(), Error> {
+        let admin: Address = env.storage().instance().get(&DataKey::Admin).ok_or(Error::NotFound)?;
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::EscrowContract, &escrow);
+        Ok(())
+    }
+
     pub fn create_buy_order(
         env: Env,
+        trader: Address,
         base_token: Address,
         quote_token: Address,
         price: i128,
         amount: i128,
     ) -> Result<u64, Error> {
-        Self::create_order_internal(env, OrderSide::Buy, base_token, quote_token, price, amount)
+        Self::create_order_internal(env, trader, OrderSide::Buy, base_token, quote_token, price, amount, None)
     }
 
     pub fn create_sell_order(
         env: Env,
+        trader: Address,
+        base_token: Address,
+        quote_token: Address,
+        price: i128,
+        amount: i128,
+    ) -> Result<u64, Error> {
+        Self::create_order_internal(env, trader, OrderSide::Sell, base_token, quote_token, price, amount, None)
+    }
+
+    /// Limit order that rests on the book but only trades once `escrow_id` in the
+    /// configured Escrow contract is `Completed`, e.g. one leg of an HTLC swap.
+    pub fn create_linked_order(
+        env: Env,
+        trader: Address,
+        side: OrderSide,
         base_token: Address,
         quote_token: Address,
         price: i128,
         amount: i128,
+        escrow_id: u64,
     ) -> Result<u64, Error> {
-        Self::create_order_internal(env, OrderSide::Sell, base_token, quote_token, price, amount)
+        if !env.storage().instance().has(&DataKey::EscrowContract) { return Err(Error::EscrowNotSet); }
+        Self::create_order_internal(env, trader, side, base_token, quote_token, price, amount, Some(escrow_id))
     }
 
     fn create_order_internal(
         env: Env,
+        trader: Address,
         side: OrderSide,
         base_token: Address,
         quote_token: Address,
         price: i128,
         amount: i128,
+        linked_escrow: Option<u64>,
     ) -> Result<u64, Error> {
-        let trader = env.invoker();
         trader.require_auth();
 
+        if Self::is_paused(env.clone()) { return Err(Error::Paused); }
         if amount <= 0 { return Err(Error::InvalidAmount); }
         if price <= 0 { return Err(Error::InvalidPrice); }
 
-        let required_funds = match side {
-            OrderSide::Buy => price.checked_mul(amount).ok_or(Error::InvalidAmount)?.checked_div(1_000_000).ok_or(Error::InvalidAmount)?,
-            OrderSide::Sell => amount,
-        };
+        let scale = Self::fix_price_scale(&env, &base_token, &quote_token);
+        let required_funds = Self::escrow_amount(&side, price, amount, scale)?;
 
         let deposit_token = match side { OrderSide::Buy => &quote_token, OrderSide::Sell => &base_token };
         let token_client = token::Client::new(&env, deposit_token);
@@ -124,7 +207,7 @@ impl DEXOrdersContract {
             id: order_id, trader: trader.clone(), side: side.clone(),
             base_token: base_token.clone(), quote_token: quote_token.clone(),
             price, amount, filled: 0, status: OrderStatus::Open,
-            created_at: env.ledger().timestamp(),
+            created_at: env.ledger().timestamp(), linked_escrow,
         };
 
         env.storage().persistent().set(&DataKey::Order(order_id), &order);
@@ -144,9 +227,140 @@ impl DEXOrdersContract {
         Ok(order_id)
     }
 
-    fn try_match_order(env: Env, order_id: u64) -> Result<(), Error> {
+    /// Takes liquidity from the book without resting. Matches up to `max_slippage_price`
+    /// (the highest price paid for a buy, the lowest accepted for a sell). `FillOrKill`
+    /// fails with `CannotFill` unless the whole amount is available; `ImmediateOrCancel`
+    /// fills what it can and refunds the rest.
+    pub fn create_market_order(
+        env: Env,
+        trader: Address,
+        side: OrderSide,
+        base_token: Address,
+        quote_token: Address,
+        amount: i128,
+        max_slippage_price: i128,
+        tif: TimeInForce,
+    ) -> Result<u64, Error> {
+        trader.require_auth();
+
+        if Self::is_paused(env.clone()) { return Err(Error::Paused); }
+        if amount <= 0 { return Err(Error::InvalidAmount); }
+        if max_slippage_price <= 0 { return Err(Error::InvalidPrice); }
+
+        if tif == TimeInForce::FillOrKill {
+            let available = Self::fillable_amount(&env, &trader, &side, &base_token, &quote_token, max_slippage_price, amount);
+            if available < amount { return Err(Error::CannotFill); }
+        }
+
+        let scale = Self::fix_price_scale(&env, &base_token, &quote_token);
+        let required_funds = Self::escrow_amount(&side, max_slippage_price, amount, scale)?;
+
+        let deposit_token = match side { OrderSide::Buy => &quote_token, OrderSide::Sell => &base_token };
+        let token_client = token::Client::new(&env, deposit_token);
+        token_client.transfer(&trader, &env.current_contract_address(), &required_funds);
+
+        let order_id: u64 = env.storage().instance().get(&DataKey::NextOrderId).unwrap_or(1);
+        env.storage().instance().set(&DataKey::NextOrderId, &(order_id + 1));
+
+        // Stored for matching and history, but never added to the resting book
+        let order = Order {
+            id: order_id, trader: trader.clone(), side: side.clone(),
+            base_token: base_token.clone(), quote_token: quote_token.clone(),
+            price: max_slippage_price, amount, filled: 0, status: OrderStatus::Open,
+            created_at: env.ledger().timestamp(), linked_escrow: None,
+        };
+        env.storage().persistent().set(&DataKey::Order(order_id), &order);
+        env.storage().persistent().extend_ttl(&DataKey::Order(order_id), 518400, 518400);
+
+        Self::try_match_order(env.clone(), order_id)?;
+
+        // Price improvement on the filled part was refunded per fill; return the escrow
+        // still held for the unfilled part
+        let mut order: Order = env.storage().persistent().get(&DataKey::Order(order_id)).ok_or(Error::NotFound)?;
+        let refund = match side {
+            OrderSide::Buy => required_funds
+                .checked_sub(Self::escrow_amount(&side, max_slippage_price, order.filled, scale)?)
+                .ok_or(Error::InvalidAmount)?,
+            OrderSide::Sell => amount.checked_sub(order.filled).ok_or(Error::InvalidAmount)?,
+        };
+        if refund > 0 {
+            token_client.transfer(&env.current_contract_address(), &trader, &refund);
+        }
+
+        if order.filled < order.amount {
+            order.status = OrderStatus::Cancelled;
+            env.storage().persistent().set(&DataKey::Order(order_id), &order);
+        }
+
+        env.events().publish((symbol_short!("market"), trader, side), (order_id, order.filled, refund));
+        Ok(order_id)
+    }
+
+    /// Amount of the opposite book that an order at `limit_price` could take, stopping
+    /// once `wanted` is reached.
+    fn fillable_amount(
+        env: &Env,
+        trader: &Address,
+        side: &OrderSide,
+        base_token: &Address,
+        quote_token: &Address,
+        limit_price: i128,
+        wanted: i128,
+    ) -> i128 {
+        let opposite_key = match side {
+            OrderSide::Buy => DataKey::SellOrders(base_token.clone(), quote_token.clone()),
+            OrderSide::Sell => DataKey::BuyOrders(base_token.clone(), quote_token.clone()),
+        };
+        let opposite_orders: Vec<u64> = env.storage().persistent().get(&opposite_key).unwrap_or(vec![env]);
+
+        let mut available: i128 = 0;
+        for opp_id in opposite_orders.iter() {
+            let opp_order: Order = match env.storage().persistent().get(&DataKey::Order(opp_id)) {
+                Some(o) => o,
+                None => continue,
+            };
+            if !matches!(opp_order.status, OrderStatus::Open | OrderStatus::PartiallyFilled) { continue; }
+            if opp_order.trader == *trader { continue; }
+            if !Self::linked_escrow_completed(env, &opp_order) { continue; }
+            let crosses = match side {
+                OrderSide::Buy => limit_price >= opp_order.price,
+                OrderSide::Sell => limit_price <= opp_order.price,
+            };
+            if !crosses { continue; }
+
+            available = available.saturating_add(opp_order.amount - opp_order.filled);
+            if available >= wanted { break; }
+        }
+        available
+    }
+
+    /// Re-run matching for a resting order, e.g. once its linked escrow has completed.
+    /// Anyone may call this. Returns the quote amount traded.
+    pub fn match_order(env: Env, order_id: u64) -> Result<i128, Error> {
+        Self::try_match_order(env, order_id)
+    }
+
+    /// Whether an order's linked escrow (if any) has completed
+    fn linked_escrow_completed(env: &Env, order: &Order) -> bool {
+        let escrow_id = match order.linked_escrow {
+            Some(id) => id,
+            None => return true,
+        };
+        match env.storage().instance().get::<_, Address>(&DataKey::EscrowContract) {
+            Some(escrow) => EscrowClient::new(env, &escrow).is_completed(&escrow_id),
+            None => false,
+        }
+    }
+
+    /// Matches an order against the opposite book, returning the quote amount traded.
+    /// Orders whose linked escrow has not completed neither take nor provide liquidity.
+    /// Fills execute at the maker's price, so a buy taker escrowed at a higher limit
+    /// is refunded the difference as each fill settles.
+    fn try_match_order(env: Env, order_id: u64) -> Result<i128, Error> {
+        if Self::is_paused(env.clone()) { return Err(Error::Paused); }
         let order: Order = env.storage().persistent().get(&DataKey::Order(order_id)).ok_or(Error::NotFound)?;
-        if !matches!(order.status, OrderStatus::Open | OrderStatus::PartiallyFilled) { return Ok(()); }
+        if !matches!(order.status, OrderStatus::Open | OrderStatus::PartiallyFilled) { return Ok(0); }
+        if !Self::linked_escrow_completed(&env, &order) { return Ok(0); }
 
         let opposite_key = match order.side {
             OrderSide::Buy => DataKey::SellOrders(order.base_token.clone(), order.quote_token.clone()),
@@ -155,6 +369,8 @@ impl DEXOrdersContract {
 
         let opposite_orders: Vec<u64> = env.storage().persistent().get(&opposite_key).unwrap_or(vec![&env]);
 
+        let mut current_order = order.clone();
+        let mut quote_traded: i128 = 0;
         for opp_id in opposite_orders.iter() {
             let mut opp_order: Order = match env.storage().persistent().get(&DataKey::Order(opp_id)) {
                 Some(o) => o,
@@ -162,6 +378,9 @@ impl DEXOrdersContract {
             };
 
             if !matches!(opp_order.status, OrderStatus::Open | OrderStatus::PartiallyFilled) { continue; }
+            // Never match a trader against their own resting order
+            if opp_order.trader == order.trader { continue; }
+            if !Self::linked_escrow_completed(&env, &opp_order) { continue; }
 
             let can_match = match order.side {
                 OrderSide::Buy => order.price >= opp_order.price,
@@ -170,24 +389,35 @@ impl DEXOrdersContract {
 
             if !can_match { continue; }
 
-            let mut current_order = order.clone();
             let remaining_amount = current_order.amount - current_order.filled;
             let opp_remaining = opp_order.amount - opp_order.filled;
             let fill_amount = remaining_amount.min(opp_remaining);
 
             if fill_amount <= 0 { break; }
 
-            Self::execute_trade(env.clone(), &mut current_order, &mut opp_order, fill_amount, opp_order.price)?;
+            let quote_amount = Self::execute_trade(env.clone(), &mut current_order, &mut opp_order, fill_amount, opp_order.price)?;
+            quote_traded = quote_traded.checked_add(quote_amount).ok_or(Error::InvalidAmount)?;
+
+            if current_order.side == OrderSide::Buy {
+                let scale = Self::get_price_scale(env.clone(), current_order.base_token.clone(), current_order.quote_token.clone());
+                let escrowed = Self::escrow_amount(&OrderSide::Buy, current_order.price, fill_amount, scale)?;
+                let improvement = escrowed.checked_sub(quote_amount).ok_or(Error::InvalidAmount)?;
+                if improvement > 0 {
+                    token::Client::new(&env, &current_order.quote_token)
+                        .transfer(&env.current_contract_address(), &current_order.trader, &improvement);
+                }
+            }
             env.storage().persistent().set(&DataKey::Order(current_order.id), &current_order);
             env.storage().persistent().set(&DataKey::Order(opp_order.id), &opp_order);
 
             if current_order.filled >= current_order.amount { break; }
         }
-        Ok(())
+        Ok(quote_traded)
     }
 
-    fn execute_trade(env: Env, order1: &mut Order, order2: &mut Order, amount: i128, exec_price: i128) -> Result<(), Error> {
-        let quote_amount = exec_price.checked_mul(amount).ok_or(Error::InvalidAmount)?.checked_div(1_000_000).ok_or(Error::InvalidAmount)?;
+    fn execute_trade(env: Env, order1: &mut Order, order2: &mut Order, amount: i128, exec_price: i128) -> Result<i128, Error> {
+        let scale = Self::get_price_scale(env.clone(), order1.base_token.clone(), order1.quote_token.clone());
+        let quote_amount = exec_price.checked_mul(amount).ok_or(Error::InvalidAmount)?.checked_div(scale).ok_or(Error::InvalidAmount)?;
         let (buyer, seller) = match order1.side { OrderSide::Buy => (order1, order2), OrderSide::Sell => (order2, order1) };
 
         let base_token_client = token::Client::new(&env, &buyer.base_token);
@@ -202,25 +432,51 @@ impl DEXOrdersContract {
         order1.status = if order1.filled >= order1.amount { OrderStatus::Filled } else { OrderStatus::PartiallyFilled };
         order2.status = if order2.filled >= order2.amount { OrderStatus::Filled } else { OrderStatus::PartiallyFilled };
 
+        Self::record_trade(&env, order1, order2, amount, exec_price);
         env.events().publish((symbol_short!("trade"), order1.id, order2.id), (amount, exec_price));
-        Ok(())
+        Ok(quote_amount)
     }
 
-    pub fn cancel_order(env: Env, order_id: u64) -> Result<(), Error> {
-        let caller = env.invoker();
-        caller.require_auth();
+    fn record_trade(env: &Env, taker: &Order, maker: &Order, amount: i128, price: i128) {
+        let trade_id: u64 = env.storage().instance().get(&DataKey::NextTradeId).unwrap_or(1);
+        env.storage().instance().set(&DataKey::NextTradeId, &(trade_id + 1));
+
+        let trade = Trade {
+            id: trade_id,
+            maker_order_id: maker.id,
+            taker_order_id: taker.id,
+            base_token: taker.base_token.clone(),
+            quote_token: taker.quote_token.clone(),
+            amount,
+            price,
+            timestamp: env.ledger().timestamp(),
+            ledger: env.ledger().sequence(),
+        };
+        env.storage().persistent().set(&DataKey::Trade(trade_id), &trade);
+        env.storage().persistent().extend_ttl(&DataKey::Trade(trade_id), 518400, 518400);
+
+        let keys = [
+            DataKey::PairTrades(trade.base_token.clone(), trade.quote_token.clone()),
+            DataKey::OrderTrades(maker.id),
+            DataKey::OrderTrades(taker.id),
+        ];
+        for key in keys.iter() {
+            let mut ids: Vec<u64> = env.storage().persistent().get(key).unwrap_or(vec![env]);
+            ids.push_back(trade_id);
+            env.storage().persistent().set(key, &ids);
+        }
+    }
 
+    pub fn cancel_order(env: Env, order_id: u64) -> Result<(), Error> {
         let mut order: Order = env.storage().persistent().get(&DataKey::Order(order_id)).ok_or(Error::NotFound)?;
-        if caller != order.trader { return Err(Error::Unauthorized); }
+        order.trader.require_auth();
         if !matches!(order.status, OrderStatus::Open | OrderStatus::PartiallyFilled) { return Err(Error::OrderNotOpen); }
 
         let remaining = order.amount.checked_sub(order.filled).ok_or(Error::InvalidAmount)?;
 
         if remaining > 0 {
-            let refund_amount = match order.side {
-                OrderSide::Buy => order.price.checked_mul(remaining).ok_or(Error::InvalidAmount)?.checked_div(1_000_000).ok_or(Error::InvalidAmount)?,
-                OrderSide::Sell => remaining,
-            };
+            let scale = Self::get_price_scale(env.clone(), order.base_token.clone(), order.quote_token.clone());
+            let refund_amount = Self::escrow_amount(&order.side, order.price, remaining, scale)?;
 
             let refund_token = match order.side { OrderSide::Buy => &order.quote_token, OrderSide::Sell => &order.base_token };
             let token_client = token::Client::new(&env, refund_token);
@@ -233,10 +489,145 @@ impl DEXOrdersContract {
         Ok(())
     }
 
+    /// Reprices or resizes a resting order in place, pulling or refunding the escrow
+    /// difference and re-running matching at the new price.
+    pub fn amend_order(env: Env, order_id: u64, new_price: i128, new_amount: i128) -> Result<(), Error> {
+        let mut order: Order = env.storage().persistent().get(&DataKey::Order(order_id)).ok_or(Error::NotFound)?;
+        order.trader.require_auth();
+        if !matches!(order.status, OrderStatus::Open | OrderStatus::PartiallyFilled) { return Err(Error::OrderNotOpen); }
+        if new_price <= 0 { return Err(Error::InvalidPrice); }
+        if new_amount < order.filled { return Err(Error::InvalidAmount); }
+
+        let scale = Self::get_price_scale(env.clone(), order.base_token.clone(), order.quote_token.clone());
+        let old_escrow = Self::escrow_amount(&order.side, order.price, order.amount - order.filled, scale)?;
+        let new_escrow = Self::escrow_amount(&order.side, new_price, new_amount - order.filled, scale)?;
+
+        let escrow_token = match order.side { OrderSide::Buy => &order.quote_token, OrderSide::Sell => &order.base_token };
+        let token_client = token::Client::new(&env, escrow_token);
+        if new_escrow > old_escrow {
+            token_client.transfer(&order.trader, &env.current_contract_address(), &(new_escrow - old_escrow));
+        } else if old_escrow > new_escrow {
+            token_client.transfer(&env.current_contract_address(), &order.trader, &(old_escrow - new_escrow));
+        }
+
+        order.price = new_price;
+        order.amount = new_amount;
+        if order.filled >= order.amount {
+            order.status = OrderStatus::Filled;
+        }
+        env.storage().persistent().set(&DataKey::Order(order_id), &order);
+
+        env.events().publish((symbol_short!("amend"), order_id), (new_price, new_amount));
+        Self::try_match_order(env, order_id)?;
+        Ok(())
+    }
+
+    /// Funds held for `amount` of an order: quote at `price` for buys, base for sells.
+    fn escrow_amount(side: &OrderSide, price: i128, amount: i128, scale: i128) -> Result<i128, Error> {
+        match side {
+            OrderSide::Buy => price.checked_mul(amount).ok_or(Error::InvalidAmount)?.checked_div(scale).ok_or(Error::InvalidAmount),
+            OrderSide::Sell => Ok(amount),
+        }
+    }
+
+    /// Set the price scale for a pair, e.g. `100_000_000` for 8-decimal prices. Admin only,
+    /// and only before the pair's first order fixes it.
+    pub fn set_price_scale(env: Env, base_token: Address, quote_token: Address, scale: i128) -> Result<(), Error> {
+        let admin: Address = env.storage().instance().get(&DataKey::Admin).ok_or(Error::NotFound)?;
+        admin.require_auth();
+
+        if scale <= 0 { return Err(Error::InvalidPrice); }
+        let key = DataKey::PriceScale(base_token.clone(), quote_token.clone());
+        if env.storage().persistent().has(&key) { return Err(Error::ScaleAlreadySet); }
+
+        env.storage().persistent().set(&key, &scale);
+        env.events().publish((symbol_short!("scale"), base_token, quote_token), scale);
+        Ok(())
+    }
+
+    pub fn get_price_scale(env: Env, base_token: Address, quote_token: Address) -> i128 {
+        env.storage().persistent().get(&DataKey::PriceScale(base_token, quote_token)).unwrap_or(DEFAULT_PRICE_SCALE)
+    }
+
+    /// Quote token amount for `amount` of base at `price` on this pair
+    pub fn get_quote_amount(env: Env, base_token: Address, quote_token: Address, price: i128, amount: i128) -> Result<i128, Error> {
+        let scale = Self::get_price_scale(env, base_token, quote_token);
+        Self::escrow_amount(&OrderSide::Buy, price, amount, scale)
+    }
+
+    /// Returns the pair's scale, persisting the default on first use so it cannot
+    /// change under existing orders.
+    fn fix_price_scale(env: &Env, base_token: &Address, quote_token: &Address) -> i128 {
+        let key = DataKey::PriceScale(base_token.clone(), quote_token.clone());
+        match env.storage().persistent().get(&key) {
+            Some(scale) => scale,
+            None => {
+                env.storage().persistent().set(&key, &DEFAULT_PRICE_SCALE);
+                DEFAULT_PRICE_SCALE
+            }
+        }
+    }
+
     pub fn get_order(env: Env, order_id: u64) -> Option<Order> {
         env.storage().persistent().get(&DataKey::Order(order_id))
     }
 
+    pub fn get_trade(env: Env, trade_id: u64) -> Option<Trade> {
+        env.storage().persistent().get(&DataKey::Trade(trade_id))
+    }
+
+    /// Trades for a pair in execution order, skipping the first `start` and returning at most `limit`.
+    pub fn get_trades_for_pair(env: Env, base_token: Address, quote_token: Address, start: u32, limit: u32) -> Vec<Trade> {
+        let ids: Vec<u64> = env.storage().persistent().get(&DataKey::PairTrades(base_token, quote_token)).unwrap_or(vec![&env]);
+        let end = start.saturating_add(limit).min(ids.len());
+        let mut trades = vec![&env];
+        for i in start..end {
+            if let Some(trade) = env.storage().persistent().get(&DataKey::Trade(ids.get(i).unwrap())) {
+                trades.push_back(trade);
+            }
+        }
+        trades
+    }
+
+    pub fn get_trades_for_order(env: Env, order_id: u64) -> Vec<Trade> {
+        let ids: Vec<u64> = env.storage().persistent().get(&DataKey::OrderTrades(order_id)).unwrap_or(vec![&env]);
+        let mut trades = vec![&env];
+        for id in ids.iter() {
+            if let Some(trade) = env.storage().persistent().get(&DataKey::Trade(id)) {
+                trades.push_back(trade);
+            }
+        }
+        trades
+    }
+
+    /// Execution price of the most recent trade on the pair.
+    pub fn get_last_price(env: Env, base_token: Address, quote_token: Address) -> Option<i128> {
+        let ids: Vec<u64> = env.storage().persistent().get(&DataKey::PairTrades(base_token, quote_token))?;
+        let trade: Trade = env.storage().persistent().get(&DataKey::Trade(ids.last()?))?;
+        Some(trade.price)
+    }
+
+    /// Volume-weighted average price of the pair's trades in the last `window_ledgers` ledgers.
+    pub fn get_vwap(env: Env, base_token: Address, quote_token: Address, window_ledgers: u32) -> Option<i128> {
+        let ids: Vec<u64> = env.storage().persistent().get(&DataKey::PairTrades(base_token, quote_token))?;
+        let since = env.ledger().sequence().saturating_sub(window_ledgers);
+
+        let mut notional: i128 = 0;
+        let mut volume: i128 = 0;
+        for id in ids.iter().rev() {
+            let trade: Trade = match env.storage().persistent().get(&DataKey::Trade(id)) {
+                Some(t) => t,
+                None => continue,
+            };
+            if trade.ledger < since { break; }
+            notional = notional.checked_add(trade.price.checked_mul(trade.amount)?)?;
+            volume = volume.checked_add(trade.amount)?;
+        }
+
+        if volume == 0 { return None; }
+        Some(notional / volume)
+    }
+
     pub fn get_buy_orders(env: Env, base_token: Address, quote_token: Address) -> Vec<u64> {
         env.storage().persistent().get(&DataKey::BuyOrders(base_token, quote_token)).unwrap_or(vec![&env])
     }
@@ -249,7 +640,39 @@ impl DEXOrdersContract {
 #[cfg(test)]
 mod test {
     use super::*;
-    use soroban_sdk::{testutils::Address as _, Address, Env};
+    use soroban_sdk::{symbol_short, testutils::{Address as _, Ledger}, Address, Env};
+
+    /// Token stub that records each transfer as (to, amount)
+    #[contract]
+    pub struct RecordingToken;
+
+    #[contractimpl]
+    impl RecordingToken {
+        pub fn transfer(env: Env, _from: Address, to: Address, amount: i128) {
+            let mut log: Vec<(Address, i128)> = env.storage().instance().get(&symbol_short!("log")).unwrap_or(vec![&env]);
+            log.push_back((to, amount));
+            env.storage().instance().set(&symbol_short!("log"), &log);
+        }
+
+        pub fn transfers(env: Env) -> Vec<(Address, i128)> {
+            env.storage().instance().get(&symbol_short!("log")).unwrap_or(vec![&env])
+        }
+    }
+
+    /// Escrow stub exposing only the completion flag
+    #[contract]
+    pub struct MockEscrow;
+
+    #[contractimpl]
+    impl MockEscrow {
+        pub fn complete(env: Env, escrow_id: u64) {
+            env.storage().instance().set(&(symbol_short!("done"), escrow_id), &true);
+        }
+
+        pub fn is_completed(env: Env, escrow_id: u64) -> bool {
+            env.storage().instance().get(&(symbol_short!("done"), escrow_id)).unwrap_or(false)
+        }
+    }
 
     #[test]
     fn test_order_creation() {
@@ -262,8 +685,9 @@ mod test {
 
         let base = Address::generate(&env);
         let quote = Address::generate(&env);
+        let trader = Address::generate(&env);
 
-        let buy_id = client.create_buy_order(&base, &quote, &1_000_000, &100);
+        let buy_id = client.create_buy_order(&trader, &base, &quote, &1_000_000, &100);
         let order = client.get_order(&buy_id).unwrap();
         assert_eq!(order.status, OrderStatus::Open);
         assert_eq!(order.amount, 100);
@@ -280,9 +704,11 @@ mod test {
 
         let base = Address::generate(&env);
         let quote = Address::generate(&env);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
 
-        let sell_id = client.create_sell_order(&base, &quote, &1_000_000, &50);
-        let buy_id = client.create_buy_order(&base, &quote, &1_000_000, &50);
+        let sell_id = client.create_sell_order(&maker, &base, &quote, &1_000_000, &50);
+        let buy_id = client.create_buy_order(&taker, &base, &quote, &1_000_000, &50);
 
         let sell_order = client.get_order(&sell_id).unwrap();
         let buy_order = client.get_order(&buy_id).unwrap();
@@ -302,9 +728,11 @@ mod test {
 
         let base = Address::generate(&env);
         let quote = Address::generate(&env);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
 
-        let sell_id = client.create_sell_order(&base, &quote, &1_000_000, &100);
-        let buy_id = client.create_buy_order(&base, &quote, &1_000_000, &50);
+        let sell_id = client.create_sell_order(&maker, &base, &quote, &1_000_000, &100);
+        let buy_id = client.create_buy_order(&taker, &base, &quote, &1_000_000, &50);
 
         let sell_order = client.get_order(&sell_id).unwrap();
         assert_eq!(sell_order.status, OrderStatus::PartiallyFilled);
@@ -322,11 +750,418 @@ mod test {
 
         let base = Address::generate(&env);
         let quote = Address::generate(&env);
+        let trader = Address::generate(&env);
 
-        let order_id = client.create_buy_order(&base, &quote, &1_000_000, &100);
+        let order_id = client.create_buy_order(&trader, &base, &quote, &1_000_000, &100);
         client.cancel_order(&order_id);
 
         let order = client.get_order(&order_id).unwrap();
         assert_eq!(order.status, OrderStatus::Cancelled);
     }
+
+    #[test]
+    fn test_no_self_matching() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let trader = Address::generate(&env);
+
+        // Same trader places a sell, then a buy that would cross it
+        let sell_id = client.create_sell_order(&trader, &base, &quote, &1_000_000, &50);
+        let buy_id = client.create_buy_order(&trader, &base, &quote, &1_100_000, &50);
+
+        let sell_order = client.get_order(&sell_id).unwrap();
+        let buy_order = client.get_order(&buy_id).unwrap();
+        assert_eq!(sell_order.status, OrderStatus::Open);
+        assert_eq!(buy_order.status, OrderStatus::Open);
+        assert_eq!(sell_order.filled, 0);
+        assert_eq!(buy_order.filled, 0);
+    }
+
+    #[test]
+    fn test_fill_or_kill_fully_fillable() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+
+        let sell_id = client.create_sell_order(&maker, &base, &quote, &1_000_000, &100);
+        let market_id = client.create_market_order(
+            &taker, &OrderSide::Buy, &base, &quote, &100, &1_000_000, &TimeInForce::FillOrKill,
+        );
+
+        let market = client.get_order(&market_id).unwrap();
+        assert_eq!(market.status, OrderStatus::Filled);
+        assert_eq!(market.filled, 100);
+        assert_eq!(client.get_order(&sell_id).unwrap().status, OrderStatus::Filled);
+        // Market orders never rest on the book
+        assert_eq!(client.get_buy_orders(&base, &quote).len(), 0);
+    }
+
+    #[test]
+    fn test_fill_or_kill_unfillable_reverts() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+
+        let sell_id = client.create_sell_order(&maker, &base, &quote, &1_000_000, &50);
+        let result = client.try_create_market_order(
+            &taker, &OrderSide::Buy, &base, &quote, &100, &1_000_000, &TimeInForce::FillOrKill,
+        );
+        assert_eq!(result, Err(Ok(Error::CannotFill)));
+
+        let sell_order = client.get_order(&sell_id).unwrap();
+        assert_eq!(sell_order.status, OrderStatus::Open);
+        assert_eq!(sell_order.filled, 0);
+    }
+
+    #[test]
+    fn test_immediate_or_cancel_partial_fill() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+
+        let sell_id = client.create_sell_order(&maker, &base, &quote, &1_000_000, &40);
+        // Above the slippage limit, so it must not be taken
+        let expensive_id = client.create_sell_order(&maker, &base, &quote, &2_000_000, &60);
+        let market_id = client.create_market_order(
+            &taker, &OrderSide::Buy, &base, &quote, &100, &1_500_000, &TimeInForce::ImmediateOrCancel,
+        );
+
+        let market = client.get_order(&market_id).unwrap();
+        assert_eq!(market.filled, 40);
+        assert_eq!(market.status, OrderStatus::Cancelled);
+        assert_eq!(client.get_order(&sell_id).unwrap().status, OrderStatus::Filled);
+        assert_eq!(client.get_order(&expensive_id).unwrap().filled, 0);
+    }
+
+    #[test]
+    fn test_buy_taker_refunded_price_improvement() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = env.register_contract(None, RecordingToken);
+        let quote_id = env.register_contract(None, RecordingToken);
+        let quote = RecordingTokenClient::new(&env, &quote_id);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+
+        client.create_sell_order(&maker, &base, &quote_id, &1_000_000, &50);
+        // Escrows 60 at the 1.2 limit but trades at the maker's 1.0
+        client.create_buy_order(&taker, &base, &quote_id, &1_200_000, &50);
+
+        let transfers = quote.transfers();
+        assert_eq!(transfers.len(), 3);
+        assert_eq!(transfers.get(0).unwrap(), (contract_id.clone(), 60));
+        assert_eq!(transfers.get(1).unwrap(), (maker, 50));
+        assert_eq!(transfers.get(2).unwrap(), (taker, 10));
+    }
+
+    #[test]
+    fn test_market_buy_refunds_improvement_and_remainder() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = env.register_contract(None, RecordingToken);
+        let quote_id = env.register_contract(None, RecordingToken);
+        let quote = RecordingTokenClient::new(&env, &quote_id);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+
+        client.create_sell_order(&maker, &base, &quote_id, &1_000_000, &40);
+        // Escrows 150 at the 1.5 limit; 40 trade at 1.0 and 60 go unfilled
+        client.create_market_order(
+            &taker, &OrderSide::Buy, &base, &quote_id, &100, &1_500_000, &TimeInForce::ImmediateOrCancel,
+        );
+
+        let transfers = quote.transfers();
+        assert_eq!(transfers.len(), 4);
+        assert_eq!(transfers.get(0).unwrap(), (contract_id.clone(), 150));
+        assert_eq!(transfers.get(1).unwrap(), (maker, 40));
+        assert_eq!(transfers.get(2).unwrap(), (taker.clone(), 20));
+        assert_eq!(transfers.get(3).unwrap(), (taker, 90));
+    }
+
+    #[test]
+    fn test_amend_increase_amount() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let trader = Address::generate(&env);
+
+        let order_id = client.create_buy_order(&trader, &base, &quote, &1_000_000, &100);
+        client.amend_order(&order_id, &1_000_000, &150);
+
+        let order = client.get_order(&order_id).unwrap();
+        assert_eq!(order.amount, 150);
+        assert_eq!(order.status, OrderStatus::Open);
+    }
+
+    #[test]
+    fn test_amend_decrease_amount() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+
+        let sell_id = client.create_sell_order(&maker, &base, &quote, &1_000_000, &100);
+        client.create_buy_order(&taker, &base, &quote, &1_000_000, &40);
+        client.amend_order(&sell_id, &1_000_000, &60);
+
+        let order = client.get_order(&sell_id).unwrap();
+        assert_eq!(order.amount, 60);
+        assert_eq!(order.filled, 40);
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+
+        // Cannot shrink below what has already filled
+        let result = client.try_amend_order(&sell_id, &1_000_000, &30);
+        assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+    }
+
+    #[test]
+    fn test_amend_reprice_triggers_match() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+
+        let sell_id = client.create_sell_order(&maker, &base, &quote, &2_000_000, &50);
+        let buy_id = client.create_buy_order(&taker, &base, &quote, &1_000_000, &50);
+        assert_eq!(client.get_order(&buy_id).unwrap().status, OrderStatus::Open);
+
+        client.amend_order(&sell_id, &1_000_000, &50);
+
+        assert_eq!(client.get_order(&sell_id).unwrap().status, OrderStatus::Filled);
+        assert_eq!(client.get_order(&buy_id).unwrap().status, OrderStatus::Filled);
+    }
+
+    #[test]
+    fn test_trade_history() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+
+        let sell_id = client.create_sell_order(&maker, &base, &quote, &1_000_000, &50);
+        let buy_id = client.create_buy_order(&taker, &base, &quote, &1_200_000, &50);
+
+        let trades = client.get_trades_for_pair(&base, &quote, &0, &10);
+        assert_eq!(trades.len(), 1);
+        let trade = trades.get(0).unwrap();
+        assert_eq!(trade.maker_order_id, sell_id);
+        assert_eq!(trade.taker_order_id, buy_id);
+        assert_eq!(trade.amount, 50);
+        // Executes at the resting maker's price
+        assert_eq!(trade.price, 1_000_000);
+
+        assert_eq!(client.get_trades_for_order(&sell_id), trades);
+        assert_eq!(client.get_trades_for_order(&buy_id), trades);
+        assert_eq!(client.get_trades_for_pair(&base, &quote, &1, &10).len(), 0);
+    }
+
+    #[test]
+    fn test_last_price_and_vwap() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+
+        assert_eq!(client.get_last_price(&base, &quote), None);
+        assert_eq!(client.get_vwap(&base, &quote, &100), None);
+
+        client.create_sell_order(&maker, &base, &quote, &1_000_000, &10);
+        client.create_sell_order(&maker, &base, &quote, &2_000_000, &30);
+        client.create_buy_order(&taker, &base, &quote, &2_000_000, &40);
+
+        assert_eq!(client.get_last_price(&base, &quote), Some(2_000_000));
+        // (1.0 * 10 + 2.0 * 30) / 40 = 1.75
+        assert_eq!(client.get_vwap(&base, &quote, &100), Some(1_750_000));
+
+        // Trades fall out of a window that no longer covers their ledger
+        env.ledger().with_mut(|li| li.sequence_number += 200);
+        assert_eq!(client.get_vwap(&base, &quote, &100), None);
+        assert_eq!(client.get_last_price(&base, &quote), Some(2_000_000));
+    }
+
+    #[test]
+    fn test_eight_decimal_price_scale() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let trader = Address::generate(&env);
+
+        client.set_price_scale(&base, &quote, &100_000_000);
+        assert_eq!(client.get_price_scale(&base, &quote), 100_000_000);
+
+        // 1.25 quote per base at 8 decimals
+        assert_eq!(client.get_quote_amount(&base, &quote, &125_000_000, &400), 500);
+        assert_eq!(client.get_quote_amount(&base, &quote, &100_000_001, &100_000_000), 100_000_001);
+
+        let order_id = client.create_buy_order(&trader, &base, &quote, &125_000_000, &400);
+        client.cancel_order(&order_id);
+        assert_eq!(client.get_order(&order_id).unwrap().status, OrderStatus::Cancelled);
+
+        // Fixed once the pair is in use
+        let result = client.try_set_price_scale(&base, &quote, &1_000_000);
+        assert_eq!(result, Err(Ok(Error::ScaleAlreadySet)));
+    }
+
+    #[test]
+    fn test_default_scale_fixed_on_first_order() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let trader = Address::generate(&env);
+
+        client.create_sell_order(&trader, &base, &quote, &1_000_000, &10);
+        assert_eq!(client.get_price_scale(&base, &quote), 1_000_000);
+        let result = client.try_set_price_scale(&base, &quote, &100_000_000);
+        assert_eq!(result, Err(Ok(Error::ScaleAlreadySet)));
+    }
+
+    #[test]
+    fn test_pause_blocks_orders_not_cancels() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let trader = Address::generate(&env);
+
+        let order_id = client.create_buy_order(&trader, &base, &quote, &1_000_000, &100);
+        client.set_paused(&true);
+        assert!(client.is_paused());
+
+        assert_eq!(client.try_create_buy_order(&trader, &base, &quote, &1_000_000, &100), Err(Ok(Error::Paused)));
+        assert_eq!(client.try_create_sell_order(&trader, &base, &quote, &1_000_000, &100), Err(Ok(Error::Paused)));
+
+        client.cancel_order(&order_id);
+        assert_eq!(client.get_order(&order_id).unwrap().status, OrderStatus::Cancelled);
+
+        client.set_paused(&false);
+        client.create_sell_order(&trader, &base, &quote, &1_000_000, &100);
+    }
+
+    #[test]
+    fn test_linked_order_waits_for_escrow() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DEXOrdersContract);
+        let client = DEXOrdersContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin, &100);
+
+        let base = Address::generate(&env);
+        let quote = Address::generate(&env);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+
+        let result = client.try_create_linked_order(&maker, &OrderSide::Sell, &base, &quote, &1_000_000, &50, &7);
+        assert_eq!(result, Err(Ok(Error::EscrowNotSet)));
+
+        let escrow_id = env.register_contract(None, MockEscrow);
+        let escrow = MockEscrowClient::new(&env, &escrow_id);
+        client.set_escrow_contract(&escrow_id);
+
+        let sell_id = client.create_linked_order(&maker, &OrderSide::Sell, &base, &quote, &1_000_000, &50, &7);
+        let buy_id = client.create_buy_order(&taker, &base, &quote, &1_000_000, &50);
+
+        // The escrow is unresolved, so the crossing buy rests instead of trading
+        assert_eq!(client.get_order(&sell_id).unwrap().status, OrderStatus::Open);
+        assert_eq!(client.get_order(&buy_id).unwrap().status, OrderStatus::Open);
+        assert_eq!(client.match_order(&buy_id), 0);
+
+        escrow.complete(&7);
+        client.match_order(&buy_id);
+
+        assert_eq!(client.get_order(&sell_id).unwrap().status, OrderStatus::Filled);
+        assert_eq!(client.get_order(&buy_id).unwrap().status, OrderStatus::Filled);
+    }
 }
@@ -38,6 +38,7 @@ pub enum Error {
     AlreadyExists = 3,
     Deactivated = 4,
     InvalidMethod = 5,
+    InvalidEndpoint = 6,
 }
 
 #[contract]
@@ -109,6 +110,96 @@ impl DIDManagerContract {
         Ok(())
     }
 
+    pub fn remove_verification_method(env: Env, did_id: String, method: BytesN<32>) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+
+        let mut doc: DIDDocument = env.storage().persistent().get(&DataKey::DID(did_id.clone())).ok_or(Error::NotFound)?;
+        if caller != doc.controller { return Err(Error::Unauthorized); }
+        if doc.deactivated { return Err(Error::Deactivated); }
+
+        let index = doc.verification_methods.first_index_of(&method).ok_or(Error::InvalidMethod)?;
+        doc.verification_methods.remove(index);
+        if let Some(auth_index) = doc.authentication.first_index_of(&method) {
+            doc.authentication.remove(auth_index);
+        }
+        doc.updated = env.ledger().timestamp();
+        env.storage().persistent().set(&DataKey::DID(did_id.clone()), &doc);
+        env.events().publish((symbol_short!("updated"), did_id), ());
+        Ok(())
+    }
+
+    pub fn rotate_verification_method(env: Env, did_id: String, old_method: BytesN<32>, new_method: BytesN<32>) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+
+        let mut doc: DIDDocument = env.storage().persistent().get(&DataKey::DID(did_id.clone())).ok_or(Error::NotFound)?;
+        if caller != doc.controller { return Err(Error::Unauthorized); }
+        if doc.deactivated { return Err(Error::Deactivated); }
+
+        let index = doc.verification_methods.first_index_of(&old_method).ok_or(Error::InvalidMethod)?;
+        if doc.verification_methods.contains(&new_method) { return Err(Error::InvalidMethod); }
+
+        doc.verification_methods.set(index, new_method.clone());
+        if let Some(auth_index) = doc.authentication.first_index_of(&old_method) {
+            doc.authentication.set(auth_index, new_method.clone());
+        }
+        doc.updated = env.ledger().timestamp();
+        env.storage().persistent().set(&DataKey::DID(did_id.clone()), &doc);
+        env.events().publish((symbol_short!("rotated"), did_id), (old_method, new_method));
+        Ok(())
+    }
+
+    /// Marks an existing verification method as usable for authentication.
+    pub fn add_authentication(env: Env, did_id: String, method: BytesN<32>) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+
+        let mut doc: DIDDocument = env.storage().persistent().get(&DataKey::DID(did_id.clone())).ok_or(Error::NotFound)?;
+        if caller != doc.controller { return Err(Error::Unauthorized); }
+        if doc.deactivated { return Err(Error::Deactivated); }
+        if !doc.verification_methods.contains(&method) { return Err(Error::InvalidMethod); }
+        if doc.authentication.contains(&method) { return Err(Error::AlreadyExists); }
+
+        doc.authentication.push_back(method);
+        doc.updated = env.ledger().timestamp();
+        env.storage().persistent().set(&DataKey::DID(did_id.clone()), &doc);
+        env.events().publish((symbol_short!("updated"), did_id), ());
+        Ok(())
+    }
+
+    pub fn remove_authentication(env: Env, did_id: String, method: BytesN<32>) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+
+        let mut doc: DIDDocument = env.storage().persistent().get(&DataKey::DID(did_id.clone())).ok_or(Error::NotFound)?;
+        if caller != doc.controller { return Err(Error::Unauthorized); }
+        if doc.deactivated { return Err(Error::Deactivated); }
+
+        let index = doc.authentication.first_index_of(&method).ok_or(Error::InvalidMethod)?;
+        doc.authentication.remove(index);
+        doc.updated = env.ledger().timestamp();
+        env.storage().persistent().set(&DataKey::DID(did_id.clone()), &doc);
+        env.events().publish((symbol_short!("updated"), did_id), ());
+        Ok(())
+    }
+
+    pub fn remove_service_endpoint(env: Env, did_id: String, endpoint: String) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+
+        let mut doc: DIDDocument = env.storage().persistent().get(&DataKey::DID(did_id.clone())).ok_or(Error::NotFound)?;
+        if caller != doc.controller { return Err(Error::Unauthorized); }
+        if doc.deactivated { return Err(Error::Deactivated); }
+
+        let index = doc.service_endpoints.first_index_of(&endpoint).ok_or(Error::InvalidEndpoint)?;
+        doc.service_endpoints.remove(index);
+        doc.updated = env.ledger().timestamp();
+        env.storage().persistent().set(&DataKey::DID(did_id.clone()), &doc);
+        env.events().publish((symbol_short!("updated"), did_id), ());
+        Ok(())
+    }
+
     pub fn transfer_control(env: Env, did_id: String, new_controller: Address) -> Result<(), Error> {
         let caller = env.invoker();
         caller.require_auth();
@@ -212,4 +303,65 @@ mod test {
         let doc = client.get_did_document(&did_id).unwrap();
         assert_eq!(doc.deactivated, true);
     }
+
+    #[test]
+    fn test_remove_and_rotate_methods() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DIDManagerContract);
+        let client = DIDManagerContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let did_id = String::from_str(&env, "did:stellar:24680");
+        let original = BytesN::from_array(&env, &[4u8; 32]);
+        let added = BytesN::from_array(&env, &[5u8; 32]);
+        let rotated = BytesN::from_array(&env, &[6u8; 32]);
+        client.create_did(&did_id, &vec![&env, original.clone()]);
+
+        client.add_verification_method(&did_id, &added);
+        assert_eq!(client.get_did_document(&did_id).unwrap().verification_methods.len(), 2);
+
+        client.remove_verification_method(&did_id, &added);
+        assert_eq!(client.get_did_document(&did_id).unwrap().verification_methods.len(), 1);
+        assert_eq!(client.try_remove_verification_method(&did_id, &added), Err(Ok(Error::InvalidMethod)));
+
+        client.rotate_verification_method(&did_id, &original, &rotated);
+        let doc = client.get_did_document(&did_id).unwrap();
+        assert_eq!(doc.verification_methods, vec![&env, rotated.clone()]);
+
+        let endpoint = String::from_str(&env, "https://example.com/hub");
+        client.add_service_endpoint(&did_id, &endpoint);
+        client.remove_service_endpoint(&did_id, &endpoint);
+        assert_eq!(client.get_did_document(&did_id).unwrap().service_endpoints.len(), 0);
+        assert_eq!(client.try_remove_service_endpoint(&did_id, &endpoint), Err(Ok(Error::InvalidEndpoint)));
+
+        client.add_service_endpoint(&did_id, &endpoint);
+        client.deactivate_did(&did_id);
+        assert_eq!(client.try_remove_service_endpoint(&did_id, &endpoint), Err(Ok(Error::Deactivated)));
+        assert_eq!(client.try_remove_verification_method(&did_id, &rotated), Err(Ok(Error::Deactivated)));
+    }
+
+    #[test]
+    fn test_authentication_requires_verification_method() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, DIDManagerContract);
+        let client = DIDManagerContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let did_id = String::from_str(&env, "did:stellar:13579");
+        let method = BytesN::from_array(&env, &[7u8; 32]);
+        let unknown = BytesN::from_array(&env, &[8u8; 32]);
+        client.create_did(&did_id, &vec![&env, method.clone()]);
+
+        assert_eq!(client.try_add_authentication(&did_id, &unknown), Err(Ok(Error::InvalidMethod)));
+
+        client.add_authentication(&did_id, &method);
+        assert_eq!(client.get_did_document(&did_id).unwrap().authentication, vec![&env, method.clone()]);
+
+        client.remove_authentication(&did_id, &method);
+        assert_eq!(client.get_did_document(&did_id).unwrap().authentication.len(), 0);
+    }
 }
@@ -7,14 +7,26 @@
 //! - Time-locked escrow (release after specific ledger)
 //! - Hash-locked escrow (HTLC with preimage verification)
 //! - Combined time+hash locks for atomic swaps
+//! - Linear vesting with partial claims
 //! - Expiration with sender cancellation
+//! - Early cancellation with a penalty paid to the recipient
 //! - Clawback mechanism for compliance
+//! - Optional SignerList quorum approval for institutional custody
 
 use soroban_sdk::{
-    contract, contracterror, contractimpl, contracttype,
-    token, Address, BytesN, Env, Vec, vec,
+    contract, contractclient, contracterror, contractimpl, contracttype,
+    token, xdr::ToXdr, Address, BytesN, Env, Vec, vec,
 };
 
+/// Basis points denominator for `early_cancel_penalty_bps`
+const BPS_DENOMINATOR: i128 = 10_000;
+
+/// The part of the SignerList contract that multisig-gated escrows depend on
+#[contractclient(name = "SignerListClient")]
+pub trait SignerListInterface {
+    fn is_approved(env: Env, owner: Address, tx_hash: BytesN<32>) -> bool;
+}
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum EscrowStatus {
@@ -31,6 +43,8 @@ pub enum Condition {
     HashLock(BytesN<32>),
     TimeLock(u32),
     Combined(BytesN<32>, u32),
+    /// Vests linearly from nothing at the first ledger to the full amount at the second
+    Linear(u32, u32),
 }
 
 #[contracttype]
@@ -41,6 +55,8 @@ pub struct Escrow {
     pub recipient: Address,
     pub token: Address,
     pub amount: i128,
+    /// Paid out so far through `execute_partial`
+    pub released: i128,
     pub condition: Condition,
     pub expires_at: u32,
     pub status: EscrowStatus,
@@ -48,6 +64,10 @@ pub struct Escrow {
     pub allow_clawback: bool,
     pub created_at: u64,
     pub finished_at: Option<u64>,
+    /// SignerList owner whose quorum must approve `approval_hash` before `execute`
+    pub require_signer_list: Option<Address>,
+    /// Share of the remaining amount forfeited to the recipient on `cancel_early`; `None` disallows it
+    pub early_cancel_penalty_bps: Option<u32>,
 }
 
 #[contracttype]
@@ -56,6 +76,11 @@ pub enum DataKey {
     Escrow(u64),
     NextEscrowId,
     Admin,
+    HashLockIndex(BytesN<32>),
+    Paused,
+    SenderEscrows(Address),
+    RecipientEscrows(Address),
+    SignerListContract,
 }
 
 #[contracterror]
@@ -73,6 +98,10 @@ pub enum Error {
     ClawbackNotAllowed = 9,
     InvalidCondition = 10,
     HashMismatch = 11,
+    Paused = 12,
+    SignerListNotSet = 13,
+    NotApproved = 14,
+    EarlyCancelNotAllowed = 15,
 }
 
 #[contract]
@@ -89,6 +118,27 @@ impl EscrowContract {
         env.storage().instance().set(&DataKey::NextEscrowId, &1u64);
     }
 
+    /// Block new escrows. Existing escrows can still be executed, cancelled or clawed back.
+    pub fn set_paused(env: Env, paused: bool) -> Result<(), Error> {
+        let admin: Address = env.storage().instance().get(&DataKey::Admin).ok_or(Error::NotFound)?;
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::Paused, &paused);
+        env.events().publish((symbol_short!("paused"),), paused);
+        Ok(())
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+    }
+
+    /// SignerList deployment consulted for escrows created with `require_signer_list`
+    pub fn set_signer_list_contract(env: Env, signer_list: Address) -> Result<(), Error> {
+        let admin: Address = env.storage().instance().get(&DataKey::Admin).ok_or(Error::NotFound)?;
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::SignerListContract, &signer_list);
+        Ok(())
+    }
+
     pub fn create_simple(
         env: Env,
         recipient: Address,
@@ -98,7 +148,7 @@ impl EscrowContract {
     ) -> Result<u64, Error> {
         Self::create_escrow_internal(
             env, recipient, token, amount,
-            Condition::None, duration, None, false,
+            Condition::None, duration, None, false, None, None,
         )
     }
 
@@ -112,7 +162,7 @@ impl EscrowContract {
     ) -> Result<u64, Error> {
         Self::create_escrow_internal(
             env, recipient, token, amount,
-            Condition::HashLock(hash_lock), duration, None, false,
+            Condition::HashLock(hash_lock), duration, None, false, None, None,
         )
     }
 
@@ -131,7 +181,7 @@ impl EscrowContract {
             .ok_or(Error::InvalidAmount)?;
         Self::create_escrow_internal(
             env, recipient, token, amount,
-            Condition::TimeLock(unlock_at), duration, None, false,
+            Condition::TimeLock(unlock_at), duration, None, false, None, None,
         )
     }
 
@@ -151,7 +201,7 @@ impl EscrowContract {
             .ok_or(Error::InvalidAmount)?;
         Self::create_escrow_internal(
             env, recipient, token, amount,
-            Condition::Combined(hash_lock, unlock_at), duration, None, false,
+            Condition::Combined(hash_lock, unlock_at), duration, None, false, None, None,
         )
     }
 
@@ -164,10 +214,12 @@ impl EscrowContract {
         duration: u32,
         memo: Option<BytesN<32>>,
         allow_clawback: bool,
+        require_signer_list: Option<Address>,
+        early_cancel_penalty_bps: Option<u32>,
     ) -> Result<u64, Error> {
         Self::create_escrow_internal(
             env, recipient, token, amount, condition,
-            duration, memo, allow_clawback,
+            duration, memo, allow_clawback, require_signer_list, early_cancel_penalty_bps,
         )
     }
 
@@ -180,16 +232,45 @@ impl EscrowContract {
         duration: u32,
         memo: Option<BytesN<32>>,
         allow_clawback: bool,
+        require_signer_list: Option<Address>,
+        early_cancel_penalty_bps: Option<u32>,
     ) -> Result<u64, Error> {
         let sender = env.invoker();
         sender.require_auth();
 
+        if Self::is_paused(env.clone()) {
+            return Err(Error::Paused);
+        }
+
         if amount <= 0 {
             return Err(Error::InvalidAmount);
         }
 
+        if early_cancel_penalty_bps.map_or(false, |bps| bps as i128 > BPS_DENOMINATOR) {
+            return Err(Error::InvalidAmount);
+        }
+
+        let expires_at = env.ledger().sequence() + duration;
+
+        // Vesting must complete while the escrow can still be executed
+        if let Condition::Linear(start, end) = &condition {
+            if start >= end || *end > expires_at {
+                return Err(Error::InvalidCondition);
+            }
+        }
+
+        if require_signer_list.is_some() && !env.storage().instance().has(&DataKey::SignerListContract) {
+            return Err(Error::SignerListNotSet);
+        }
+
+        // Record what actually arrived so fee-on-transfer tokens cannot leave the escrow short
         let token_client = token::Client::new(&env, &token);
+        let balance_before = token_client.balance(&env.current_contract_address());
         token_client.transfer(&sender, &env.current_contract_address(), &amount);
+        let amount = token_client.balance(&env.current_contract_address()) - balance_before;
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
 
         let escrow_id: u64 = env.storage()
             .instance()
@@ -203,18 +284,31 @@ impl EscrowContract {
             recipient: recipient.clone(),
             token: token.clone(),
             amount,
+            released: 0,
             condition,
-            expires_at: env.ledger().sequence() + duration,
+            expires_at,
             status: EscrowStatus::Pending,
             memo,
             allow_clawback,
             created_at: env.ledger().timestamp(),
             finished_at: None,
+            require_signer_list,
+            early_cancel_penalty_bps,
         };
 
         env.storage().persistent().set(&DataKey::Escrow(escrow_id), &escrow);
         env.storage().persistent().extend_ttl(&DataKey::Escrow(escrow_id), 518400, 518400);
 
+        Self::index_add(&env, DataKey::SenderEscrows(sender.clone()), escrow_id);
+        Self::index_add(&env, DataKey::RecipientEscrows(recipient.clone()), escrow_id);
+
+        if let Condition::HashLock(hash) | Condition::Combined(hash, _) = &escrow.condition {
+            let index_key = DataKey::HashLockIndex(hash.clone());
+            let mut linked: Vec<u64> = env.storage().persistent().get(&index_key).unwrap_or(vec![&env]);
+            linked.push_back(escrow_id);
+            env.storage().persistent().set(&index_key, &linked);
+        }
+
         env.events().publish(
             (symbol_short!("created"), sender, recipient),
             (escrow_id, amount),
@@ -251,7 +345,7 @@ impl EscrowContract {
         match &escrow.condition {
             Condition::None => {},
             Condition::HashLock(hash) => {
-                let provided_preimage = preimage.ok_or(Error::InvalidPreimage)?;
+                let provided_preimage = preimage.clone().ok_or(Error::InvalidPreimage)?;
                 let computed_hash = env.crypto().sha256(&provided_preimage);
                 if computed_hash != *hash {
                     return Err(Error::HashMismatch);
@@ -266,33 +360,93 @@ impl EscrowContract {
                 if env.ledger().sequence() < *unlock_at {
                     return Err(Error::TimeNotReached);
                 }
-                let provided_preimage = preimage.ok_or(Error::InvalidPreimage)?;
+                let provided_preimage = preimage.clone().ok_or(Error::InvalidPreimage)?;
                 let computed_hash = env.crypto().sha256(&provided_preimage);
                 if computed_hash != *hash {
                     return Err(Error::HashMismatch);
                 }
             },
+            Condition::Linear(_, end) => {
+                if env.ledger().sequence() < *end {
+                    return Err(Error::TimeNotReached);
+                }
+            },
         }
 
+        Self::check_signer_list(&env, &escrow)?;
+
+        // Persist the terminal state before the outgoing transfer so a re-entrant
+        // token contract sees the escrow as no longer pending
+        let payout = escrow.amount - escrow.released;
+        escrow.released = escrow.amount;
+        escrow.status = EscrowStatus::Completed;
+        escrow.finished_at = Some(env.ledger().timestamp());
+        env.storage().persistent().set(&DataKey::Escrow(escrow_id), &escrow);
+        Self::unindex(&env, &escrow);
+
         let token_client = token::Client::new(&env, &escrow.token);
         token_client.transfer(
             &env.current_contract_address(),
             &escrow.recipient,
-            &escrow.amount,
+            &payout,
         );
 
-        escrow.status = EscrowStatus::Completed;
-        escrow.finished_at = Some(env.ledger().timestamp());
-        env.storage().persistent().set(&DataKey::Escrow(escrow_id), &escrow);
+        // Reveal the preimage so linked HTLCs sharing this hash lock can be claimed downstream,
+        // and carry it on `executed` for relays on the counterpart chain
+        let revealed = match &escrow.condition {
+            Condition::HashLock(_) | Condition::Combined(_, _) => preimage,
+            Condition::None | Condition::TimeLock(_) | Condition::Linear(_, _) => None,
+        };
+        if let (Condition::HashLock(hash) | Condition::Combined(hash, _), Some(revealed)) = (&escrow.condition, &revealed) {
+            env.events().publish((symbol_short!("preimage"), hash.clone()), revealed.clone());
+        }
 
         env.events().publish(
             (symbol_short!("executed"), escrow_id),
-            escrow.amount,
+            (payout, revealed),
         );
 
         Ok(())
     }
 
+    /// Fails unless the escrow's SignerList owner, if any, has approved its `approval_hash`
+    fn check_signer_list(env: &Env, escrow: &Escrow) -> Result<(), Error> {
+        if let Some(owner) = &escrow.require_signer_list {
+            let signer_list: Address = env.storage().instance().get(&DataKey::SignerListContract).ok_or(Error::SignerListNotSet)?;
+            let approved = SignerListClient::new(env, &signer_list).is_approved(owner, &Self::escrow_approval_hash(env, escrow));
+            if !approved {
+                return Err(Error::NotApproved);
+            }
+        }
+        Ok(())
+    }
+
+    /// Release `amount` of a linearly vesting escrow to its recipient, up to what has vested
+    pub fn execute_partial(env: Env, escrow_id: u64, amount: i128) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+        let mut escrow: Escrow = env.storage().persistent().get(&DataKey::Escrow(escrow_id)).ok_or(Error::NotFound)?;
+        if caller != escrow.recipient { return Err(Error::Unauthorized); }
+        if !matches!(escrow.status, EscrowStatus::Pending) { return Err(Error::EscrowNotPending); }
+        if env.ledger().sequence() >= escrow.expires_at { return Err(Error::AlreadyExpired); }
+        if !matches!(escrow.condition, Condition::Linear(_, _)) { return Err(Error::InvalidCondition); }
+        if amount <= 0 || amount > Self::vested_unreleased(&env, &escrow) { return Err(Error::InvalidAmount); }
+        Self::check_signer_list(&env, &escrow)?;
+
+        escrow.released += amount;
+        if escrow.released == escrow.amount {
+            escrow.status = EscrowStatus::Completed;
+            escrow.finished_at = Some(env.ledger().timestamp());
+            Self::unindex(&env, &escrow);
+        }
+        env.storage().persistent().set(&DataKey::Escrow(escrow_id), &escrow);
+
+        let token_client = token::Client::new(&env, &escrow.token);
+        token_client.transfer(&env.current_contract_address(), &escrow.recipient, &amount);
+        env.events().publish((symbol_short!("partial"), escrow_id), (amount, escrow.released));
+        Ok(())
+    }
+
     pub fn cancel_expired(env: Env, escrow_id: u64) -> Result<(), Error> {
         let caller = env.invoker();
         caller.require_auth();
@@ -300,15 +454,48 @@ impl EscrowContract {
         if caller != escrow.sender { return Err(Error::Unauthorized); }
         if !matches!(escrow.status, EscrowStatus::Pending) { return Err(Error::EscrowNotPending); }
         if env.ledger().sequence() < escrow.expires_at { return Err(Error::NotExpired); }
-        let token_client = token::Client::new(&env, &escrow.token);
-        token_client.transfer(&env.current_contract_address(), &escrow.sender, &escrow.amount);
+        // Vested funds belong to the recipient even if they were never claimed
+        let vested = Self::vested_unreleased(&env, &escrow);
+        let refund = escrow.amount - escrow.released - vested;
+        escrow.released += vested;
         escrow.status = EscrowStatus::Expired;
         escrow.finished_at = Some(env.ledger().timestamp());
         env.storage().persistent().set(&DataKey::Escrow(escrow_id), &escrow);
+        Self::unindex(&env, &escrow);
+        let token_client = token::Client::new(&env, &escrow.token);
+        if vested > 0 { token_client.transfer(&env.current_contract_address(), &escrow.recipient, &vested); }
+        if refund > 0 { token_client.transfer(&env.current_contract_address(), &escrow.sender, &refund); }
         env.events().publish((symbol_short!("expired"), escrow_id), ());
         Ok(())
     }
 
+    /// Sender exit before expiry. Anything already vested goes to the recipient; of the unvested
+    /// remainder the penalty share goes to the recipient and the rest is refunded
+    pub fn cancel_early(env: Env, escrow_id: u64) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+        let mut escrow: Escrow = env.storage().persistent().get(&DataKey::Escrow(escrow_id)).ok_or(Error::NotFound)?;
+        if caller != escrow.sender { return Err(Error::Unauthorized); }
+        let penalty_bps = escrow.early_cancel_penalty_bps.ok_or(Error::EarlyCancelNotAllowed)?;
+        if !matches!(escrow.status, EscrowStatus::Pending) { return Err(Error::EscrowNotPending); }
+        if env.ledger().sequence() >= escrow.expires_at { return Err(Error::AlreadyExpired); }
+        let vested = Self::vested_unreleased(&env, &escrow);
+        let unvested = escrow.amount - escrow.released - vested;
+        let penalty = unvested * penalty_bps as i128 / BPS_DENOMINATOR;
+        let refund = unvested - penalty;
+        escrow.released += vested;
+        escrow.status = EscrowStatus::Cancelled;
+        escrow.finished_at = Some(env.ledger().timestamp());
+        env.storage().persistent().set(&DataKey::Escrow(escrow_id), &escrow);
+        Self::unindex(&env, &escrow);
+        let token_client = token::Client::new(&env, &escrow.token);
+        let to_recipient = vested + penalty;
+        if to_recipient > 0 { token_client.transfer(&env.current_contract_address(), &escrow.recipient, &to_recipient); }
+        if refund > 0 { token_client.transfer(&env.current_contract_address(), &escrow.sender, &refund); }
+        env.events().publish((symbol_short!("cancelled"), escrow_id), (vested, penalty, refund));
+        Ok(())
+    }
+
     pub fn clawback(env: Env, escrow_id: u64) -> Result<(), Error> {
         let caller = env.invoker();
         caller.require_auth();
@@ -316,11 +503,12 @@ impl EscrowContract {
         if caller != escrow.sender { return Err(Error::Unauthorized); }
         if !escrow.allow_clawback { return Err(Error::ClawbackNotAllowed); }
         if !matches!(escrow.status, EscrowStatus::Pending) { return Err(Error::EscrowNotPending); }
-        let token_client = token::Client::new(&env, &escrow.token);
-        token_client.transfer(&env.current_contract_address(), &escrow.sender, &escrow.amount);
         escrow.status = EscrowStatus::Cancelled;
         escrow.finished_at = Some(env.ledger().timestamp());
         env.storage().persistent().set(&DataKey::Escrow(escrow_id), &escrow);
+        Self::unindex(&env, &escrow);
+        let token_client = token::Client::new(&env, &escrow.token);
+        token_client.transfer(&env.current_contract_address(), &escrow.sender, &(escrow.amount - escrow.released));
         env.events().publish((symbol_short!("clawback"), escrow_id), ());
         Ok(())
     }
@@ -337,10 +525,105 @@ impl EscrowContract {
         Ok(())
     }
 
+    pub fn reassign_recipient(env: Env, escrow_id: u64, new_recipient: Address) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+        let mut escrow: Escrow = env.storage().persistent().get(&DataKey::Escrow(escrow_id)).ok_or(Error::NotFound)?;
+        if caller != escrow.sender { return Err(Error::Unauthorized); }
+        if !matches!(escrow.status, EscrowStatus::Pending) { return Err(Error::EscrowNotPending); }
+        if env.ledger().sequence() >= escrow.expires_at { return Err(Error::AlreadyExpired); }
+        let old_recipient = escrow.recipient.clone();
+        escrow.recipient = new_recipient.clone();
+        env.storage().persistent().set(&DataKey::Escrow(escrow_id), &escrow);
+        Self::index_remove(&env, DataKey::RecipientEscrows(old_recipient.clone()), escrow_id);
+        Self::index_add(&env, DataKey::RecipientEscrows(new_recipient.clone()), escrow_id);
+        env.events().publish((symbol_short!("reassign"), escrow_id), (old_recipient, new_recipient));
+        Ok(())
+    }
+
+    /// Pending escrows funded by `sender`
+    pub fn get_escrows_by_sender(env: Env, sender: Address) -> Vec<u64> {
+        env.storage().persistent().get(&DataKey::SenderEscrows(sender)).unwrap_or(vec![&env])
+    }
+
+    /// Pending escrows payable to `recipient`
+    pub fn get_escrows_by_recipient(env: Env, recipient: Address) -> Vec<u64> {
+        env.storage().persistent().get(&DataKey::RecipientEscrows(recipient)).unwrap_or(vec![&env])
+    }
+
+    fn index_add(env: &Env, key: DataKey, escrow_id: u64) {
+        let mut ids: Vec<u64> = env.storage().persistent().get(&key).unwrap_or(vec![env]);
+        ids.push_back(escrow_id);
+        env.storage().persistent().set(&key, &ids);
+    }
+
+    fn index_remove(env: &Env, key: DataKey, escrow_id: u64) {
+        let mut ids: Vec<u64> = env.storage().persistent().get(&key).unwrap_or(vec![env]);
+        if let Some(index) = ids.first_index_of(escrow_id) {
+            ids.remove(index);
+            env.storage().persistent().set(&key, &ids);
+        }
+    }
+
+    /// Drop a finished escrow from the sender/recipient indexes
+    fn unindex(env: &Env, escrow: &Escrow) {
+        Self::index_remove(env, DataKey::SenderEscrows(escrow.sender.clone()), escrow.id);
+        Self::index_remove(env, DataKey::RecipientEscrows(escrow.recipient.clone()), escrow.id);
+    }
+
     pub fn get_escrow(env: Env, escrow_id: u64) -> Option<Escrow> {
         env.storage().persistent().get(&DataKey::Escrow(escrow_id))
     }
 
+    /// Vested but not yet released amount a recipient can take now. Escrows without a linear
+    /// condition report their whole remaining amount once `can_execute` would pass.
+    pub fn claimable_amount(env: Env, escrow_id: u64) -> Result<i128, Error> {
+        let escrow: Escrow = env.storage().persistent().get(&DataKey::Escrow(escrow_id)).ok_or(Error::NotFound)?;
+        if !matches!(escrow.status, EscrowStatus::Pending) { return Ok(0); }
+        if env.ledger().sequence() >= escrow.expires_at { return Ok(0); }
+        match &escrow.condition {
+            Condition::Linear(_, _) => Ok(Self::vested_unreleased(&env, &escrow)),
+            Condition::TimeLock(unlock_at) if env.ledger().sequence() < *unlock_at => Ok(0),
+            Condition::Combined(_, unlock_at) if env.ledger().sequence() < *unlock_at => Ok(0),
+            _ => Ok(escrow.amount - escrow.released),
+        }
+    }
+
+    fn vested_unreleased(env: &Env, escrow: &Escrow) -> i128 {
+        let Condition::Linear(start, end) = escrow.condition else { return 0; };
+        let now = env.ledger().sequence();
+        let vested = if now <= start {
+            0
+        } else if now >= end {
+            escrow.amount
+        } else {
+            escrow.amount * (now - start) as i128 / (end - start) as i128
+        };
+        vested - escrow.released
+    }
+
+    /// Whether the escrow has been executed; used by DEX orders linked to an escrow
+    pub fn is_completed(env: Env, escrow_id: u64) -> bool {
+        Self::get_escrow(env, escrow_id).map_or(false, |escrow| escrow.status == EscrowStatus::Completed)
+    }
+
+    /// Transaction hash the SignerList must approve before a `require_signer_list` escrow executes.
+    /// It covers the payout terms, so reassigning the recipient invalidates an earlier approval.
+    pub fn approval_hash(env: Env, escrow_id: u64) -> Result<BytesN<32>, Error> {
+        let escrow: Escrow = env.storage().persistent().get(&DataKey::Escrow(escrow_id)).ok_or(Error::NotFound)?;
+        Ok(Self::escrow_approval_hash(&env, &escrow))
+    }
+
+    fn escrow_approval_hash(env: &Env, escrow: &Escrow) -> BytesN<32> {
+        let terms = (env.current_contract_address(), escrow.id, escrow.recipient.clone(), escrow.token.clone(), escrow.amount);
+        env.crypto().sha256(&terms.to_xdr(env))
+    }
+
+    /// Escrows created with this hash lock, in creation order
+    pub fn get_escrows_by_hashlock(env: Env, hash: BytesN<32>) -> Vec<u64> {
+        env.storage().persistent().get(&DataKey::HashLockIndex(hash)).unwrap_or(vec![&env])
+    }
+
     pub fn can_execute(env: Env, escrow_id: u64, preimage: Option<BytesN<32>>) -> Result<bool, Error> {
         let escrow: Escrow = env.storage().persistent().get(&DataKey::Escrow(escrow_id)).ok_or(Error::NotFound)?;
         if !matches!(escrow.status, EscrowStatus::Pending) { return Ok(false); }
@@ -359,6 +642,7 @@ impl EscrowContract {
                     Ok(env.crypto().sha256(&provided_preimage) == *hash)
                 } else { Ok(false) }
             },
+            Condition::Linear(_, end) => Ok(env.ledger().sequence() >= *end),
         }
     }
 
@@ -377,7 +661,96 @@ impl EscrowContract {
 #[cfg(test)]
 mod test {
     use super::*;
-    use soroban_sdk::{testutils::{Address as _, Ledger}, Address, BytesN, Env};
+    use soroban_sdk::{symbol_short, testutils::{Address as _, Events, Ledger}, Address, BytesN, Env, Symbol, TryFromVal};
+
+    /// Data of the `executed` event for `escrow_id`: (amount, revealed preimage)
+    fn executed_event(env: &Env, escrow_id: u64) -> Option<(i128, Option<BytesN<32>>)> {
+        env.events().all().iter().find_map(|(_, topics, data)| {
+            let name = Symbol::try_from_val(env, &topics.get(0)?).ok()?;
+            let id = u64::try_from_val(env, &topics.get(1)?).ok()?;
+            if name != symbol_short!("executed") || id != escrow_id {
+                return None;
+            }
+            <(i128, Option<BytesN<32>>)>::try_from_val(env, &data).ok()
+        })
+    }
+
+    /// Stub ledger shared by the token stubs; balances may go negative since senders are never funded
+    fn move_balance(env: &Env, from: &Address, to: &Address, debited: i128, credited: i128) {
+        let from_balance: i128 = env.storage().instance().get(from).unwrap_or(0);
+        env.storage().instance().set(from, &(from_balance - debited));
+        let to_balance: i128 = env.storage().instance().get(to).unwrap_or(0);
+        env.storage().instance().set(to, &(to_balance + credited));
+    }
+
+    /// Token stub that, once armed, reads the escrow's stored status from inside `transfer`
+    #[contract]
+    pub struct ObservingToken;
+
+    #[contractimpl]
+    impl ObservingToken {
+        pub fn arm(env: Env, escrow: Address, escrow_id: u64) {
+            env.storage().instance().set(&symbol_short!("target"), &(escrow, escrow_id));
+        }
+
+        pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+            move_balance(&env, &from, &to, amount, amount);
+
+            let target: Option<(Address, u64)> = env.storage().instance().get(&symbol_short!("target"));
+            if let Some((escrow, escrow_id)) = target {
+                // The host rejects a call back into the escrow, so read its storage directly
+                let status = env.as_contract(&escrow, || EscrowContract::get_escrow(env.clone(), escrow_id))
+                    .map(|escrow| escrow.status);
+                env.storage().instance().set(&symbol_short!("seen"), &status);
+            }
+        }
+
+        pub fn balance(env: Env, id: Address) -> i128 {
+            env.storage().instance().get(&id).unwrap_or(0)
+        }
+
+        /// Escrow status as stored when the armed transfer ran
+        pub fn observed(env: Env) -> Option<EscrowStatus> {
+            env.storage().instance().get(&symbol_short!("seen")).unwrap_or(None)
+        }
+    }
+
+    /// Token stub that records each outgoing transfer as (to, amount)
+    #[contract]
+    pub struct RecordingToken;
+
+    #[contractimpl]
+    impl RecordingToken {
+        pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+            move_balance(&env, &from, &to, amount, amount);
+            let mut log: Vec<(Address, i128)> = env.storage().instance().get(&symbol_short!("log")).unwrap_or(vec![&env]);
+            log.push_back((to, amount));
+            env.storage().instance().set(&symbol_short!("log"), &log);
+        }
+
+        pub fn transfers(env: Env) -> Vec<(Address, i128)> {
+            env.storage().instance().get(&symbol_short!("log")).unwrap_or(vec![&env])
+        }
+
+        pub fn balance(env: Env, id: Address) -> i128 {
+            env.storage().instance().get(&id).unwrap_or(0)
+        }
+    }
+
+    /// Token stub that burns 1% of every transfer
+    #[contract]
+    pub struct DeflationaryToken;
+
+    #[contractimpl]
+    impl DeflationaryToken {
+        pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+            move_balance(&env, &from, &to, amount, amount - amount / 100);
+        }
+
+        pub fn balance(env: Env, id: Address) -> i128 {
+            env.storage().instance().get(&id).unwrap_or(0)
+        }
+    }
 
     #[test]
     fn test_simple_escrow() {
@@ -386,7 +759,7 @@ mod test {
         let contract_id = env.register_contract(None, EscrowContract);
         let client = EscrowContractClient::new(&env, &contract_id);
         let recipient = Address::generate(&env);
-        let token = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
         let escrow_id = client.create_simple(&recipient, &token, &1000, &100);
         let escrow = client.get_escrow(&escrow_id).unwrap();
         assert_eq\!(escrow.amount, 1000);
@@ -400,7 +773,7 @@ mod test {
         let contract_id = env.register_contract(None, EscrowContract);
         let client = EscrowContractClient::new(&env, &contract_id);
         let recipient = Address::generate(&env);
-        let token = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
         let preimage = BytesN::from_array(&env, &[1u8; 32]);
         let hash = env.crypto().sha256(&preimage);
         let escrow_id = client.create_hash_locked(&recipient, &token, &1000, &hash, &100);
@@ -417,7 +790,7 @@ mod test {
         let contract_id = env.register_contract(None, EscrowContract);
         let client = EscrowContractClient::new(&env, &contract_id);
         let recipient = Address::generate(&env);
-        let token = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
         let hash = BytesN::from_array(&env, &[2u8; 32]);
         let escrow_id = client.create_hash_locked(&recipient, &token, &1000, &hash, &100);
         let wrong_preimage = BytesN::from_array(&env, &[1u8; 32]);
@@ -431,7 +804,7 @@ mod test {
         let contract_id = env.register_contract(None, EscrowContract);
         let client = EscrowContractClient::new(&env, &contract_id);
         let recipient = Address::generate(&env);
-        let token = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
         let current_ledger = env.ledger().sequence();
         let unlock_at = current_ledger + 50;
         let expiration = current_ledger + 100;
@@ -453,7 +826,7 @@ mod test {
         let contract_id = env.register_contract(None, EscrowContract);
         let client = EscrowContractClient::new(&env, &contract_id);
         let recipient = Address::generate(&env);
-        let token = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
         let escrow_id = client.create_simple(&recipient, &token, &1000, &10);
         env.ledger().with_mut(|li| li.sequence_number += 20);
         client.cancel_expired(&escrow_id);
@@ -468,8 +841,8 @@ mod test {
         let contract_id = env.register_contract(None, EscrowContract);
         let client = EscrowContractClient::new(&env, &contract_id);
         let recipient = Address::generate(&env);
-        let token = Address::generate(&env);
-        let escrow_id = client.create_escrow(&recipient, &token, &1000, &Condition::None, &100, &None, &true);
+        let token = env.register_contract(None, RecordingToken);
+        let escrow_id = client.create_escrow(&recipient, &token, &1000, &Condition::None, &100, &None, &true, &None, &None);
         client.clawback(&escrow_id);
         let escrow = client.get_escrow(&escrow_id).unwrap();
         assert_eq\!(escrow.status, EscrowStatus::Cancelled);
@@ -482,7 +855,7 @@ mod test {
         let contract_id = env.register_contract(None, EscrowContract);
         let client = EscrowContractClient::new(&env, &contract_id);
         let recipient = Address::generate(&env);
-        let token = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
         let preimage = BytesN::from_array(&env, &[42u8; 32]);
         let hash = env.crypto().sha256(&preimage);
         let current_ledger = env.ledger().sequence();
@@ -494,4 +867,430 @@ mod test {
         let escrow = client.get_escrow(&escrow_id).unwrap();
         assert_eq\!(escrow.status, EscrowStatus::Completed);
     }
+
+    #[test]
+    fn test_linked_htlcs_share_hashlock() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let recipient = Address::generate(&env);
+        let downstream = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+        let preimage = BytesN::from_array(&env, &[9u8; 32]);
+        let hash = env.crypto().sha256(&preimage);
+
+        let first = client.create_hash_locked(&recipient, &token, &1000, &hash, &100);
+        let second = client.create_hash_locked(&downstream, &token, &900, &hash, &50);
+
+        let linked = client.get_escrows_by_hashlock(&hash);
+        assert_eq!(linked.len(), 2);
+        assert_eq!(linked.get(0).unwrap(), first);
+        assert_eq!(linked.get(1).unwrap(), second);
+
+        client.execute(&second, &Some(preimage.clone()));
+
+        let revealed = env.events().all().iter().any(|(_, _, data)| {
+            BytesN::<32>::try_from_val(&env, &data).map(|b| b == preimage).unwrap_or(false)
+        });
+        assert!(revealed);
+        assert_eq!(client.get_escrow(&first).unwrap().status, EscrowStatus::Pending);
+    }
+
+    #[test]
+    fn test_reassign_recipient() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let old_recipient = Address::generate(&env);
+        let new_recipient = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+        let escrow_id = client.create_simple(&old_recipient, &token, &1000, &100);
+
+        client.reassign_recipient(&escrow_id, &new_recipient);
+        let escrow = client.get_escrow(&escrow_id).unwrap();
+        assert_eq!(escrow.recipient, new_recipient);
+        assert_ne!(escrow.recipient, old_recipient);
+
+        client.execute(&escrow_id, &None);
+        let escrow = client.get_escrow(&escrow_id).unwrap();
+        assert_eq!(escrow.status, EscrowStatus::Completed);
+
+        // Completed escrows can no longer be redirected
+        let result = client.try_reassign_recipient(&escrow_id, &old_recipient);
+        assert_eq!(result, Err(Ok(Error::EscrowNotPending)));
+    }
+
+    #[test]
+    fn test_reassign_rejected_after_expiry() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let recipient = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+        let escrow_id = client.create_simple(&recipient, &token, &1000, &10);
+        env.ledger().with_mut(|li| li.sequence_number += 20);
+        let result = client.try_reassign_recipient(&escrow_id, &Address::generate(&env));
+        assert_eq!(result, Err(Ok(Error::AlreadyExpired)));
+    }
+
+    #[test]
+    fn test_execute_commits_state_before_transfer() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let token_id = env.register_contract(None, ObservingToken);
+        let token = ObservingTokenClient::new(&env, &token_id);
+        let recipient = Address::generate(&env);
+
+        let escrow_id = client.create_simple(&recipient, &token_id, &1000, &100);
+        token.arm(&contract_id, &escrow_id);
+
+        client.execute(&escrow_id, &None);
+
+        // The payout transfer already saw the escrow as completed
+        assert_eq!(token.observed(), Some(EscrowStatus::Completed));
+        assert_eq!(token.balance(&recipient), 1000);
+        assert_eq!(client.get_escrow(&escrow_id).unwrap().status, EscrowStatus::Completed);
+        assert_eq!(client.try_execute(&escrow_id, &None), Err(Ok(Error::EscrowNotPending)));
+    }
+
+    #[test]
+    fn test_pause_blocks_creation_only() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+        let recipient = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+
+        let to_execute = client.create_simple(&recipient, &token, &1000, &100);
+        let to_expire = client.create_simple(&recipient, &token, &1000, &10);
+        client.set_paused(&true);
+
+        assert_eq!(client.try_create_simple(&recipient, &token, &1000, &100), Err(Ok(Error::Paused)));
+
+        client.execute(&to_execute, &None);
+        assert_eq!(client.get_escrow(&to_execute).unwrap().status, EscrowStatus::Completed);
+
+        env.ledger().with_mut(|li| li.sequence_number += 20);
+        client.cancel_expired(&to_expire);
+        assert_eq!(client.get_escrow(&to_expire).unwrap().status, EscrowStatus::Expired);
+    }
+
+    #[test]
+    fn test_sender_and_recipient_indexes() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+
+        let first = client.create_simple(&alice, &token, &1000, &100);
+        let second = client.create_simple(&bob, &token, &500, &100);
+        let third = client.create_escrow(&alice, &token, &250, &Condition::None, &100, &None, &true, &None, &None);
+        let sender = client.get_escrow(&first).unwrap().sender;
+
+        assert_eq!(client.get_escrows_by_sender(&sender), vec![&env, first, second, third]);
+        assert_eq!(client.get_escrows_by_recipient(&alice), vec![&env, first, third]);
+        assert_eq!(client.get_escrows_by_recipient(&bob), vec![&env, second]);
+
+        client.execute(&first, &None);
+        client.clawback(&third);
+
+        assert_eq!(client.get_escrows_by_sender(&sender), vec![&env, second]);
+        assert_eq!(client.get_escrows_by_recipient(&alice).len(), 0);
+        assert_eq!(client.get_escrows_by_recipient(&bob), vec![&env, second]);
+    }
+
+    #[test]
+    fn test_executed_event_reveals_preimage() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let recipient = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+        let preimage = BytesN::from_array(&env, &[7u8; 32]);
+        let hash = env.crypto().sha256(&preimage);
+
+        let escrow_id = client.create_hash_locked(&recipient, &token, &1000, &hash, &100);
+        client.execute(&escrow_id, &Some(preimage.clone()));
+        assert_eq!(executed_event(&env, escrow_id), Some((1000, Some(preimage))));
+    }
+
+    #[test]
+    fn test_executed_event_omits_preimage_for_time_lock() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let recipient = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+        let current_ledger = env.ledger().sequence();
+        let escrow_id = client.create_time_locked(&recipient, &token, &1000, &(current_ledger + 5), &(current_ledger + 100));
+        env.ledger().with_mut(|li| li.sequence_number = current_ledger + 5);
+
+        // A preimage passed to a time lock is not echoed
+        client.execute(&escrow_id, &Some(BytesN::from_array(&env, &[7u8; 32])));
+        assert_eq!(executed_event(&env, escrow_id), Some((1000, None)));
+    }
+
+    /// SignerList stub whose approvals are granted directly by the test
+    #[contract]
+    pub struct MockSignerList;
+
+    #[contractimpl]
+    impl MockSignerList {
+        pub fn approve(env: Env, owner: Address, tx_hash: BytesN<32>) {
+            env.storage().instance().set(&(owner, tx_hash), &true);
+        }
+
+        pub fn is_approved(env: Env, owner: Address, tx_hash: BytesN<32>) -> bool {
+            env.storage().instance().get(&(owner, tx_hash)).unwrap_or(false)
+        }
+    }
+
+    #[test]
+    fn test_signer_list_gates_execution() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+        let recipient = Address::generate(&env);
+        let custodian = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+
+        let result = client.try_create_escrow(&recipient, &token, &1000, &Condition::None, &100, &None, &false, &Some(custodian.clone()), &None);
+        assert_eq!(result, Err(Ok(Error::SignerListNotSet)));
+
+        let signer_list_id = env.register_contract(None, MockSignerList);
+        let signer_list = MockSignerListClient::new(&env, &signer_list_id);
+        client.set_signer_list_contract(&signer_list_id);
+        let escrow_id = client.create_escrow(&recipient, &token, &1000, &Condition::None, &100, &None, &false, &Some(custodian.clone()), &None);
+        let other_id = client.create_escrow(&recipient, &token, &1000, &Condition::None, &100, &None, &false, &Some(custodian.clone()), &None);
+
+        assert_eq!(client.try_execute(&escrow_id, &None), Err(Ok(Error::NotApproved)));
+        assert_eq!(client.get_escrow(&escrow_id).unwrap().status, EscrowStatus::Pending);
+
+        // Approval for another owner or another escrow does not count
+        signer_list.approve(&Address::generate(&env), &client.approval_hash(&escrow_id));
+        signer_list.approve(&custodian, &client.approval_hash(&other_id));
+        assert_eq!(client.try_execute(&escrow_id, &None), Err(Ok(Error::NotApproved)));
+
+        signer_list.approve(&custodian, &client.approval_hash(&escrow_id));
+        client.execute(&escrow_id, &None);
+        assert_eq!(client.get_escrow(&escrow_id).unwrap().status, EscrowStatus::Completed);
+    }
+
+    #[test]
+    fn test_reassign_voids_signer_list_approval() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+        let recipient = Address::generate(&env);
+        let custodian = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+        let signer_list_id = env.register_contract(None, MockSignerList);
+        let signer_list = MockSignerListClient::new(&env, &signer_list_id);
+        client.set_signer_list_contract(&signer_list_id);
+
+        let escrow_id = client.create_escrow(&recipient, &token, &1000, &Condition::None, &100, &None, &false, &Some(custodian.clone()), &None);
+        let approved = client.approval_hash(&escrow_id);
+        signer_list.approve(&custodian, &approved);
+
+        // Sending the approved escrow elsewhere changes the terms the quorum signed off on
+        client.reassign_recipient(&escrow_id, &Address::generate(&env));
+        assert_ne!(client.approval_hash(&escrow_id), approved);
+        assert_eq!(client.try_execute(&escrow_id, &None), Err(Ok(Error::NotApproved)));
+        assert_eq!(client.get_escrow(&escrow_id).unwrap().status, EscrowStatus::Pending);
+    }
+
+    #[test]
+    fn test_linear_vesting_claimable() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let recipient = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+        let start = env.ledger().sequence() + 10;
+        let end = start + 100;
+
+        let result = client.try_create_escrow(&recipient, &token, &1000, &Condition::Linear(end, start), &200, &None, &false, &None, &None);
+        assert_eq!(result, Err(Ok(Error::InvalidCondition)));
+        // Would expire before fully vesting
+        let result = client.try_create_escrow(&recipient, &token, &1000, &Condition::Linear(start, end), &100, &None, &false, &None, &None);
+        assert_eq!(result, Err(Ok(Error::InvalidCondition)));
+
+        let escrow_id = client.create_escrow(&recipient, &token, &1000, &Condition::Linear(start, end), &200, &None, &false, &None, &None);
+        assert_eq!(client.claimable_amount(&escrow_id), 0);
+
+        env.ledger().with_mut(|li| li.sequence_number = start + 50);
+        assert_eq!(client.claimable_amount(&escrow_id), 500);
+        assert_eq!(client.try_execute(&escrow_id, &None), Err(Ok(Error::TimeNotReached)));
+
+        client.execute_partial(&escrow_id, &300);
+        assert_eq!(client.claimable_amount(&escrow_id), 200);
+        assert_eq!(client.get_escrow(&escrow_id).unwrap().released, 300);
+
+        env.ledger().with_mut(|li| li.sequence_number = end + 5);
+        assert_eq!(client.claimable_amount(&escrow_id), 700);
+        client.execute_partial(&escrow_id, &700);
+        let escrow = client.get_escrow(&escrow_id).unwrap();
+        assert_eq!(escrow.status, EscrowStatus::Completed);
+        assert_eq!(escrow.released, 1000);
+    }
+
+    #[test]
+    fn test_expired_vesting_pays_recipient() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let token_id = env.register_contract(None, RecordingToken);
+        let token = RecordingTokenClient::new(&env, &token_id);
+        let recipient = Address::generate(&env);
+        let start = env.ledger().sequence();
+
+        let escrow_id = client.create_escrow(&recipient, &token_id, &1000, &Condition::Linear(start, start + 100), &200, &None, &false, &None, &None);
+        env.ledger().with_mut(|li| li.sequence_number = start + 50);
+        client.execute_partial(&escrow_id, &200);
+
+        // The recipient never claims the rest before expiry
+        env.ledger().with_mut(|li| li.sequence_number = start + 200);
+        client.cancel_expired(&escrow_id);
+
+        let escrow = client.get_escrow(&escrow_id).unwrap();
+        assert_eq!(escrow.status, EscrowStatus::Expired);
+        assert_eq!(escrow.released, 1000);
+        let transfers = token.transfers();
+        assert_eq!(transfers.len(), 3);
+        assert_eq!(transfers.get(2).unwrap(), (recipient, 800));
+    }
+
+    #[test]
+    fn test_linear_vesting_rejects_over_claim() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let recipient = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+        let start = env.ledger().sequence();
+        let escrow_id = client.create_escrow(&recipient, &token, &1000, &Condition::Linear(start, start + 100), &200, &None, &false, &None, &None);
+
+        env.ledger().with_mut(|li| li.sequence_number = start + 25);
+        assert_eq!(client.try_execute_partial(&escrow_id, &251), Err(Ok(Error::InvalidAmount)));
+        client.execute_partial(&escrow_id, &250);
+        assert_eq!(client.try_execute_partial(&escrow_id, &1), Err(Ok(Error::InvalidAmount)));
+
+        // Partial release only applies to vesting escrows
+        let simple = client.create_simple(&recipient, &token, &1000, &100);
+        assert_eq!(client.try_execute_partial(&simple, &10), Err(Ok(Error::InvalidCondition)));
+    }
+
+    #[test]
+    fn test_cancel_early_splits_penalty() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let token_id = env.register_contract(None, RecordingToken);
+        let token = RecordingTokenClient::new(&env, &token_id);
+        let recipient = Address::generate(&env);
+
+        let result = client.try_create_escrow(&recipient, &token_id, &1000, &Condition::None, &100, &None, &false, &None, &Some(10_001));
+        assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+
+        // 2.5% penalty
+        let escrow_id = client.create_escrow(&recipient, &token_id, &1000, &Condition::None, &100, &None, &false, &None, &Some(250));
+        client.cancel_early(&escrow_id);
+
+        let escrow = client.get_escrow(&escrow_id).unwrap();
+        assert_eq!(escrow.status, EscrowStatus::Cancelled);
+        let transfers = token.transfers();
+        // The first transfer funded the escrow
+        assert_eq!(transfers.len(), 3);
+        assert_eq!(transfers.get(1).unwrap(), (recipient.clone(), 25));
+        assert_eq!(transfers.get(2).unwrap(), (escrow.sender, 975));
+        assert_eq!(client.try_cancel_early(&escrow_id), Err(Ok(Error::EscrowNotPending)));
+    }
+
+    #[test]
+    fn test_cancel_early_pays_vested_before_penalty() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let token_id = env.register_contract(None, RecordingToken);
+        let token = RecordingTokenClient::new(&env, &token_id);
+        let recipient = Address::generate(&env);
+        let start = env.ledger().sequence();
+
+        // 10% penalty on a 1000 grant vesting over 100 ledgers
+        let escrow_id = client.create_escrow(&recipient, &token_id, &1000, &Condition::Linear(start, start + 100), &200, &None, &false, &None, &Some(1_000));
+        env.ledger().with_mut(|li| li.sequence_number = start + 40);
+        client.execute_partial(&escrow_id, &100);
+        client.cancel_early(&escrow_id);
+
+        // 300 vested but unclaimed, plus 10% of the 600 unvested
+        let escrow = client.get_escrow(&escrow_id).unwrap();
+        assert_eq!(escrow.released, 400);
+        let transfers = token.transfers();
+        assert_eq!(transfers.len(), 4);
+        assert_eq!(transfers.get(2).unwrap(), (recipient, 360));
+        assert_eq!(transfers.get(3).unwrap(), (escrow.sender, 540));
+    }
+
+    #[test]
+    fn test_cancel_early_requires_opt_in_and_no_expiry() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let recipient = Address::generate(&env);
+        let token = env.register_contract(None, RecordingToken);
+
+        let no_exit = client.create_simple(&recipient, &token, &1000, &100);
+        assert_eq!(client.try_cancel_early(&no_exit), Err(Ok(Error::EarlyCancelNotAllowed)));
+
+        let expiring = client.create_escrow(&recipient, &token, &1000, &Condition::None, &10, &None, &false, &None, &Some(500));
+        env.ledger().with_mut(|li| li.sequence_number += 20);
+        assert_eq!(client.try_cancel_early(&expiring), Err(Ok(Error::AlreadyExpired)));
+        assert_eq!(client.get_escrow(&expiring).unwrap().status, EscrowStatus::Pending);
+    }
+
+    #[test]
+    fn test_fee_on_transfer_token_records_received_amount() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, EscrowContract);
+        let client = EscrowContractClient::new(&env, &contract_id);
+        let token_id = env.register_contract(None, DeflationaryToken);
+        let token = DeflationaryTokenClient::new(&env, &token_id);
+        let recipient = Address::generate(&env);
+
+        let escrow_id = client.create_simple(&recipient, &token_id, &1000, &100);
+        assert_eq!(client.get_escrow(&escrow_id).unwrap().amount, 990);
+        assert_eq!(token.balance(&contract_id), 990);
+
+        client.execute(&escrow_id, &None);
+        assert_eq!(executed_event(&env, escrow_id), Some((990, None)));
+        // The payout itself is taxed, but the escrow paid out exactly what it held
+        assert_eq!(token.balance(&contract_id), 0);
+        assert_eq!(token.balance(&recipient), 990 - 9);
+    }
 }
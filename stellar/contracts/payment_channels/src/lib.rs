@@ -9,9 +9,10 @@
 //! - Dispute resolution with challenge period
 //! - Unilateral close after expiration
 
+use shared::verify_claim;
 use soroban_sdk::{
     contract, contracterror, contractimpl, contracttype,
-    token, Address, BytesN, Env,
+    token, vec, xdr::ToXdr, Address, Bytes, BytesN, Env, Vec,
 };
 
 #[contracttype]
@@ -37,16 +38,45 @@ pub struct Channel {
     pub balance: i128,
     /// Amount claimed by recipient
     pub claimed: i128,
+    /// Portion of `claimed` already transferred to the recipient
+    pub paid_out: i128,
     /// Last nonce used (replay protection)
     pub nonce: u64,
     /// Expiration ledger
     pub expires_at: u32,
     /// Current status
     pub status: ChannelStatus,
-    /// Dispute initiated timestamp
-    pub disputed_at: Option<u64>,
+    /// Ledger sequence at which the dispute was raised
+    pub disputed_at_ledger: Option<u32>,
     /// Challenge period (ledgers)
     pub challenge_period: u32,
+    /// Recipient's Ed25519 key for authorizing third-party top-ups
+    pub recipient_key: Option<BytesN<32>>,
+    /// Number of authorized top-ups so far; each recipient signature covers the next value
+    pub funding_nonce: u64,
+    /// Sender's Ed25519 key that signs off-chain claims; fixed for the channel's lifetime
+    pub sender_key: BytesN<32>,
+    /// Optional reference (e.g. invoice hash) for reconciliation
+    pub memo: Option<BytesN<32>>,
+}
+
+/// One claim in a `claim_batch` call
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClaimInput {
+    pub channel_id: u64,
+    pub amount: i128,
+    pub nonce: u64,
+    pub signature: BytesN<64>,
+}
+
+/// Per-claim result of `claim_batch`; `error` is the `Error` code when not settled
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClaimOutcome {
+    pub channel_id: u64,
+    pub settled: bool,
+    pub error: u32,
 }
 
 #[contracttype]
@@ -55,6 +85,8 @@ pub enum DataKey {
     Channel(u64),
     NextChannelId,
     Admin,
+    Paused,
+    SenderChannels(Address),
 }
 
 #[contracterror]
@@ -73,6 +105,22 @@ pub enum Error {
     NoDispute = 10,
     InvalidAmount = 11,
     AlreadyDisputed = 12,
+    KeyNotSet = 13,
+    Paused = 14,
+    ChallengePeriodEnded = 15,
+}
+
+/// Message a recipient signs to authorize a top-up:
+/// `contract (Address XDR) || channel_id (u64 BE) || amount (i128 BE) || funding_nonce (u64 BE)`
+///
+/// The contract address and the channel's funding nonce make each signature good for one
+/// top-up on one deployment.
+fn funding_message(env: &Env, contract: &Address, channel_id: u64, amount: i128, funding_nonce: u64) -> Bytes {
+    let mut message = contract.clone().to_xdr(env);
+    message.append(&Bytes::from_array(env, &channel_id.to_be_bytes()));
+    message.append(&Bytes::from_array(env, &amount.to_be_bytes()));
+    message.append(&Bytes::from_array(env, &funding_nonce.to_be_bytes()));
+    message
 }
 
 #[contract]
@@ -87,6 +135,20 @@ impl PaymentChannelsContract {
         env.storage().instance().set(&DataKey::NextChannelId, &1u64);
     }
 
+    /// Block new channels. Existing channels can still be claimed, withdrawn from and closed.
+    pub fn set_paused(env: Env, paused: bool) -> Result<(), Error> {
+        let admin: Address = env.storage().instance().get(&DataKey::Admin).ok_or(Error::NotFound)?;
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::Paused, &paused);
+        env.events().publish((symbol_short!("paused"),), paused);
+        Ok(())
+    }
+
+    /// Whether new channels are currently blocked
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+    }
+
     /// Create a new payment channel
     ///
     /// # Arguments
@@ -95,6 +157,9 @@ impl PaymentChannelsContract {
     /// * `amount` - Initial deposit amount
     /// * `duration` - Channel duration in ledgers
     /// * `challenge_period` - Dispute challenge period in ledgers
+    /// * `sender_key` - Ed25519 key that signs claims; it cannot be rotated, so claims
+    ///   the recipient already holds stay valid
+    /// * `memo` - Optional reference carried on the channel and its `created` event
     pub fn create_channel(
         env: Env,
         recipient: Address,
@@ -102,10 +167,16 @@ impl PaymentChannelsContract {
         amount: i128,
         duration: u32,
         challenge_period: u32,
+        sender_key: BytesN<32>,
+        memo: Option<BytesN<32>>,
     ) -> Result<u64, Error> {
         let sender = env.invoker();
         sender.require_auth();
 
+        if Self::is_paused(env.clone()) {
+            return Err(Error::Paused);
+        }
+
         if amount <= 0 {
             return Err(Error::InvalidAmount);
         }
@@ -129,19 +200,33 @@ impl PaymentChannelsContract {
             token: token.clone(),
             balance: amount,
             claimed: 0,
+            paid_out: 0,
             nonce: 0,
             expires_at: env.ledger().sequence() + duration,
             status: ChannelStatus::Active,
-            disputed_at: None,
+            disputed_at_ledger: None,
             challenge_period,
+            recipient_key: None,
+            funding_nonce: 0,
+            sender_key,
+            memo: memo.clone(),
         };
 
         env.storage().persistent().set(&DataKey::Channel(channel_id), &channel);
         env.storage().persistent().extend_ttl(&DataKey::Channel(channel_id), 518400, 518400);
 
+        // Index by sender
+        let index_key = DataKey::SenderChannels(sender.clone());
+        let mut sender_channels: Vec<u64> = env.storage()
+            .persistent()
+            .get(&index_key)
+            .unwrap_or(vec![&env]);
+        sender_channels.push_back(channel_id);
+        env.storage().persistent().set(&index_key, &sender_channels);
+
         env.events().publish(
             (symbol_short!("created"), sender, recipient),
-            (channel_id, amount),
+            (channel_id, amount, memo),
         );
 
         Ok(channel_id)
@@ -188,6 +273,124 @@ impl PaymentChannelsContract {
         Ok(())
     }
 
+    /// Withdraw surplus funds the recipient has not claimed. Only tokens still held
+    /// by the contract and not owed to the recipient (`claimed - paid_out`) can leave.
+    pub fn withdraw_unclaimed(
+        env: Env,
+        channel_id: u64,
+        amount: i128,
+    ) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        let mut channel: Channel = env.storage()
+            .persistent()
+            .get(&DataKey::Channel(channel_id))
+            .ok_or(Error::NotFound)?;
+
+        if caller != channel.sender {
+            return Err(Error::Unauthorized);
+        }
+
+        if !matches!(channel.status, ChannelStatus::Active) {
+            return Err(Error::ChannelNotActive);
+        }
+
+        let held = channel.balance.checked_sub(channel.paid_out).ok_or(Error::InvalidAmount)?;
+        let owed = channel.claimed.checked_sub(channel.paid_out).ok_or(Error::InvalidAmount)?;
+        if amount > held - owed {
+            return Err(Error::InsufficientBalance);
+        }
+
+        channel.balance -= amount;
+        env.storage().persistent().set(&DataKey::Channel(channel_id), &channel);
+
+        let token_client = token::Client::new(&env, &channel.token);
+        token_client.transfer(&env.current_contract_address(), &channel.sender, &amount);
+
+        env.events().publish(
+            (symbol_short!("withdrawn"), channel_id),
+            amount,
+        );
+
+        Ok(())
+    }
+
+    /// Register the recipient's Ed25519 key used to authorize third-party top-ups
+    pub fn set_recipient_key(
+        env: Env,
+        channel_id: u64,
+        public_key: BytesN<32>,
+    ) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+
+        let mut channel: Channel = env.storage()
+            .persistent()
+            .get(&DataKey::Channel(channel_id))
+            .ok_or(Error::NotFound)?;
+
+        if caller != channel.recipient {
+            return Err(Error::Unauthorized);
+        }
+
+        channel.recipient_key = Some(public_key);
+        env.storage().persistent().set(&DataKey::Channel(channel_id), &channel);
+
+        Ok(())
+    }
+
+    /// Fund a channel on the recipient's behalf. Any funder may call this with the
+    /// recipient's signature over `funding_message(contract, channel_id, amount, funding_nonce)`.
+    pub fn fund_channel_authorized(
+        env: Env,
+        channel_id: u64,
+        amount: i128,
+        recipient_sig: BytesN<64>,
+    ) -> Result<(), Error> {
+        let funder = env.invoker();
+        funder.require_auth();
+
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        let mut channel: Channel = env.storage()
+            .persistent()
+            .get(&DataKey::Channel(channel_id))
+            .ok_or(Error::NotFound)?;
+
+        if !matches!(channel.status, ChannelStatus::Active) {
+            return Err(Error::ChannelNotActive);
+        }
+
+        let recipient_key = channel.recipient_key.clone().ok_or(Error::KeyNotSet)?;
+        // Traps on an invalid signature
+        env.crypto().ed25519_verify(
+            &recipient_key,
+            &funding_message(&env, &env.current_contract_address(), channel_id, amount, channel.funding_nonce),
+            &recipient_sig,
+        );
+        channel.funding_nonce += 1;
+
+        let token_client = token::Client::new(&env, &channel.token);
+        token_client.transfer(&funder, &env.current_contract_address(), &amount);
+
+        channel.balance = channel.balance.checked_add(amount).ok_or(Error::InvalidAmount)?;
+        env.storage().persistent().set(&DataKey::Channel(channel_id), &channel);
+
+        env.events().publish(
+            (symbol_short!("funded"), channel_id),
+            amount,
+        );
+
+        Ok(())
+    }
+
     /// Extend channel expiration
     pub fn extend_channel(
         env: Env,
@@ -224,7 +427,7 @@ impl PaymentChannelsContract {
     /// * `channel_id` - Channel to claim from
     /// * `amount` - Total amount to claim
     /// * `nonce` - Nonce (must be > previous)
-    /// * `signature` - Signature from sender
+    /// * `signature` - Sender's signature over `shared::claim_message(contract, channel_id, amount, nonce)`
     pub fn claim_payment(
         env: Env,
         channel_id: u64,
@@ -235,12 +438,53 @@ impl PaymentChannelsContract {
         let caller = env.invoker();
         caller.require_auth();
 
+        Self::settle_claim(&env, &caller, channel_id, amount, nonce, &signature)
+    }
+
+    /// Settle several claims in one transaction (e.g. a hub operator that is the
+    /// recipient on many channels). Each claim is validated on its own, so a bad
+    /// claim (including a malformed signature) is reported in its outcome instead of
+    /// aborting the batch. A well-formed signature that fails host verification
+    /// still traps the whole batch.
+    pub fn claim_batch(env: Env, claims: Vec<ClaimInput>) -> Vec<ClaimOutcome> {
+        let caller = env.invoker();
+        caller.require_auth();
+
+        let mut outcomes = vec![&env];
+        for claim in claims.iter() {
+            let result = Self::settle_claim(
+                &env,
+                &caller,
+                claim.channel_id,
+                claim.amount,
+                claim.nonce,
+                &claim.signature,
+            );
+            outcomes.push_back(ClaimOutcome {
+                channel_id: claim.channel_id,
+                settled: result.is_ok(),
+                error: result.err().map(|e| e as u32).unwrap_or(0),
+            });
+        }
+        outcomes
+    }
+
+    /// Validate a claim and pay the recipient the difference from what was already
+    /// claimed. All checks run before any state change or transfer.
+    fn settle_claim(
+        env: &Env,
+        caller: &Address,
+        channel_id: u64,
+        amount: i128,
+        nonce: u64,
+        signature: &BytesN<64>,
+    ) -> Result<(), Error> {
         let mut channel: Channel = env.storage()
             .persistent()
             .get(&DataKey::Channel(channel_id))
             .ok_or(Error::NotFound)?;
 
-        if caller != channel.recipient {
+        if *caller != channel.recipient {
             return Err(Error::Unauthorized);
         }
 
@@ -260,20 +504,25 @@ impl PaymentChannelsContract {
             return Err(Error::InsufficientBalance);
         }
 
-        // TODO: Verify Ed25519 signature
-        // In production: verify signature of (channel_id, amount, nonce) from sender
-        // env.crypto().ed25519_verify(&channel.sender, message_hash, &signature);
+        if amount <= channel.claimed {
+            return Err(Error::InvalidAmount);
+        }
+
+        if !verify_claim(env, &env.current_contract_address(), &channel.sender_key, channel_id, amount, nonce, signature) {
+            return Err(Error::InvalidSignature);
+        }
 
         // Update channel
-        let claim_amount = amount.checked_sub(channel.claimed)
+        let claim_amount = amount.checked_sub(channel.paid_out)
             .ok_or(Error::InvalidAmount)?;
 
         channel.claimed = amount;
+        channel.paid_out = amount;
         channel.nonce = nonce;
 
         // Transfer claimed amount to recipient
-        let token_client = token::Client::new(&env, &channel.token);
-        token_client.transfer(&env.current_contract_address(), &caller, &claim_amount);
+        let token_client = token::Client::new(env, &channel.token);
+        token_client.transfer(&env.current_contract_address(), caller, &claim_amount);
 
         // Auto-close if fully claimed
         if channel.claimed >= channel.balance {
@@ -316,14 +565,22 @@ impl PaymentChannelsContract {
             return Err(Error::InsufficientBalance);
         }
 
+        // Claims already paid out cannot be clawed back
+        if final_amount < channel.paid_out {
+            return Err(Error::InvalidAmount);
+        }
+
         let token_client = token::Client::new(&env, &channel.token);
 
-        // Transfer final amount to recipient
-        token_client.transfer(
-            &env.current_contract_address(),
-            &channel.recipient,
-            &final_amount,
-        );
+        // Transfer the part of the final amount not yet paid to recipient
+        let owed = final_amount - channel.paid_out;
+        if owed > 0 {
+            token_client.transfer(
+                &env.current_contract_address(),
+                &channel.recipient,
+                &owed,
+            );
+        }
 
         // Return remainder to sender
         let remainder = channel.balance.checked_sub(final_amount)
@@ -338,6 +595,7 @@ impl PaymentChannelsContract {
 
         channel.status = ChannelStatus::Closed;
         channel.claimed = final_amount;
+        channel.paid_out = final_amount;
         env.storage().persistent().set(&DataKey::Channel(channel_id), &channel);
 
         env.events().publish(
@@ -372,9 +630,8 @@ impl PaymentChannelsContract {
 
         // If disputed, challenge period must have passed
         if matches!(channel.status, ChannelStatus::Disputed) {
-            if let Some(disputed_at) = channel.disputed_at {
-                let elapsed = env.ledger().timestamp().saturating_sub(disputed_at);
-                if elapsed < (channel.challenge_period as u64 * 5) { // ~5 seconds per ledger
+            if let Some(disputed_at_ledger) = channel.disputed_at_ledger {
+                if env.ledger().sequence() < disputed_at_ledger.saturating_add(channel.challenge_period) {
                     return Err(Error::ChallengePeriodActive);
                 }
             }
@@ -382,12 +639,16 @@ impl PaymentChannelsContract {
 
         let token_client = token::Client::new(&env, &channel.token);
 
-        // Transfer claimed to recipient
-        token_client.transfer(
-            &env.current_contract_address(),
-            &channel.recipient,
-            &channel.claimed,
-        );
+        // Transfer claimed but not yet paid (e.g. a dispute state) to recipient
+        let owed = channel.claimed.checked_sub(channel.paid_out)
+            .ok_or(Error::InvalidAmount)?;
+        if owed > 0 {
+            token_client.transfer(
+                &env.current_contract_address(),
+                &channel.recipient,
+                &owed,
+            );
+        }
 
         // Return unclaimed to sender
         let unclaimed = channel.balance.checked_sub(channel.claimed)
@@ -401,6 +662,7 @@ impl PaymentChannelsContract {
         }
 
         channel.status = ChannelStatus::Closed;
+        channel.paid_out = channel.claimed;
         env.storage().persistent().set(&DataKey::Channel(channel_id), &channel);
 
         env.events().publish(
@@ -433,7 +695,7 @@ impl PaymentChannelsContract {
         }
 
         channel.status = ChannelStatus::Disputed;
-        channel.disputed_at = Some(env.ledger().timestamp());
+        channel.disputed_at_ledger = Some(env.ledger().sequence());
         env.storage().persistent().set(&DataKey::Channel(channel_id), &channel);
 
         env.events().publish(
@@ -444,11 +706,72 @@ impl PaymentChannelsContract {
         Ok(())
     }
 
+    /// Submit a newer signed state during a dispute (recipient or a watchtower acting for
+    /// them). A higher nonce replaces the recorded claim so the latest state wins at close.
+    pub fn submit_dispute_state(
+        env: Env,
+        channel_id: u64,
+        amount: i128,
+        nonce: u64,
+        signature: BytesN<64>,
+    ) -> Result<(), Error> {
+        let mut channel: Channel = env.storage()
+            .persistent()
+            .get(&DataKey::Channel(channel_id))
+            .ok_or(Error::NotFound)?;
+
+        channel.recipient.require_auth();
+
+        if !matches!(channel.status, ChannelStatus::Disputed) {
+            return Err(Error::NoDispute);
+        }
+
+        let disputed_at_ledger = channel.disputed_at_ledger.ok_or(Error::NoDispute)?;
+        if env.ledger().sequence() >= disputed_at_ledger.saturating_add(channel.challenge_period) {
+            return Err(Error::ChallengePeriodEnded);
+        }
+
+        if nonce <= channel.nonce {
+            return Err(Error::InvalidNonce);
+        }
+
+        if amount > channel.balance {
+            return Err(Error::InsufficientBalance);
+        }
+
+        if amount < channel.claimed {
+            return Err(Error::InvalidAmount);
+        }
+
+        if !verify_claim(&env, &env.current_contract_address(), &channel.sender_key, channel_id, amount, nonce, &signature) {
+            return Err(Error::InvalidSignature);
+        }
+
+        channel.claimed = amount;
+        channel.nonce = nonce;
+        env.storage().persistent().set(&DataKey::Channel(channel_id), &channel);
+
+        env.events().publish(
+            (symbol_short!("dispstate"), channel_id),
+            (amount, nonce),
+        );
+
+        Ok(())
+    }
+
     /// Get channel details
     pub fn get_channel(env: Env, channel_id: u64) -> Option<Channel> {
         env.storage().persistent().get(&DataKey::Channel(channel_id))
     }
 
+    /// List channel IDs created by a sender
+    pub fn get_channels_by_sender(env: Env, sender: Address) -> Vec<u64> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::SenderChannels(sender))
+            .unwrap_or(vec![&env])
+    }
+
     /// Get available balance in channel
     pub fn get_available_balance(env: Env, channel_id: u64) -> Result<i128, Error> {
         let channel: Channel = env.storage()
@@ -463,7 +786,23 @@ impl PaymentChannelsContract {
 #[cfg(test)]
 mod test {
     use super::*;
-    use soroban_sdk::{testutils::{Address as _, Ledger}, Address, Env};
+    use soroban_sdk::{symbol_short, testutils::{Address as _, Ledger}, Address, Env};
+
+    fn sender_signing_key() -> ed25519_dalek::SigningKey {
+        ed25519_dalek::SigningKey::from_bytes(&[9u8; 32])
+    }
+
+    fn sender_public_key(env: &Env) -> BytesN<32> {
+        BytesN::from_array(env, &sender_signing_key().verifying_key().to_bytes())
+    }
+
+    /// Sender signature over a claim on the channels contract behind `client`
+    fn signed_claim(client: &PaymentChannelsContractClient, channel_id: u64, amount: i128, nonce: u64) -> BytesN<64> {
+        use ed25519_dalek::Signer;
+        let mut message = [0u8; 72];
+        shared::claim_message(&client.env, &client.address, channel_id, amount, nonce).copy_into_slice(&mut message);
+        BytesN::from_array(&client.env, &sender_signing_key().sign(&message).to_bytes())
+    }
 
     #[test]
     fn test_create_and_claim() {
@@ -476,13 +815,12 @@ mod test {
         let sender = Address::generate(&env);
         let recipient = Address::generate(&env);
         let token = Address::generate(&env);
-        let signature = BytesN::from_array(&env, &[0u8; 64]);
 
         // Simplified: skip actual token setup for unit test
-        let channel_id = client.create_channel(&recipient, &token, &1000, &1000, &100);
+        let channel_id = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
 
         // Claim payment
-        client.claim_payment(&channel_id, &500, &1, &signature);
+        client.claim_payment(&channel_id, &500, &1, &signed_claim(&client, channel_id, 500, 1));
 
         let available = client.get_available_balance(&channel_id);
         assert_eq!(available, 500);
@@ -499,14 +837,445 @@ mod test {
 
         let recipient = Address::generate(&env);
         let token = Address::generate(&env);
-        let signature = BytesN::from_array(&env, &[0u8; 64]);
 
-        let channel_id = client.create_channel(&recipient, &token, &1000, &1000, &100);
+        let channel_id = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
 
         // First claim
-        client.claim_payment(&channel_id, &100, &1, &signature);
+        client.claim_payment(&channel_id, &100, &1, &signed_claim(&client, channel_id, 100, 1));
 
         // Try with same nonce - should fail
-        client.claim_payment(&channel_id, &200, &1, &signature);
+        client.claim_payment(&channel_id, &200, &1, &signed_claim(&client, channel_id, 200, 1));
+    }
+
+    #[test]
+    fn test_claim_batch_skips_invalid_claim() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+
+        let recipient = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        let channel_a = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+        let channel_b = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+        let channel_c = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+
+        let claims = soroban_sdk::vec![
+            &env,
+            ClaimInput { channel_id: channel_a, amount: 300, nonce: 1, signature: signed_claim(&client, channel_a, 300, 1) },
+            // Exceeds the channel balance
+            ClaimInput { channel_id: channel_b, amount: 5000, nonce: 1, signature: signed_claim(&client, channel_b, 5000, 1) },
+            ClaimInput { channel_id: channel_c, amount: 700, nonce: 1, signature: signed_claim(&client, channel_c, 700, 1) },
+        ];
+        let outcomes = client.claim_batch(&claims);
+
+        assert_eq!(outcomes.len(), 3);
+        assert!(outcomes.get(0).unwrap().settled);
+        assert!(!outcomes.get(1).unwrap().settled);
+        assert_eq!(outcomes.get(1).unwrap().error, Error::InsufficientBalance as u32);
+        assert!(outcomes.get(2).unwrap().settled);
+
+        assert_eq!(client.get_available_balance(&channel_a), 700);
+        assert_eq!(client.get_available_balance(&channel_b), 1000);
+        assert_eq!(client.get_available_balance(&channel_c), 300);
+    }
+
+    #[test]
+    fn test_claim_batch_mixed_outcomes() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+
+        let recipient = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        let good = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+        let stale = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+        let forged = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+        client.claim_payment(&stale, &200, &5, &signed_claim(&client, stale, 200, 5));
+
+        let claims = soroban_sdk::vec![
+            &env,
+            ClaimInput { channel_id: good, amount: 400, nonce: 1, signature: signed_claim(&client, good, 400, 1) },
+            // Nonce not above the one already used
+            ClaimInput { channel_id: stale, amount: 300, nonce: 5, signature: signed_claim(&client, stale, 300, 5) },
+            // Signature is not canonically encoded
+            ClaimInput { channel_id: forged, amount: 900, nonce: 1, signature: BytesN::from_array(&env, &[0xff; 64]) },
+            // Unknown channel
+            ClaimInput { channel_id: 99, amount: 100, nonce: 1, signature: signed_claim(&client, 99, 100, 1) },
+            // A second, later claim on a channel settled earlier in the batch
+            ClaimInput { channel_id: good, amount: 600, nonce: 2, signature: signed_claim(&client, good, 600, 2) },
+        ];
+        let outcomes = client.claim_batch(&claims);
+
+        let expected = [
+            (good, true, 0),
+            (stale, false, Error::InvalidNonce as u32),
+            (forged, false, Error::InvalidSignature as u32),
+            (99, false, Error::NotFound as u32),
+            (good, true, 0),
+        ];
+        assert_eq!(outcomes.len(), expected.len() as u32);
+        for (i, (channel_id, settled, error)) in expected.into_iter().enumerate() {
+            assert_eq!(outcomes.get(i as u32).unwrap(), ClaimOutcome { channel_id, settled, error });
+        }
+
+        assert_eq!(client.get_available_balance(&good), 400);
+        assert_eq!(client.get_available_balance(&stale), 800);
+        assert_eq!(client.get_available_balance(&forged), 1000);
+    }
+
+    /// Recipient signature over a top-up on the channels contract behind `client`
+    fn signed_funding(
+        client: &PaymentChannelsContractClient,
+        channel_id: u64,
+        amount: i128,
+        funding_nonce: u64,
+        key: &ed25519_dalek::SigningKey,
+    ) -> [u8; 64] {
+        use ed25519_dalek::Signer;
+        let mut message = [0u8; 72];
+        funding_message(&client.env, &client.address, channel_id, amount, funding_nonce).copy_into_slice(&mut message);
+        key.sign(&message).to_bytes()
+    }
+
+    #[test]
+    fn test_fund_channel_authorized() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+
+        let recipient = Address::generate(&env);
+        let token = Address::generate(&env);
+        let recipient_key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+
+        let channel_id = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+        client.set_recipient_key(
+            &channel_id,
+            &BytesN::from_array(&env, &recipient_key.verifying_key().to_bytes()),
+        );
+
+        let signature = signed_funding(&client, channel_id, 500, 0, &recipient_key);
+        client.fund_channel_authorized(&channel_id, &500, &BytesN::from_array(&env, &signature));
+
+        let channel = client.get_channel(&channel_id).unwrap();
+        assert_eq!(channel.balance, 1500);
+        assert_eq!(channel.funding_nonce, 1);
+    }
+
+    #[test]
+    fn test_fund_channel_authorized_rejects_replay() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+        let other_id = env.register_contract(None, PaymentChannelsContract);
+        let other = PaymentChannelsContractClient::new(&env, &other_id);
+
+        let recipient = Address::generate(&env);
+        let token = Address::generate(&env);
+        let recipient_key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let recipient_public = BytesN::from_array(&env, &recipient_key.verifying_key().to_bytes());
+
+        let channel_id = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+        client.set_recipient_key(&channel_id, &recipient_public);
+        let other_channel = other.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+        other.set_recipient_key(&other_channel, &recipient_public);
+
+        let signature = BytesN::from_array(&env, &signed_funding(&client, channel_id, 500, 0, &recipient_key));
+        client.fund_channel_authorized(&channel_id, &500, &signature);
+
+        // The same signature can neither top up again nor fund the same channel id elsewhere
+        assert!(client.try_fund_channel_authorized(&channel_id, &500, &signature).is_err());
+        assert!(other.try_fund_channel_authorized(&other_channel, &500, &signature).is_err());
+        assert_eq!(client.get_channel(&channel_id).unwrap().balance, 1500);
+        assert_eq!(other.get_channel(&other_channel).unwrap().balance, 1000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_fund_channel_authorized_rejects_bad_signature() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+
+        let recipient = Address::generate(&env);
+        let token = Address::generate(&env);
+        let recipient_key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+
+        let channel_id = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+        client.set_recipient_key(
+            &channel_id,
+            &BytesN::from_array(&env, &recipient_key.verifying_key().to_bytes()),
+        );
+
+        // Signed for a different amount than is being funded
+        let signature = signed_funding(&client, channel_id, 100, 0, &recipient_key);
+        client.fund_channel_authorized(&channel_id, &500, &BytesN::from_array(&env, &signature));
+    }
+
+    #[test]
+    fn test_withdraw_unclaimed() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+
+        let recipient = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        let channel_id = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+        client.claim_payment(&channel_id, &300, &1, &signed_claim(&client, channel_id, 300, 1));
+
+        // Everything above the 300 already claimed is withdrawable
+        client.withdraw_unclaimed(&channel_id, &700);
+        let channel = client.get_channel(&channel_id).unwrap();
+        assert_eq!(channel.balance, 300);
+        assert_eq!(client.get_available_balance(&channel_id), 0);
+
+        let result = client.try_withdraw_unclaimed(&channel_id, &1);
+        assert_eq!(result, Err(Ok(Error::InsufficientBalance)));
+    }
+
+    #[test]
+    fn test_withdraw_then_close_pays_nothing_twice() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+        let token_id = env.register_contract(None, RecordingToken);
+        let token = RecordingTokenClient::new(&env, &token_id);
+
+        let recipient = Address::generate(&env);
+
+        let channel_id = client.create_channel(&recipient, &token_id, &1000, &10, &100, &sender_public_key(&env), &None);
+        client.claim_payment(&channel_id, &300, &1, &signed_claim(&client, channel_id, 300, 1));
+        client.withdraw_unclaimed(&channel_id, &700);
+
+        env.ledger().with_mut(|li| li.sequence_number += 20);
+        client.close_unilateral(&channel_id);
+
+        // Funding, the claim and the withdrawal; the close has nothing left to move
+        let transfers = token.transfers();
+        assert_eq!(transfers.len(), 3);
+        assert_eq!(transfers.get(1).unwrap(), (recipient, 300));
+        assert_eq!(transfers.get(2).unwrap(), (client.get_channel(&channel_id).unwrap().sender, 700));
+    }
+
+    #[test]
+    fn test_pause_blocks_creation_only() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let recipient = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        let channel_id = client.create_channel(&recipient, &token, &1000, &10, &100, &sender_public_key(&env), &None);
+        client.set_paused(&true);
+
+        let result = client.try_create_channel(&recipient, &token, &1000, &10, &100, &sender_public_key(&env), &None);
+        assert_eq!(result, Err(Ok(Error::Paused)));
+
+        client.claim_payment(&channel_id, &400, &1, &signed_claim(&client, channel_id, 400, 1));
+        env.ledger().with_mut(|li| li.sequence_number += 20);
+        client.close_unilateral(&channel_id);
+        assert_eq!(client.get_channel(&channel_id).unwrap().status, ChannelStatus::Closed);
+    }
+
+    #[test]
+    fn test_memo_and_sender_index() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+
+        let recipient = Address::generate(&env);
+        let token = Address::generate(&env);
+        let invoice = BytesN::from_array(&env, &[5u8; 32]);
+
+        let first = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &Some(invoice.clone()));
+        let second = client.create_channel(&recipient, &token, &500, &1000, &100, &sender_public_key(&env), &None);
+
+        assert_eq!(client.get_channel(&first).unwrap().memo, Some(invoice));
+        assert_eq!(client.get_channel(&second).unwrap().memo, None);
+
+        let sender = client.get_channel(&first).unwrap().sender;
+        let channels = client.get_channels_by_sender(&sender);
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels.get(0).unwrap(), first);
+        assert_eq!(channels.get(1).unwrap(), second);
+    }
+
+    #[test]
+    fn test_challenge_period_counts_ledgers() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+
+        let recipient = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        let channel_id = client.create_channel(&recipient, &token, &1000, &10, &100, &sender_public_key(&env), &None);
+        client.dispute_claim(&channel_id);
+        let disputed_at_ledger = client.get_channel(&channel_id).unwrap().disputed_at_ledger.unwrap();
+        assert_eq!(disputed_at_ledger, env.ledger().sequence());
+
+        // Timestamps are irrelevant; only the sequence gates the close
+        env.ledger().with_mut(|li| {
+            li.sequence_number = disputed_at_ledger + 99;
+            li.timestamp += 1_000_000;
+        });
+        assert_eq!(client.try_close_unilateral(&channel_id), Err(Ok(Error::ChallengePeriodActive)));
+
+        env.ledger().with_mut(|li| li.sequence_number = disputed_at_ledger + 100);
+        client.close_unilateral(&channel_id);
+        assert_eq!(client.get_channel(&channel_id).unwrap().status, ChannelStatus::Closed);
+    }
+
+    #[test]
+    fn test_dispute_state_overrides_with_higher_nonce() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+
+        let recipient = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        let channel_id = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+        client.claim_payment(&channel_id, &300, &1, &signed_claim(&client, channel_id, 300, 1));
+
+        let signature = signed_claim(&client, channel_id, 600, 2);
+        let result = client.try_submit_dispute_state(&channel_id, &600, &2, &signature);
+        assert_eq!(result, Err(Ok(Error::NoDispute)));
+
+        client.dispute_claim(&channel_id);
+        client.submit_dispute_state(&channel_id, &600, &2, &signature);
+
+        let channel = client.get_channel(&channel_id).unwrap();
+        assert_eq!(channel.claimed, 600);
+        assert_eq!(channel.nonce, 2);
+        assert_eq!(channel.status, ChannelStatus::Disputed);
+
+        let result = client.try_submit_dispute_state(&channel_id, &700, &2, &signed_claim(&client, channel_id, 700, 2));
+        assert_eq!(result, Err(Ok(Error::InvalidNonce)));
+
+        env.ledger().with_mut(|li| li.sequence_number += 100);
+        let result = client.try_submit_dispute_state(&channel_id, &700, &3, &signed_claim(&client, channel_id, 700, 3));
+        assert_eq!(result, Err(Ok(Error::ChallengePeriodEnded)));
+    }
+
+    /// Token stub that records each outgoing transfer as (to, amount)
+    #[contract]
+    pub struct RecordingToken;
+
+    #[contractimpl]
+    impl RecordingToken {
+        pub fn transfer(env: Env, _from: Address, to: Address, amount: i128) {
+            let mut log: Vec<(Address, i128)> = env.storage().instance().get(&symbol_short!("log")).unwrap_or(vec![&env]);
+            log.push_back((to, amount));
+            env.storage().instance().set(&symbol_short!("log"), &log);
+        }
+
+        pub fn transfers(env: Env) -> Vec<(Address, i128)> {
+            env.storage().instance().get(&symbol_short!("log")).unwrap_or(vec![&env])
+        }
+    }
+
+    #[test]
+    fn test_dispute_close_pays_only_unpaid_claim() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+        let token_id = env.register_contract(None, RecordingToken);
+        let token = RecordingTokenClient::new(&env, &token_id);
+
+        let recipient = Address::generate(&env);
+
+        let channel_id = client.create_channel(&recipient, &token_id, &1000, &10, &100, &sender_public_key(&env), &None);
+        client.claim_payment(&channel_id, &300, &1, &signed_claim(&client, channel_id, 300, 1));
+
+        client.dispute_claim(&channel_id);
+        client.submit_dispute_state(&channel_id, &600, &2, &signed_claim(&client, channel_id, 600, 2));
+        env.ledger().with_mut(|li| li.sequence_number += 100);
+        client.close_unilateral(&channel_id);
+
+        let channel = client.get_channel(&channel_id).unwrap();
+        assert_eq!(channel.paid_out, 600);
+        let transfers = token.transfers();
+        // Funding, the 300 claim, then 300 more to the recipient and 400 back to the sender
+        assert_eq!(transfers.len(), 4);
+        assert_eq!(transfers.get(1).unwrap(), (recipient.clone(), 300));
+        assert_eq!(transfers.get(2).unwrap(), (recipient, 300));
+        assert_eq!(transfers.get(3).unwrap(), (channel.sender, 400));
+    }
+
+    #[test]
+    fn test_cooperative_close_nets_paid_claims() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+        let token_id = env.register_contract(None, RecordingToken);
+        let token = RecordingTokenClient::new(&env, &token_id);
+
+        let recipient = Address::generate(&env);
+
+        let channel_id = client.create_channel(&recipient, &token_id, &1000, &1000, &100, &sender_public_key(&env), &None);
+        client.claim_payment(&channel_id, &300, &1, &signed_claim(&client, channel_id, 300, 1));
+
+        assert_eq!(client.try_close_cooperative(&channel_id, &200), Err(Ok(Error::InvalidAmount)));
+        client.close_cooperative(&channel_id, &500);
+
+        let transfers = token.transfers();
+        assert_eq!(transfers.len(), 4);
+        assert_eq!(transfers.get(2).unwrap(), (recipient, 200));
+        assert_eq!(transfers.get(3).unwrap(), (client.get_channel(&channel_id).unwrap().sender, 500));
+    }
+
+    #[test]
+    fn test_claim_rejects_tampered_amount() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register_contract(None, PaymentChannelsContract);
+        let client = PaymentChannelsContractClient::new(&env, &contract_id);
+
+        let recipient = Address::generate(&env);
+        let token = Address::generate(&env);
+
+        let channel_id = client.create_channel(&recipient, &token, &1000, &1000, &100, &sender_public_key(&env), &None);
+
+        // Signed for 300, claimed as 900: the host rejects the signature
+        let result = client.try_claim_payment(&channel_id, &900, &1, &signed_claim(&client, channel_id, 300, 1));
+        assert!(matches!(result, Err(Err(_))));
+        assert_eq!(client.get_available_balance(&channel_id), 1000);
+
+        // A malformed signature is rejected before reaching the host
+        let result = client.try_claim_payment(&channel_id, &900, &1, &BytesN::from_array(&env, &[0xff; 64]));
+        assert_eq!(result, Err(Ok(Error::InvalidSignature)));
     }
 }
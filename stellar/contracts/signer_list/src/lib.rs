@@ -1,6 +1,6 @@
 #\![no_std]
 
-use soroban_sdk::{contract, contracterror, contractimpl, contracttype, Address, BytesN, Env, Vec, vec};
+use soroban_sdk::{contract, contracterror, contractimpl, contracttype, token, xdr::ToXdr, Address, BytesN, Env, Vec, vec};
 
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -12,16 +12,20 @@ pub struct SignerList { pub owner: Address, pub signers: Vec<SignerEntry>, pub q
 
 #[contracttype]
 #[derive(Clone)]
-pub enum DataKey { SignerList(Address), PendingTx(BytesN<32>), Admin }
+pub enum DataKey { SignerList(Address), PendingTx(Address, BytesN<32>), Proposal(BytesN<32>), NextProposalNonce, Admin, TimelockPeriod(Address) }
 
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct PendingTransaction { pub tx_hash: BytesN<32>, pub signers: Vec<Address>, pub total_weight: u32, pub executed: bool }
+pub struct PendingTransaction { pub tx_hash: BytesN<32>, pub signers: Vec<Address>, pub total_weight: u32, pub executed: bool, pub execute_after: Option<u32> }
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Proposal { pub owner: Address, pub target: Address, pub token: Address, pub amount: i128, pub created_at: u64, pub executed: bool }
 
 #[contracterror]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 #[repr(u32)]
-pub enum Error { NotFound = 1, Unauthorized = 2, InvalidWeight = 3, InvalidQuorum = 4, AlreadySigned = 5, InsufficientSignatures = 6, AlreadyExecuted = 7 }
+pub enum Error { NotFound = 1, Unauthorized = 2, InvalidWeight = 3, InvalidQuorum = 4, AlreadySigned = 5, InsufficientSignatures = 6, AlreadyExecuted = 7, InvalidAmount = 8, DuplicateSigner = 9, TimelockActive = 10, TimelockNotStarted = 11 }
 
 #[contract]
 pub struct SignerListContract;
@@ -33,6 +37,9 @@ impl SignerListContract {
     pub fn create_signer_list(env: Env, signers: Vec<SignerEntry>, quorum: u32) -> Result<(), Error> {
         let owner = env.invoker(); owner.require_auth();
         if quorum == 0 { return Err(Error::InvalidQuorum); }
+        for (i, entry) in signers.iter().enumerate() {
+            if signers.iter().take(i).any(|earlier| earlier.signer == entry.signer) { return Err(Error::DuplicateSigner); }
+        }
         let total_weight: u32 = signers.iter().map(|s| s.weight).sum();
         if quorum > total_weight { return Err(Error::InvalidQuorum); }
         let list = SignerList { owner: owner.clone(), signers, quorum, created_at: env.ledger().timestamp(), updated_at: env.ledger().timestamp() };
@@ -46,26 +53,405 @@ impl SignerListContract {
         let owner = env.invoker(); owner.require_auth();
         let mut list: SignerList = env.storage().persistent().get(&DataKey::SignerList(owner.clone())).ok_or(Error::NotFound)?;
         if weight == 0 { return Err(Error::InvalidWeight); }
+        if list.signers.iter().any(|entry| entry.signer == signer) { return Err(Error::DuplicateSigner); }
         list.signers.push_back(SignerEntry { signer, weight });
         list.updated_at = env.ledger().timestamp();
         env.storage().persistent().set(&DataKey::SignerList(owner.clone()), &list);
         Ok(())
     }
 
+    /// Changes an existing signer's weight. Fails with `InvalidQuorum` if the new total could no longer reach quorum.
+    pub fn update_signer_weight(env: Env, signer: Address, new_weight: u32) -> Result<(), Error> {
+        let owner = env.invoker(); owner.require_auth();
+        let mut list: SignerList = env.storage().persistent().get(&DataKey::SignerList(owner.clone())).ok_or(Error::NotFound)?;
+        if new_weight == 0 { return Err(Error::InvalidWeight); }
+        let index = list.signers.iter().position(|entry| entry.signer == signer).ok_or(Error::NotFound)? as u32;
+        list.signers.set(index, SignerEntry { signer: signer.clone(), weight: new_weight });
+        let total_weight: u32 = list.signers.iter().map(|s| s.weight).sum();
+        if list.quorum > total_weight { return Err(Error::InvalidQuorum); }
+        list.updated_at = env.ledger().timestamp();
+        env.storage().persistent().set(&DataKey::SignerList(owner.clone()), &list);
+        env.events().publish((symbol_short!("weight"), owner, signer), new_weight);
+        Ok(())
+    }
+
+    /// Sets how many ledgers a transaction must wait after first reaching quorum before it can execute.
+    pub fn set_timelock_period(env: Env, timelock_period: u32) -> Result<(), Error> {
+        let owner = env.invoker(); owner.require_auth();
+        if !env.storage().persistent().has(&DataKey::SignerList(owner.clone())) { return Err(Error::NotFound); }
+        env.storage().persistent().set(&DataKey::TimelockPeriod(owner.clone()), &timelock_period);
+        env.events().publish((symbol_short!("timelock"), owner), timelock_period);
+        Ok(())
+    }
+
     pub fn sign_transaction(env: Env, owner: Address, tx_hash: BytesN<32>) -> Result<bool, Error> {
         let signer = env.invoker(); signer.require_auth();
         let list: SignerList = env.storage().persistent().get(&DataKey::SignerList(owner.clone())).ok_or(Error::NotFound)?;
-        let signer_weight = list.signers.iter().find(|entry| entry.signer == signer).map(|entry| entry.weight).ok_or(Error::Unauthorized)?;
-        let mut pending: PendingTransaction = env.storage().persistent().get(&DataKey::PendingTx(tx_hash.clone())).unwrap_or(PendingTransaction { tx_hash: tx_hash.clone(), signers: vec\![&env], total_weight: 0, executed: false });
+        if !list.signers.iter().any(|entry| entry.signer == signer) { return Err(Error::Unauthorized); }
+        let mut pending: PendingTransaction = env.storage().persistent().get(&DataKey::PendingTx(owner.clone(), tx_hash.clone())).unwrap_or(PendingTransaction { tx_hash: tx_hash.clone(), signers: vec\![&env], total_weight: 0, executed: false, execute_after: None });
         if pending.executed { return Err(Error::AlreadyExecuted); }
         for existing_signer in pending.signers.iter() { if existing_signer == signer { return Err(Error::AlreadySigned); } }
         pending.signers.push_back(signer.clone());
-        pending.total_weight += signer_weight;
-        env.storage().persistent().set(&DataKey::PendingTx(tx_hash.clone()), &pending);
+        pending.total_weight = Self::signed_weight(&list, &pending.signers);
         let ready = pending.total_weight >= list.quorum;
+        if ready && pending.execute_after.is_none() {
+            let period: u32 = env.storage().persistent().get(&DataKey::TimelockPeriod(owner.clone())).unwrap_or(0);
+            pending.execute_after = Some(env.ledger().sequence() + period);
+        }
+        env.storage().persistent().set(&DataKey::PendingTx(owner.clone(), tx_hash.clone()), &pending);
         if ready { env.events().publish((symbol_short\!("ready"), tx_hash), pending.total_weight); }
         Ok(ready)
     }
 
+    /// Starts the timelock on a transaction that reached quorum without a signature starting it, such
+    /// as through a weight change. Anyone may call it; it returns the ledger the transaction unlocks at.
+    /// A failed `execute_proposal` cannot start the clock itself because its writes are rolled back.
+    pub fn start_timelock(env: Env, owner: Address, tx_hash: BytesN<32>) -> Result<u32, Error> {
+        let list: SignerList = env.storage().persistent().get(&DataKey::SignerList(owner.clone())).ok_or(Error::NotFound)?;
+        let pending_key = DataKey::PendingTx(owner.clone(), tx_hash.clone());
+        let mut pending: PendingTransaction = env.storage().persistent().get(&pending_key).ok_or(Error::InsufficientSignatures)?;
+        if pending.executed { return Err(Error::AlreadyExecuted); }
+        if Self::signed_weight(&list, &pending.signers) < list.quorum { return Err(Error::InsufficientSignatures); }
+        if let Some(execute_after) = pending.execute_after { return Ok(execute_after); }
+        let execute_after = env.ledger().sequence() + Self::get_timelock_period(env.clone(), owner.clone());
+        pending.execute_after = Some(execute_after);
+        env.storage().persistent().set(&pending_key, &pending);
+        env.events().publish((symbol_short!("ready"), tx_hash), pending.total_weight);
+        Ok(execute_after)
+    }
+
+    /// Records a token transfer from the list owner to `target`. The returned hash is signed
+    /// through `sign_transaction` like any other pending transaction.
+    pub fn create_proposal(env: Env, target: Address, token: Address, amount: i128) -> Result<BytesN<32>, Error> {
+        let owner = env.invoker(); owner.require_auth();
+        if !env.storage().persistent().has(&DataKey::SignerList(owner.clone())) { return Err(Error::NotFound); }
+        if amount <= 0 { return Err(Error::InvalidAmount); }
+        let nonce: u64 = env.storage().instance().get(&DataKey::NextProposalNonce).unwrap_or(0);
+        env.storage().instance().set(&DataKey::NextProposalNonce, &(nonce + 1));
+        let proposal = Proposal { owner: owner.clone(), target, token, amount, created_at: env.ledger().timestamp(), executed: false };
+        let hash = env.crypto().sha256(&(proposal.clone(), nonce).to_xdr(&env));
+        env.storage().persistent().set(&DataKey::Proposal(hash.clone()), &proposal);
+        env.storage().persistent().extend_ttl(&DataKey::Proposal(hash.clone()), 518400, 518400);
+        env.events().publish((symbol_short!("proposed"), owner), hash.clone());
+        Ok(hash)
+    }
+
+    /// Executes a proposal once its signatures reach the owner's quorum and the owner's timelock has
+    /// elapsed. Fails with `TimelockNotStarted` if quorum was reached without starting the timelock;
+    /// call `start_timelock` first. The transfer is drawn from the owner's allowance to this contract,
+    /// so the owner must have approved it.
+    pub fn execute_proposal(env: Env, proposal_hash: BytesN<32>) -> Result<(), Error> {
+        let mut proposal: Proposal = env.storage().persistent().get(&DataKey::Proposal(proposal_hash.clone())).ok_or(Error::NotFound)?;
+        if proposal.executed { return Err(Error::AlreadyExecuted); }
+        let list: SignerList = env.storage().persistent().get(&DataKey::SignerList(proposal.owner.clone())).ok_or(Error::NotFound)?;
+        let pending_key = DataKey::PendingTx(proposal.owner.clone(), proposal_hash.clone());
+        let mut pending: PendingTransaction = env.storage().persistent().get(&pending_key).ok_or(Error::InsufficientSignatures)?;
+        if pending.executed { return Err(Error::AlreadyExecuted); }
+        if Self::signed_weight(&list, &pending.signers) < list.quorum { return Err(Error::InsufficientSignatures); }
+        if pending.execute_after.is_none() && Self::get_timelock_period(env.clone(), proposal.owner.clone()) > 0 { return Err(Error::TimelockNotStarted); }
+        if !Self::timelock_cleared(&env, &proposal.owner, &pending) { return Err(Error::TimelockActive); }
+
+        proposal.executed = true;
+        pending.executed = true;
+        env.storage().persistent().set(&DataKey::Proposal(proposal_hash.clone()), &proposal);
+        env.storage().persistent().set(&pending_key, &pending);
+
+        token::Client::new(&env, &proposal.token).transfer_from(&env.current_contract_address(), &proposal.owner, &proposal.target, &proposal.amount);
+        env.events().publish((symbol_short!("executed"), proposal.owner, proposal.target), (proposal_hash, proposal.amount));
+        Ok(())
+    }
+
+    /// Whether `tx_hash` has reached `owner`'s quorum, has cleared its timelock and is not yet executed.
+    /// Other contracts call this to gate actions on signer-list approval.
+    pub fn is_approved(env: Env, owner: Address, tx_hash: BytesN<32>) -> bool {
+        let Some(list) = Self::get_signer_list(env.clone(), owner.clone()) else { return false; };
+        let Some(pending) = env.storage().persistent().get::<_, PendingTransaction>(&DataKey::PendingTx(owner, tx_hash)) else { return false; };
+        !pending.executed && Self::signed_weight(&list, &pending.signers) >= list.quorum && Self::timelock_cleared(&env, &list.owner, &pending)
+    }
+
+    /// Weight of the recorded signatures under the list as it stands now, so removed or re-weighted
+    /// signers count at their current weight
+    fn signed_weight(list: &SignerList, signers: &Vec<Address>) -> u32 {
+        list.signers.iter().filter(|entry| signers.contains(&entry.signer)).map(|entry| entry.weight).sum()
+    }
+
+    /// A transaction that reached quorum only through a weight change has no `execute_after` yet;
+    /// it stays locked until a signature or `start_timelock` starts the clock, unless the owner has no timelock
+    fn timelock_cleared(env: &Env, owner: &Address, pending: &PendingTransaction) -> bool {
+        match pending.execute_after {
+            Some(execute_after) => env.ledger().sequence() >= execute_after,
+            None => Self::get_timelock_period(env.clone(), owner.clone()) == 0,
+        }
+    }
+
     pub fn get_signer_list(env: Env, owner: Address) -> Option<SignerList> { env.storage().persistent().get(&DataKey::SignerList(owner)) }
+
+    pub fn get_signers(env: Env, owner: Address) -> Vec<SignerEntry> { Self::get_signer_list(env.clone(), owner).map(|list| list.signers).unwrap_or(vec![&env]) }
+
+    pub fn get_timelock_period(env: Env, owner: Address) -> u32 { env.storage().persistent().get(&DataKey::TimelockPeriod(owner)).unwrap_or(0) }
+
+    pub fn get_proposal(env: Env, proposal_hash: BytesN<32>) -> Option<Proposal> { env.storage().persistent().get(&DataKey::Proposal(proposal_hash)) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use soroban_sdk::{testutils::{Address as _, Ledger}, Address, Env};
+
+    #[test]
+    fn test_proposal_executes_only_after_quorum() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, SignerListContract);
+        let client = SignerListContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let owner = Address::generate(&env);
+        let signer = Address::generate(&env);
+        let target = Address::generate(&env);
+        client.create_signer_list(&vec![&env, SignerEntry { signer: signer.clone(), weight: 2 }], &2);
+
+        let asset = env.register_stellar_asset_contract(admin.clone());
+        token::StellarAssetClient::new(&env, &asset).mint(&owner, &1000);
+        let token_client = token::Client::new(&env, &asset);
+        token_client.approve(&owner, &contract_id, &400, &1000);
+
+        assert_eq!(client.try_create_proposal(&target, &asset, &0), Err(Ok(Error::InvalidAmount)));
+        let hash = client.create_proposal(&target, &asset, &400);
+        assert_eq!(client.get_proposal(&hash).unwrap().amount, 400);
+
+        assert_eq!(client.try_execute_proposal(&hash), Err(Ok(Error::InsufficientSignatures)));
+        assert_eq!(token_client.balance(&target), 0);
+
+        assert_eq!(client.sign_transaction(&owner, &hash), true);
+        client.execute_proposal(&hash);
+        assert_eq!(token_client.balance(&target), 400);
+        assert_eq!(token_client.balance(&owner), 600);
+        assert_eq!(client.get_proposal(&hash).unwrap().executed, true);
+
+        assert_eq!(client.try_execute_proposal(&hash), Err(Ok(Error::AlreadyExecuted)));
+    }
+
+    #[test]
+    fn test_duplicate_signers_rejected() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, SignerListContract);
+        let client = SignerListContractClient::new(&env, &contract_id);
+
+        let signer = Address::generate(&env);
+        let entry = SignerEntry { signer: signer.clone(), weight: 1 };
+        assert_eq!(client.try_create_signer_list(&vec![&env, entry.clone(), entry.clone()], &1), Err(Ok(Error::DuplicateSigner)));
+
+        client.create_signer_list(&vec![&env, entry], &1);
+        assert_eq!(client.try_add_signer(&signer, &3), Err(Ok(Error::DuplicateSigner)));
+    }
+
+    #[test]
+    fn test_update_weight_and_enumerate() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, SignerListContract);
+        let client = SignerListContractClient::new(&env, &contract_id);
+
+        let owner = Address::generate(&env);
+        let signer = Address::generate(&env);
+        let other = Address::generate(&env);
+        client.create_signer_list(&vec![&env, SignerEntry { signer: signer.clone(), weight: 1 }, SignerEntry { signer: other.clone(), weight: 1 }], &2);
+
+        // One weight-1 signature is short of quorum
+        let first = BytesN::from_array(&env, &[1u8; 32]);
+        assert_eq!(client.sign_transaction(&owner, &first), false);
+
+        client.update_signer_weight(&signer, &2);
+        let second = BytesN::from_array(&env, &[2u8; 32]);
+        assert_eq!(client.sign_transaction(&owner, &second), true);
+
+        let signers = client.get_signers(&owner);
+        assert_eq!(signers.len(), 2);
+        assert_eq!(signers.get(0).unwrap(), SignerEntry { signer: signer.clone(), weight: 2 });
+        assert_eq!(signers.get(1).unwrap(), SignerEntry { signer: other.clone(), weight: 1 });
+
+        // Lowering back to 1 keeps total weight at the quorum of 2
+        client.update_signer_weight(&signer, &1);
+        assert_eq!(client.try_update_signer_weight(&signer, &0), Err(Ok(Error::InvalidWeight)));
+        assert_eq!(client.try_update_signer_weight(&Address::generate(&env), &1), Err(Ok(Error::NotFound)));
+        assert_eq!(client.get_signers(&Address::generate(&env)).len(), 0);
+    }
+
+    #[test]
+    fn test_weight_update_cannot_break_quorum() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, SignerListContract);
+        let client = SignerListContractClient::new(&env, &contract_id);
+
+        let owner = Address::generate(&env);
+        let signer = Address::generate(&env);
+        client.create_signer_list(&vec![&env, SignerEntry { signer: signer.clone(), weight: 2 }], &2);
+
+        assert_eq!(client.try_update_signer_weight(&signer, &1), Err(Ok(Error::InvalidQuorum)));
+        assert_eq!(client.get_signers(&owner).get(0).unwrap().weight, 2);
+    }
+
+    #[test]
+    fn test_timelock_delays_execution() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, SignerListContract);
+        let client = SignerListContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let owner = Address::generate(&env);
+        let signer = Address::generate(&env);
+        let target = Address::generate(&env);
+        client.create_signer_list(&vec![&env, SignerEntry { signer: signer.clone(), weight: 1 }], &1);
+        client.set_timelock_period(&100);
+        assert_eq!(client.get_timelock_period(&owner), 100);
+
+        let asset = env.register_stellar_asset_contract(admin.clone());
+        token::StellarAssetClient::new(&env, &asset).mint(&owner, &1000);
+        let token_client = token::Client::new(&env, &asset);
+        token_client.approve(&owner, &contract_id, &400, &1000);
+
+        env.ledger().with_mut(|li| li.sequence_number = 50);
+        let hash = client.create_proposal(&target, &asset, &400);
+        assert_eq!(client.sign_transaction(&owner, &hash), true);
+
+        env.ledger().with_mut(|li| li.sequence_number = 149);
+        assert_eq!(client.try_execute_proposal(&hash), Err(Ok(Error::TimelockActive)));
+        assert_eq!(client.is_approved(&owner, &hash), false);
+        assert_eq!(token_client.balance(&target), 0);
+
+        env.ledger().with_mut(|li| li.sequence_number = 150);
+        assert_eq!(client.is_approved(&owner, &hash), true);
+        client.execute_proposal(&hash);
+        assert_eq!(token_client.balance(&target), 400);
+        assert_eq!(client.is_approved(&owner, &hash), false);
+    }
+
+    #[test]
+    fn test_foreign_list_cannot_approve_proposal() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, SignerListContract);
+        let client = SignerListContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let owner = Address::generate(&env);
+        let attacker = Address::generate(&env);
+        let target = Address::generate(&env);
+        client.create_signer_list(&vec![&env, SignerEntry { signer: Address::generate(&env), weight: 1 }], &1);
+
+        let asset = env.register_stellar_asset_contract(admin.clone());
+        token::StellarAssetClient::new(&env, &asset).mint(&owner, &1000);
+        token::Client::new(&env, &asset).approve(&owner, &contract_id, &400, &1000);
+        let hash = client.create_proposal(&target, &asset, &400);
+
+        // Signatures collected under another owner's list do not count toward this proposal
+        client.create_signer_list(&vec![&env, SignerEntry { signer: attacker.clone(), weight: 5 }], &1);
+        assert_eq!(client.sign_transaction(&attacker, &hash), true);
+        assert_eq!(client.try_execute_proposal(&hash), Err(Ok(Error::InsufficientSignatures)));
+        assert_eq!(token::Client::new(&env, &asset).balance(&target), 0);
+    }
+
+    #[test]
+    fn test_approval_is_per_owner_and_tracks_weights() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, SignerListContract);
+        let client = SignerListContractClient::new(&env, &contract_id);
+
+        let owner = Address::generate(&env);
+        let attacker = Address::generate(&env);
+        let signer = Address::generate(&env);
+        let other = Address::generate(&env);
+        let hash = BytesN::from_array(&env, &[7u8; 32]);
+
+        client.create_signer_list(&vec![&env, SignerEntry { signer: attacker.clone(), weight: 5 }], &1);
+        assert_eq!(client.sign_transaction(&attacker, &hash), true);
+        assert_eq!(client.is_approved(&attacker, &hash), true);
+        assert_eq!(client.is_approved(&owner, &hash), false);
+
+        client.create_signer_list(&vec![&env, SignerEntry { signer: signer.clone(), weight: 2 }, SignerEntry { signer: other.clone(), weight: 1 }], &2);
+        assert_eq!(client.sign_transaction(&owner, &hash), true);
+        assert_eq!(client.is_approved(&owner, &hash), true);
+
+        // Lowering the signer's weight drops the recorded signature below quorum
+        client.update_signer_weight(&signer, &1);
+        assert_eq!(client.is_approved(&owner, &hash), false);
+    }
+
+    #[test]
+    fn test_weight_change_quorum_needs_timelock_start() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, SignerListContract);
+        let client = SignerListContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let owner = Address::generate(&env);
+        let signer = Address::generate(&env);
+        let other = Address::generate(&env);
+        let target = Address::generate(&env);
+        client.create_signer_list(&vec![&env, SignerEntry { signer: signer.clone(), weight: 1 }, SignerEntry { signer: other.clone(), weight: 1 }], &2);
+        client.set_timelock_period(&100);
+
+        let asset = env.register_stellar_asset_contract(admin.clone());
+        token::StellarAssetClient::new(&env, &asset).mint(&owner, &1000);
+        let token_client = token::Client::new(&env, &asset);
+        token_client.approve(&owner, &contract_id, &400, &1000);
+
+        env.ledger().with_mut(|li| li.sequence_number = 50);
+        let hash = client.create_proposal(&target, &asset, &400);
+        assert_eq!(client.sign_transaction(&owner, &hash), false);
+        assert_eq!(client.try_start_timelock(&owner, &hash), Err(Ok(Error::InsufficientSignatures)));
+
+        // Raising the signer's weight reaches quorum without a signature to start the clock
+        client.update_signer_weight(&signer, &2);
+        env.ledger().with_mut(|li| li.sequence_number = 500);
+        assert_eq!(client.try_execute_proposal(&hash), Err(Ok(Error::TimelockNotStarted)));
+        assert_eq!(client.is_approved(&owner, &hash), false);
+
+        assert_eq!(client.start_timelock(&owner, &hash), 600);
+        assert_eq!(client.start_timelock(&owner, &hash), 600);
+        env.ledger().with_mut(|li| li.sequence_number = 599);
+        assert_eq!(client.try_execute_proposal(&hash), Err(Ok(Error::TimelockActive)));
+
+        env.ledger().with_mut(|li| li.sequence_number = 600);
+        assert_eq!(client.is_approved(&owner, &hash), true);
+        client.execute_proposal(&hash);
+        assert_eq!(token_client.balance(&target), 400);
+        assert_eq!(client.try_start_timelock(&owner, &hash), Err(Ok(Error::AlreadyExecuted)));
+    }
+
+    #[test]
+    fn test_weight_change_quorum_executes_without_timelock() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, SignerListContract);
+        let client = SignerListContractClient::new(&env, &contract_id);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let owner = Address::generate(&env);
+        let signer = Address::generate(&env);
+        let other = Address::generate(&env);
+        let target = Address::generate(&env);
+        client.create_signer_list(&vec![&env, SignerEntry { signer: signer.clone(), weight: 1 }, SignerEntry { signer: other.clone(), weight: 1 }], &2);
+
+        let asset = env.register_stellar_asset_contract(admin.clone());
+        token::StellarAssetClient::new(&env, &asset).mint(&owner, &1000);
+        token::Client::new(&env, &asset).approve(&owner, &contract_id, &400, &1000);
+
+        let hash = client.create_proposal(&target, &asset, &400);
+        assert_eq!(client.sign_transaction(&owner, &hash), false);
+        client.update_signer_weight(&signer, &2);
+        client.execute_proposal(&hash);
+        assert_eq!(token::Client::new(&env, &asset).balance(&target), 400);
+    }
 }
@@ -14,6 +14,14 @@ use soroban_sdk::{
     Address, Env, Map, Vec,
 };
 
+/// Path length used until the admin configures one
+const DEFAULT_MAX_PATH_LENGTH: u32 = 6;
+/// Upper bound on the configurable path length, keeping per-call work bounded
+const MAX_PATH_LENGTH_CAP: u32 = 12;
+
+/// Seconds in the 365-day year used for simple interest
+const SECONDS_PER_YEAR: u64 = 31_536_000;
+
 /// Trust line data structure
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -36,6 +44,12 @@ pub struct TrustLine {
     pub quality_in: u32,
     /// Quality out (1000 = 100%)
     pub quality_out: u32,
+    /// Frozen lines reject all payments until unfrozen
+    pub frozen: bool,
+    /// Annual simple interest on the outstanding balance (100 = 1%)
+    pub interest_rate_bps: u32,
+    /// Ledger timestamp interest was last accrued to
+    pub last_accrued: u64,
 }
 
 /// Storage keys
@@ -46,6 +60,10 @@ pub enum DataKey {
     TrustLine(Address, Address, Address),
     /// Admin address
     Admin,
+    /// Net settlement obligation: (account1, account2, settlement_token)
+    Settlement(Address, Address, Address),
+    /// Maximum hops accepted by `send_through_path`
+    MaxPathLength,
 }
 
 /// Errors
@@ -75,6 +93,12 @@ pub enum Error {
     PathTooLong = 10,
     /// Invalid quality parameter
     InvalidQuality = 11,
+    /// Trust line is frozen
+    Frozen = 12,
+    /// Settlement amounts don't match the lines being netted
+    InvalidSettlement = 13,
+    /// Path revisits an account
+    InvalidPath = 14,
 }
 
 #[contract]
@@ -139,6 +163,9 @@ impl TrustLinesContract {
             allow_rippling,
             quality_in: 1000,
             quality_out: 1000,
+            frozen: false,
+            interest_rate_bps: 0,
+            last_accrued: env.ledger().timestamp(),
         };
 
         // Store with TTL extension
@@ -216,6 +243,12 @@ impl TrustLinesContract {
             .get(&key)
             .ok_or(Error::NotFound)?;
 
+        if trust_line.frozen {
+            return Err(Error::Frozen);
+        }
+
+        Self::apply_interest(&env, &mut trust_line)?;
+
         // Calculate new balance
         let new_balance = if caller == account1 {
             // Payment from account1 to account2: balance decreases
@@ -262,11 +295,25 @@ impl TrustLinesContract {
             return Err(Error::InvalidAmount);
         }
 
-        if path.len() == 0 || path.len() > 6 {
+        if path.len() == 0 || path.len() > Self::get_max_path_length(env.clone()) {
             return Err(Error::PathTooLong);
         }
 
-        // Process payment through each hop
+        // Reject cycles: no account, including the caller, may appear twice
+        for i in 0..path.len() {
+            let hop = path.get_unchecked(i);
+            if hop == caller {
+                return Err(Error::InvalidPath);
+            }
+            for j in 0..i {
+                if path.get_unchecked(j) == hop {
+                    return Err(Error::InvalidPath);
+                }
+            }
+        }
+
+        // Validate every hop first; nothing is written unless all hops pass
+        let mut updated: Vec<TrustLine> = Vec::new(&env);
         let mut current = caller.clone();
         for next in path.iter() {
             let (account1, account2) = Self::order_accounts(&current, &next);
@@ -277,6 +324,10 @@ impl TrustLinesContract {
                 .get(&key)
                 .ok_or(Error::NotFound)?;
 
+            if trust_line.frozen {
+                return Err(Error::Frozen);
+            }
+
             // Check rippling enabled (except for first and last hop)
             if current != caller && next != path.get_unchecked(path.len() - 1) {
                 if !trust_line.allow_rippling {
@@ -284,6 +335,9 @@ impl TrustLinesContract {
                 }
             }
 
+            // Bring interest up to date so it never applies retroactively to this payment
+            Self::apply_interest(&env, &mut trust_line)?;
+
             // Update balance
             let new_balance = if current == account1 {
                 trust_line.balance.checked_sub(amount).ok_or(Error::InsufficientCredit)?
@@ -303,11 +357,21 @@ impl TrustLinesContract {
             }
 
             trust_line.balance = new_balance;
-            env.storage().persistent().set(&key, &trust_line);
+            updated.push_back(trust_line);
 
             current = next.clone();
         }
 
+        // Commit all balance changes
+        for trust_line in updated.iter() {
+            let key = DataKey::TrustLine(
+                trust_line.account1.clone(),
+                trust_line.account2.clone(),
+                trust_line.asset.clone(),
+            );
+            env.storage().persistent().set(&key, &trust_line);
+        }
+
         env.events().publish(
             (symbol_short!("ripple"), caller, current),
             (amount, path.len()),
@@ -316,6 +380,224 @@ impl TrustLinesContract {
         Ok(())
     }
 
+    /// Net several asset trust lines with `counterparty` into one obligation
+    ///
+    /// `amounts[i]` is the value of the balance on `assets[i]` expressed in
+    /// `settlement_token`, using the same sign convention as `TrustLine::balance`
+    /// (positive = account2 owes account1). Both parties must authorize since the
+    /// valuations are agreed between them. Each line's balance is zeroed and the
+    /// sum of `amounts` is added to the pair's settlement obligation, so the
+    /// aggregate net is preserved.
+    pub fn settle_net(
+        env: Env,
+        counterparty: Address,
+        assets: Vec<Address>,
+        settlement_token: Address,
+        amounts: Vec<i128>,
+    ) -> Result<i128, Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+        counterparty.require_auth();
+
+        if assets.len() == 0 || assets.len() != amounts.len() {
+            return Err(Error::InvalidSettlement);
+        }
+
+        let (account1, account2) = Self::order_accounts(&caller, &counterparty);
+
+        // Validate every line before zeroing any of them
+        let mut lines: Vec<TrustLine> = Vec::new(&env);
+        let mut net: i128 = 0;
+        for i in 0..assets.len() {
+            let asset = assets.get_unchecked(i);
+            let amount = amounts.get_unchecked(i);
+
+            for j in 0..i {
+                if assets.get_unchecked(j) == asset {
+                    return Err(Error::InvalidSettlement);
+                }
+            }
+
+            let key = DataKey::TrustLine(account1.clone(), account2.clone(), asset);
+            let mut trust_line: TrustLine = env.storage()
+                .persistent()
+                .get(&key)
+                .ok_or(Error::NotFound)?;
+
+            if trust_line.frozen {
+                return Err(Error::Frozen);
+            }
+
+            Self::apply_interest(&env, &mut trust_line)?;
+
+            // Valuation must keep the direction of the debt
+            if trust_line.balance.signum() != amount.signum() {
+                return Err(Error::InvalidSettlement);
+            }
+
+            net = net.checked_add(amount).ok_or(Error::InvalidAmount)?;
+            lines.push_back(trust_line);
+        }
+
+        for mut trust_line in lines.iter() {
+            let key = DataKey::TrustLine(
+                trust_line.account1.clone(),
+                trust_line.account2.clone(),
+                trust_line.asset.clone(),
+            );
+            trust_line.balance = 0;
+            env.storage().persistent().set(&key, &trust_line);
+        }
+
+        let settlement_key = DataKey::Settlement(account1.clone(), account2.clone(), settlement_token.clone());
+        let obligation: i128 = env.storage().persistent().get(&settlement_key).unwrap_or(0);
+        let obligation = obligation.checked_add(net).ok_or(Error::InvalidAmount)?;
+        env.storage().persistent().set(&settlement_key, &obligation);
+        env.storage().persistent().extend_ttl(&settlement_key, 518400, 518400);
+
+        env.events().publish(
+            (symbol_short!("netted"), account1, account2),
+            (settlement_token, net, obligation),
+        );
+
+        Ok(net)
+    }
+
+    /// Get the outstanding settlement obligation between two accounts
+    /// (positive = account2 owes account1 after ordering)
+    pub fn get_settlement_obligation(
+        env: Env,
+        account1: Address,
+        account2: Address,
+        settlement_token: Address,
+    ) -> i128 {
+        let (acc1, acc2) = Self::order_accounts(&account1, &account2);
+        env.storage()
+            .persistent()
+            .get(&DataKey::Settlement(acc1, acc2, settlement_token))
+            .unwrap_or(0)
+    }
+
+    /// Set the annual interest rate on a trust line (both parties must authorize)
+    pub fn set_interest_rate(
+        env: Env,
+        counterparty: Address,
+        asset: Address,
+        rate_bps: u32,
+    ) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+        counterparty.require_auth();
+
+        let (account1, account2) = Self::order_accounts(&caller, &counterparty);
+        let key = DataKey::TrustLine(account1.clone(), account2.clone(), asset.clone());
+
+        let mut trust_line: TrustLine = env.storage()
+            .persistent()
+            .get(&key)
+            .ok_or(Error::NotFound)?;
+
+        // Settle interest at the old rate before switching
+        Self::apply_interest(&env, &mut trust_line)?;
+        trust_line.interest_rate_bps = rate_bps;
+        env.storage().persistent().set(&key, &trust_line);
+
+        env.events().publish(
+            (symbol_short!("rate"), account1, account2),
+            (asset, rate_bps),
+        );
+
+        Ok(())
+    }
+
+    /// Accrue simple interest on the outstanding balance since the last accrual
+    ///
+    /// Returns the interest added. The balance never grows past the debtor's
+    /// credit limit.
+    pub fn accrue_interest(
+        env: Env,
+        counterparty: Address,
+        asset: Address,
+    ) -> Result<i128, Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+
+        let (account1, account2) = Self::order_accounts(&caller, &counterparty);
+        let key = DataKey::TrustLine(account1, account2, asset);
+
+        let mut trust_line: TrustLine = env.storage()
+            .persistent()
+            .get(&key)
+            .ok_or(Error::NotFound)?;
+
+        let before = trust_line.balance;
+        Self::apply_interest(&env, &mut trust_line)?;
+        env.storage().persistent().set(&key, &trust_line);
+
+        let accrued = (trust_line.balance - before).abs();
+        if accrued > 0 {
+            env.events().publish(
+                (symbol_short!("accrued"), trust_line.account1, trust_line.account2),
+                (trust_line.asset, accrued),
+            );
+        }
+
+        Ok(accrued)
+    }
+
+    // Helper: Add interest accrued since `last_accrued` to the balance, capped at the limit
+    fn apply_interest(env: &Env, trust_line: &mut TrustLine) -> Result<(), Error> {
+        let now = env.ledger().timestamp();
+        let elapsed = now.saturating_sub(trust_line.last_accrued);
+        trust_line.last_accrued = now;
+
+        if trust_line.balance == 0 || trust_line.interest_rate_bps == 0 || elapsed == 0 {
+            return Ok(());
+        }
+
+        let interest = trust_line.balance.abs()
+            .checked_mul(trust_line.interest_rate_bps as i128)
+            .and_then(|v| v.checked_mul(elapsed as i128))
+            .ok_or(Error::InvalidAmount)?
+            / (10_000 * SECONDS_PER_YEAR as i128);
+
+        // Interest grows the debt in its existing direction, up to whatever headroom is left
+        // under the debtor's limit. A balance already past a lowered limit is left as is.
+        let limit = if trust_line.balance > 0 { trust_line.limit2 } else { trust_line.limit1 };
+        let interest = interest.min((limit - trust_line.balance.abs()).max(0));
+        trust_line.balance = if trust_line.balance > 0 {
+            trust_line.balance + interest
+        } else {
+            trust_line.balance - interest
+        };
+
+        Ok(())
+    }
+
+    /// Set the maximum number of hops in `send_through_path` (admin only)
+    pub fn set_max_path_length(env: Env, max_path_length: u32) -> Result<(), Error> {
+        let admin: Address = env.storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .ok_or(Error::Unauthorized)?;
+        admin.require_auth();
+
+        if max_path_length == 0 || max_path_length > MAX_PATH_LENGTH_CAP {
+            return Err(Error::PathTooLong);
+        }
+
+        env.storage().instance().set(&DataKey::MaxPathLength, &max_path_length);
+        Ok(())
+    }
+
+    /// Get the maximum number of hops in `send_through_path`
+    pub fn get_max_path_length(env: Env) -> u32 {
+        env.storage()
+            .instance()
+            .get(&DataKey::MaxPathLength)
+            .unwrap_or(DEFAULT_MAX_PATH_LENGTH)
+    }
+
     /// Close trust line (must have zero balance)
     pub fn close_trust_line(
         env: Env,
@@ -376,6 +658,68 @@ impl TrustLinesContract {
         Ok(())
     }
 
+    /// Freeze or unfreeze a trust line (account1 only, like `set_rippling`)
+    pub fn set_frozen(
+        env: Env,
+        counterparty: Address,
+        asset: Address,
+        frozen: bool,
+    ) -> Result<(), Error> {
+        let caller = env.invoker();
+        caller.require_auth();
+
+        let (account1, account2) = Self::order_accounts(&caller, &counterparty);
+        if caller != account1 {
+            return Err(Error::Unauthorized);
+        }
+
+        Self::write_frozen(&env, account1, account2, asset, frozen)
+    }
+
+    /// Freeze or unfreeze any trust line (admin only, for compliance holds)
+    pub fn admin_set_frozen(
+        env: Env,
+        account1: Address,
+        account2: Address,
+        asset: Address,
+        frozen: bool,
+    ) -> Result<(), Error> {
+        let admin: Address = env.storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .ok_or(Error::Unauthorized)?;
+        admin.require_auth();
+
+        let (account1, account2) = Self::order_accounts(&account1, &account2);
+        Self::write_frozen(&env, account1, account2, asset, frozen)
+    }
+
+    // Helper: Persist the frozen flag on an existing line
+    fn write_frozen(
+        env: &Env,
+        account1: Address,
+        account2: Address,
+        asset: Address,
+        frozen: bool,
+    ) -> Result<(), Error> {
+        let key = DataKey::TrustLine(account1.clone(), account2.clone(), asset.clone());
+
+        let mut trust_line: TrustLine = env.storage()
+            .persistent()
+            .get(&key)
+            .ok_or(Error::NotFound)?;
+
+        trust_line.frozen = frozen;
+        env.storage().persistent().set(&key, &trust_line);
+
+        env.events().publish(
+            (symbol_short!("frozen"), account1, account2),
+            (asset, frozen),
+        );
+
+        Ok(())
+    }
+
     /// Get trust line details
     pub fn get_trust_line(
         env: Env,
@@ -424,7 +768,29 @@ impl TrustLinesContract {
 #[cfg(test)]
 mod test {
     use super::*;
-    use soroban_sdk::{testutils::Address as _, Address, Env};
+    use soroban_sdk::{testutils::{Address as _, Ledger}, Address, Env};
+
+    /// Write a trust line directly, for setting up lines between non-invoking accounts
+    fn put_line(env: &Env, contract_id: &Address, a: &Address, b: &Address, asset: &Address, limit: i128) {
+        let (account1, account2) = if a < b { (a.clone(), b.clone()) } else { (b.clone(), a.clone()) };
+        let line = TrustLine {
+            account1: account1.clone(),
+            account2: account2.clone(),
+            asset: asset.clone(),
+            limit1: limit,
+            limit2: limit,
+            balance: 0,
+            allow_rippling: true,
+            quality_in: 1000,
+            quality_out: 1000,
+            frozen: false,
+            interest_rate_bps: 0,
+            last_accrued: env.ledger().timestamp(),
+        };
+        env.as_contract(contract_id, || {
+            env.storage().persistent().set(&DataKey::TrustLine(account1, account2, asset.clone()), &line);
+        });
+    }
 
     #[test]
     fn test_create_trust_line() {
@@ -480,4 +846,315 @@ mod test {
         client.create_trust_line(&bob, &asset, &100, &true);
         client.send_payment(&bob, &asset, &200); // Should panic
     }
+
+    #[test]
+    fn test_frozen_line_blocks_payment() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, TrustLinesContract);
+        let client = TrustLinesContractClient::new(&env, &contract_id);
+
+        let admin = Address::generate(&env);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let asset = Address::generate(&env);
+
+        env.mock_all_auths();
+
+        client.initialize(&admin);
+        client.create_trust_line(&bob, &asset, &1000, &true);
+        client.send_payment(&bob, &asset, &100);
+
+        client.admin_set_frozen(&alice, &bob, &asset, &true);
+        let result = client.try_send_payment(&bob, &asset, &100);
+        assert_eq!(result, Err(Ok(Error::Frozen)));
+        assert_eq!(client.get_available_credit(&alice, &bob, &asset), 900);
+
+        client.admin_set_frozen(&alice, &bob, &asset, &false);
+        client.send_payment(&bob, &asset, &100);
+        assert_eq!(client.get_available_credit(&alice, &bob, &asset), 800);
+    }
+
+    #[test]
+    fn test_settle_net_offsetting_lines() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, TrustLinesContract);
+        let client = TrustLinesContractClient::new(&env, &contract_id);
+
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let usd = Address::generate(&env);
+        let eur = Address::generate(&env);
+        let settlement = Address::generate(&env);
+
+        env.mock_all_auths();
+
+        client.create_trust_line(&bob, &usd, &1000, &true);
+        client.create_trust_line(&bob, &eur, &1000, &true);
+
+        // Seed offsetting balances: +500 on one line, -300 on the other
+        env.as_contract(&contract_id, || {
+            for (asset, balance) in [(usd.clone(), 500i128), (eur.clone(), -300i128)] {
+                let (account1, account2) = if alice < bob { (alice.clone(), bob.clone()) } else { (bob.clone(), alice.clone()) };
+                let key = DataKey::TrustLine(account1, account2, asset);
+                let mut line: TrustLine = env.storage().persistent().get(&key).unwrap();
+                line.balance = balance;
+                env.storage().persistent().set(&key, &line);
+            }
+        });
+
+        let assets = soroban_sdk::vec![&env, usd.clone(), eur.clone()];
+        let amounts = soroban_sdk::vec![&env, 500i128, -300i128];
+        let net = client.settle_net(&bob, &assets, &settlement, &amounts);
+
+        assert_eq!(net, 200);
+        assert_eq!(client.get_trust_line(&alice, &bob, &usd).unwrap().balance, 0);
+        assert_eq!(client.get_trust_line(&alice, &bob, &eur).unwrap().balance, 0);
+        assert_eq!(client.get_settlement_obligation(&alice, &bob, &settlement), 200);
+
+        // A valuation that flips the direction of a debt is rejected
+        let bad_amounts = soroban_sdk::vec![&env, -100i128];
+        let result = client.try_settle_net(&bob, &soroban_sdk::vec![&env, usd.clone()], &settlement, &bad_amounts);
+        assert_eq!(result, Err(Ok(Error::InvalidSettlement)));
+    }
+
+    #[test]
+    fn test_cyclic_path_rejected() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, TrustLinesContract);
+        let client = TrustLinesContractClient::new(&env, &contract_id);
+
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let carol = Address::generate(&env);
+        let asset = Address::generate(&env);
+
+        env.mock_all_auths();
+
+        put_line(&env, &contract_id, &alice, &bob, &asset, 1000);
+        put_line(&env, &contract_id, &bob, &carol, &asset, 1000);
+
+        // Back to the caller
+        let result = client.try_send_through_path(&soroban_sdk::vec![&env, bob.clone(), alice.clone()], &asset, &100);
+        assert_eq!(result, Err(Ok(Error::InvalidPath)));
+
+        // Repeated intermediate account
+        let result = client.try_send_through_path(&soroban_sdk::vec![&env, bob.clone(), carol.clone(), bob.clone()], &asset, &100);
+        assert_eq!(result, Err(Ok(Error::InvalidPath)));
+
+        assert_eq!(client.get_trust_line(&alice, &bob, &asset).unwrap().balance, 0);
+    }
+
+    #[test]
+    fn test_acyclic_three_hop_path() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, TrustLinesContract);
+        let client = TrustLinesContractClient::new(&env, &contract_id);
+
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let carol = Address::generate(&env);
+        let dave = Address::generate(&env);
+        let asset = Address::generate(&env);
+
+        env.mock_all_auths();
+
+        put_line(&env, &contract_id, &alice, &bob, &asset, 1000);
+        put_line(&env, &contract_id, &bob, &carol, &asset, 1000);
+        put_line(&env, &contract_id, &carol, &dave, &asset, 1000);
+
+        client.send_through_path(&soroban_sdk::vec![&env, bob.clone(), carol.clone(), dave.clone()], &asset, &100);
+
+        assert_eq!(client.get_trust_line(&alice, &bob, &asset).unwrap().balance.abs(), 100);
+        assert_eq!(client.get_trust_line(&bob, &carol, &asset).unwrap().balance.abs(), 100);
+        assert_eq!(client.get_trust_line(&carol, &dave, &asset).unwrap().balance.abs(), 100);
+    }
+
+    #[test]
+    fn test_path_failure_leaves_no_mutations() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, TrustLinesContract);
+        let client = TrustLinesContractClient::new(&env, &contract_id);
+
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let carol = Address::generate(&env);
+        let dave = Address::generate(&env);
+        let asset = Address::generate(&env);
+
+        env.mock_all_auths();
+
+        put_line(&env, &contract_id, &alice, &bob, &asset, 1000);
+        put_line(&env, &contract_id, &bob, &carol, &asset, 1000);
+        // Last hop can't carry the payment
+        put_line(&env, &contract_id, &carol, &dave, &asset, 50);
+
+        let result = client.try_send_through_path(&soroban_sdk::vec![&env, bob.clone(), carol.clone(), dave.clone()], &asset, &100);
+        assert_eq!(result, Err(Ok(Error::InsufficientCredit)));
+
+        assert_eq!(client.get_trust_line(&alice, &bob, &asset).unwrap().balance, 0);
+        assert_eq!(client.get_trust_line(&bob, &carol, &asset).unwrap().balance, 0);
+        assert_eq!(client.get_trust_line(&carol, &dave, &asset).unwrap().balance, 0);
+    }
+
+    #[test]
+    fn test_interest_accrual() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, TrustLinesContract);
+        let client = TrustLinesContractClient::new(&env, &contract_id);
+
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let asset = Address::generate(&env);
+
+        env.mock_all_auths();
+
+        put_line(&env, &contract_id, &alice, &bob, &asset, 5000);
+        client.set_interest_rate(&bob, &asset, &1000); // 10% per year
+        client.send_payment(&bob, &asset, &1000);
+
+        // One year at 10% on 1000
+        env.ledger().with_mut(|li| li.timestamp += 31_536_000);
+        assert_eq!(client.accrue_interest(&bob, &asset), 100);
+        assert_eq!(client.get_trust_line(&alice, &bob, &asset).unwrap().balance.abs(), 1100);
+
+        // Half a year at 10% on 1100
+        env.ledger().with_mut(|li| li.timestamp += 15_768_000);
+        assert_eq!(client.accrue_interest(&bob, &asset), 55);
+        assert_eq!(client.get_trust_line(&alice, &bob, &asset).unwrap().balance.abs(), 1155);
+
+        // Nothing more accrues without time passing
+        assert_eq!(client.accrue_interest(&bob, &asset), 0);
+    }
+
+    #[test]
+    fn test_interest_never_reduces_debt_past_lowered_limit() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, TrustLinesContract);
+        let client = TrustLinesContractClient::new(&env, &contract_id);
+
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let asset = Address::generate(&env);
+
+        env.mock_all_auths();
+
+        put_line(&env, &contract_id, &alice, &bob, &asset, 5000);
+        client.set_interest_rate(&bob, &asset, &1000); // 10% per year
+        client.send_payment(&bob, &asset, &1000);
+
+        // Both limits drop below the outstanding debt
+        let key = {
+            let line = client.get_trust_line(&alice, &bob, &asset).unwrap();
+            DataKey::TrustLine(line.account1, line.account2, asset.clone())
+        };
+        env.as_contract(&contract_id, || {
+            let mut line: TrustLine = env.storage().persistent().get(&key).unwrap();
+            line.limit1 = 500;
+            line.limit2 = 500;
+            env.storage().persistent().set(&key, &line);
+        });
+
+        // No headroom is left, so nothing accrues and the debt is not cut to the limit
+        env.ledger().with_mut(|li| li.timestamp += 31_536_000);
+        assert_eq!(client.accrue_interest(&bob, &asset), 0);
+        assert_eq!(client.get_trust_line(&alice, &bob, &asset).unwrap().balance.abs(), 1000);
+
+        // With 50 of headroom only 50 of the 100 owed is added
+        env.as_contract(&contract_id, || {
+            let mut line: TrustLine = env.storage().persistent().get(&key).unwrap();
+            line.limit1 = 1050;
+            line.limit2 = 1050;
+            env.storage().persistent().set(&key, &line);
+        });
+        env.ledger().with_mut(|li| li.timestamp += 31_536_000);
+        assert_eq!(client.accrue_interest(&bob, &asset), 50);
+        assert_eq!(client.get_trust_line(&alice, &bob, &asset).unwrap().balance.abs(), 1050);
+    }
+
+    #[test]
+    fn test_path_payment_accrues_before_changing_balance() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, TrustLinesContract);
+        let client = TrustLinesContractClient::new(&env, &contract_id);
+
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let asset = Address::generate(&env);
+
+        env.mock_all_auths();
+
+        put_line(&env, &contract_id, &alice, &bob, &asset, 5000);
+        client.set_interest_rate(&bob, &asset, &1000); // 10% per year
+
+        // A year passes on a zero balance, then a path payment opens a debt
+        env.ledger().with_mut(|li| li.timestamp += 31_536_000);
+        client.send_through_path(&soroban_sdk::vec![&env, bob.clone()], &asset, &1000);
+
+        // No interest is owed for the year before the debt existed
+        assert_eq!(client.accrue_interest(&bob, &asset), 0);
+        assert_eq!(client.get_trust_line(&alice, &bob, &asset).unwrap().balance.abs(), 1000);
+    }
+
+    #[test]
+    fn test_settle_net_accrues_before_zeroing() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, TrustLinesContract);
+        let client = TrustLinesContractClient::new(&env, &contract_id);
+
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let asset = Address::generate(&env);
+        let settlement = Address::generate(&env);
+
+        env.mock_all_auths();
+
+        put_line(&env, &contract_id, &alice, &bob, &asset, 5000);
+        client.set_interest_rate(&bob, &asset, &1000); // 10% per year
+        client.send_payment(&bob, &asset, &1000);
+
+        // A year later the line is netted out, then a new debt is opened
+        env.ledger().with_mut(|li| li.timestamp += 31_536_000);
+        let balance = client.get_trust_line(&alice, &bob, &asset).unwrap().balance;
+        client.settle_net(&bob, &soroban_sdk::vec![&env, asset.clone()], &settlement, &soroban_sdk::vec![&env, balance]);
+        client.send_payment(&bob, &asset, &500);
+
+        // The year before netting is not charged on the new balance
+        assert_eq!(client.accrue_interest(&bob, &asset), 0);
+        assert_eq!(client.get_trust_line(&alice, &bob, &asset).unwrap().balance.abs(), 500);
+    }
+
+    #[test]
+    fn test_configurable_max_path_length() {
+        let env = Env::default();
+        let contract_id = env.register_contract(None, TrustLinesContract);
+        let client = TrustLinesContractClient::new(&env, &contract_id);
+
+        let admin = Address::generate(&env);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let carol = Address::generate(&env);
+        let dave = Address::generate(&env);
+        let erin = Address::generate(&env);
+        let asset = Address::generate(&env);
+
+        env.mock_all_auths();
+
+        client.initialize(&admin);
+        assert_eq!(client.get_max_path_length(), 6);
+        assert_eq!(client.try_set_max_path_length(&0), Err(Ok(Error::PathTooLong)));
+        assert_eq!(client.try_set_max_path_length(&13), Err(Ok(Error::PathTooLong)));
+        client.set_max_path_length(&3);
+
+        put_line(&env, &contract_id, &alice, &bob, &asset, 1000);
+        put_line(&env, &contract_id, &bob, &carol, &asset, 1000);
+        put_line(&env, &contract_id, &carol, &dave, &asset, 1000);
+        put_line(&env, &contract_id, &dave, &erin, &asset, 1000);
+
+        let four_hops = soroban_sdk::vec![&env, bob.clone(), carol.clone(), dave.clone(), erin.clone()];
+        assert_eq!(client.try_send_through_path(&four_hops, &asset, &100), Err(Ok(Error::PathTooLong)));
+
+        let three_hops = soroban_sdk::vec![&env, bob.clone(), carol.clone(), dave.clone()];
+        client.send_through_path(&three_hops, &asset, &100);
+        assert_eq!(client.get_trust_line(&carol, &dave, &asset).unwrap().balance.abs(), 100);
+    }
 }
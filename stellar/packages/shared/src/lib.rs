@@ -0,0 +1,211 @@
+#![no_std]
+
+//! Shared helpers for the Soroban contracts
+//!
+//! Off-chain signed messages are built here so every contract serializes them
+//! identically.
+
+use soroban_sdk::{xdr::ToXdr, Address, Bytes, BytesN, Env};
+
+/// Ed25519 group order `L`, little-endian
+const GROUP_ORDER: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+/// Field prime `2^255 - 19`, little-endian
+const FIELD_PRIME: [u8; 32] = [
+    0xed, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f,
+];
+
+/// Message a channel sender signs to authorize a cumulative claim:
+/// `contract (Address XDR) || channel_id (u64 BE) || amount (i128 BE) || nonce (u64 BE)`
+///
+/// The contract address binds the claim to one deployment, so a signature cannot be
+/// replayed against another contract that reuses the same channel ids.
+pub fn claim_message(env: &Env, contract: &Address, channel_id: u64, amount: i128, nonce: u64) -> Bytes {
+    let mut message = contract.clone().to_xdr(env);
+    message.append(&Bytes::from_array(env, &channel_id.to_be_bytes()));
+    message.append(&Bytes::from_array(env, &amount.to_be_bytes()));
+    message.append(&Bytes::from_array(env, &nonce.to_be_bytes()));
+    message
+}
+
+/// Verifies `signature` by `public_key` over `claim_message(contract, channel_id, amount, nonce)`
+/// with the host's `ed25519_verify`.
+///
+/// Returns `false` without calling the host when the key or signature is not canonically
+/// encoded, so callers can reject malformed claims with their own error. A well-formed
+/// signature that does not verify traps in the host.
+pub fn verify_claim(
+    env: &Env,
+    contract: &Address,
+    public_key: &BytesN<32>,
+    channel_id: u64,
+    amount: i128,
+    nonce: u64,
+    signature: &BytesN<64>,
+) -> bool {
+    if !is_canonical_key(&public_key.to_array()) || !is_canonical_signature(&signature.to_array()) {
+        return false;
+    }
+
+    let message = claim_message(env, contract, channel_id, amount, nonce);
+    env.crypto().ed25519_verify(public_key, &message, signature);
+    true
+}
+
+/// Whether the key's y coordinate (the low 255 bits) is below the field prime
+fn is_canonical_key(key: &[u8; 32]) -> bool {
+    let mut y = *key;
+    y[31] &= 0x7f;
+    less_than_le(&y, &FIELD_PRIME)
+}
+
+/// Whether the signature's `S` half is below the group order, as RFC 8032 requires
+fn is_canonical_signature(signature: &[u8; 64]) -> bool {
+    let mut s = [0u8; 32];
+    s.copy_from_slice(&signature[32..]);
+    less_than_le(&s, &GROUP_ORDER)
+}
+
+/// `a < b` for little-endian 256-bit integers
+fn less_than_le(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    for i in (0..32).rev() {
+        if a[i] != b[i] {
+            return a[i] < b[i];
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use soroban_sdk::String;
+
+    const CHANNEL_ID: u64 = 1;
+    const AMOUNT: i128 = 500;
+    const NONCE: u64 = 7;
+    /// Contract id `[1u8; 32]`
+    const CONTRACT: &str = "CAAQCAIBAEAQCAIBAEAQCAIBAEAQCAIBAEAQCAIBAEAQCAIBAEAQC526";
+
+    /// Signature by `signing_key()` over the golden claim message
+    const GOLDEN_SIGNATURE: [u8; 64] = [
+        0x1c, 0x63, 0x03, 0xcb, 0x58, 0x01, 0xca, 0x7e, 0x1d, 0x69, 0x26, 0xc9, 0x1b, 0xbc, 0x4a, 0xd7,
+        0xdb, 0x81, 0x42, 0xbe, 0x09, 0xa0, 0xd6, 0x6d, 0x23, 0x62, 0x8e, 0xd5, 0xab, 0x7d, 0xc4, 0x28,
+        0x0f, 0x4d, 0x7b, 0x53, 0x4d, 0xbf, 0xa0, 0xc2, 0xc0, 0xd9, 0x42, 0xa7, 0xbd, 0x77, 0x6a, 0x40,
+        0x7f, 0x06, 0x3b, 0xea, 0x64, 0xd4, 0x02, 0x3f, 0x45, 0x2c, 0x25, 0xb2, 0x9e, 0xa7, 0x4f, 0x02,
+    ];
+
+    fn contract(env: &Env) -> Address {
+        Address::from_string(&String::from_str(env, CONTRACT))
+    }
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    fn public_key(env: &Env) -> BytesN<32> {
+        BytesN::from_array(env, &signing_key().verifying_key().to_bytes())
+    }
+
+    fn sign(env: &Env, channel_id: u64, amount: i128, nonce: u64) -> BytesN<64> {
+        let message = claim_message(env, &contract(env), channel_id, amount, nonce);
+        let mut buf = [0u8; 72];
+        message.copy_into_slice(&mut buf);
+        BytesN::from_array(env, &signing_key().sign(&buf).to_bytes())
+    }
+
+    #[test]
+    fn test_claim_message_layout() {
+        let env = Env::default();
+        let mut expected = [0u8; 72];
+        expected[3] = 18; // ScVal::Address
+        expected[7] = 1; // ScAddress::Contract
+        expected[8..40].copy_from_slice(&[1u8; 32]); // contract id
+        expected[40..48].copy_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]); // channel_id
+        expected[62..64].copy_from_slice(&[0x01, 0xf4]); // amount
+        expected[64..72].copy_from_slice(&[0, 0, 0, 0, 0, 0, 0, 7]); // nonce
+        assert_eq!(
+            claim_message(&env, &contract(&env), CHANNEL_ID, AMOUNT, NONCE),
+            Bytes::from_array(&env, &expected)
+        );
+    }
+
+    #[test]
+    fn test_golden_signature() {
+        let env = Env::default();
+        let signature = BytesN::from_array(&env, &GOLDEN_SIGNATURE);
+        assert_eq!(sign(&env, CHANNEL_ID, AMOUNT, NONCE), signature);
+        assert!(verify_claim(&env, &contract(&env), &public_key(&env), CHANNEL_ID, AMOUNT, NONCE, &signature));
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Crypto, InvalidInput)")]
+    fn test_verify_claim_rejects_tampered_contract() {
+        let env = Env::default();
+        let signature = sign(&env, CHANNEL_ID, AMOUNT, NONCE);
+        let other = Address::from_string(&String::from_str(&env, "CABAEAQCAIBAEAQCAIBAEAQCAIBAEAQCAIBAEAQCAIBAEAQCAIBAFNSZ"));
+        assert!(!verify_claim(&env, &other, &public_key(&env), CHANNEL_ID, AMOUNT, NONCE, &signature));
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Crypto, InvalidInput)")]
+    fn test_verify_claim_rejects_tampered_channel() {
+        let env = Env::default();
+        let signature = sign(&env, CHANNEL_ID, AMOUNT, NONCE);
+        assert!(!verify_claim(&env, &contract(&env), &public_key(&env), CHANNEL_ID + 1, AMOUNT, NONCE, &signature));
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Crypto, InvalidInput)")]
+    fn test_verify_claim_rejects_tampered_amount() {
+        let env = Env::default();
+        let signature = sign(&env, CHANNEL_ID, AMOUNT, NONCE);
+        assert!(!verify_claim(&env, &contract(&env), &public_key(&env), CHANNEL_ID, AMOUNT + 1, NONCE, &signature));
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Crypto, InvalidInput)")]
+    fn test_verify_claim_rejects_tampered_nonce() {
+        let env = Env::default();
+        let signature = sign(&env, CHANNEL_ID, AMOUNT, NONCE);
+        assert!(!verify_claim(&env, &contract(&env), &public_key(&env), CHANNEL_ID, AMOUNT, NONCE + 1, &signature));
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Crypto, InvalidInput)")]
+    fn test_verify_claim_rejects_other_key() {
+        let env = Env::default();
+        let signature = sign(&env, CHANNEL_ID, AMOUNT, NONCE);
+        let other = SigningKey::from_bytes(&[8u8; 32]);
+        let other_key = BytesN::from_array(&env, &other.verifying_key().to_bytes());
+        assert!(!verify_claim(&env, &contract(&env), &other_key, CHANNEL_ID, AMOUNT, NONCE, &signature));
+    }
+
+    #[test]
+    fn test_verify_claim_rejects_non_canonical_signature() {
+        let env = Env::default();
+        // `S + L` verifies under lax implementations but is not a canonical encoding
+        let mut malleated = GOLDEN_SIGNATURE;
+        let mut carry = 0u16;
+        for i in 0..32 {
+            let sum = malleated[32 + i] as u16 + GROUP_ORDER[i] as u16 + carry;
+            malleated[32 + i] = sum as u8;
+            carry = sum >> 8;
+        }
+        let signature = BytesN::from_array(&env, &malleated);
+        assert!(!verify_claim(&env, &contract(&env), &public_key(&env), CHANNEL_ID, AMOUNT, NONCE, &signature));
+    }
+
+    #[test]
+    fn test_verify_claim_rejects_non_canonical_key() {
+        let env = Env::default();
+        let signature = BytesN::from_array(&env, &GOLDEN_SIGNATURE);
+        let key = BytesN::from_array(&env, &[0xff; 32]);
+        assert!(!verify_claim(&env, &contract(&env), &key, CHANNEL_ID, AMOUNT, NONCE, &signature));
+    }
+}